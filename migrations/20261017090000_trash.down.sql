@@ -0,0 +1,6 @@
+DROP TRIGGER IF EXISTS book_after_soft_delete;
+DROP TRIGGER IF EXISTS book_after_restore;
+DROP TRIGGER IF EXISTS author_after_soft_delete;
+DROP TRIGGER IF EXISTS author_after_restore;
+
+DROP TABLE IF EXISTS setting;
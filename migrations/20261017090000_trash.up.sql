@@ -0,0 +1,39 @@
+-- Cascade soft deletes from books to their quotes and notes,
+-- using the same timestamp so they can be restored together.
+DROP TRIGGER IF EXISTS book_after_soft_delete;
+CREATE TRIGGER book_after_soft_delete AFTER UPDATE OF deleted_at ON book
+WHEN old.deleted_at IS NULL AND new.deleted_at IS NOT NULL
+BEGIN
+    UPDATE quote SET deleted_at = new.deleted_at WHERE book_id = new.id AND deleted_at IS NULL;
+    UPDATE note SET deleted_at = new.deleted_at WHERE book_id = new.id AND deleted_at IS NULL;
+END;
+
+-- Restore the quotes and notes hidden with the book
+DROP TRIGGER IF EXISTS book_after_restore;
+CREATE TRIGGER book_after_restore AFTER UPDATE OF deleted_at ON book
+WHEN old.deleted_at IS NOT NULL AND new.deleted_at IS NULL
+BEGIN
+    UPDATE quote SET deleted_at = NULL WHERE book_id = new.id AND deleted_at = old.deleted_at;
+    UPDATE note SET deleted_at = NULL WHERE book_id = new.id AND deleted_at = old.deleted_at;
+END;
+
+-- Same for authors and their books
+DROP TRIGGER IF EXISTS author_after_soft_delete;
+CREATE TRIGGER author_after_soft_delete AFTER UPDATE OF deleted_at ON author
+WHEN old.deleted_at IS NULL AND new.deleted_at IS NOT NULL
+BEGIN
+    UPDATE book SET deleted_at = new.deleted_at WHERE author_id = new.id AND deleted_at IS NULL;
+END;
+
+DROP TRIGGER IF EXISTS author_after_restore;
+CREATE TRIGGER author_after_restore AFTER UPDATE OF deleted_at ON author
+WHEN old.deleted_at IS NOT NULL AND new.deleted_at IS NULL
+BEGIN
+    UPDATE book SET deleted_at = NULL WHERE author_id = new.id AND deleted_at = old.deleted_at;
+END;
+
+-- Key/value application settings
+CREATE TABLE IF NOT EXISTS setting (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT
+);
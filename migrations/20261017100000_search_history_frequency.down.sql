@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_search_history_timestamp;
+DROP INDEX IF EXISTS idx_search_history_query;
+ALTER TABLE search_history DROP COLUMN frequency;
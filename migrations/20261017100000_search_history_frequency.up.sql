@@ -0,0 +1,16 @@
+-- Keep a single entry per query, counting how many times it was searched
+ALTER TABLE search_history ADD COLUMN frequency INTEGER NOT NULL DEFAULT 1;
+
+UPDATE search_history
+SET frequency = (
+    SELECT COUNT(*) FROM search_history sh WHERE sh.query = search_history.query
+),
+timestamp = (
+    SELECT MAX(sh.timestamp) FROM search_history sh WHERE sh.query = search_history.query
+);
+
+DELETE FROM search_history
+WHERE id NOT IN (SELECT MAX(id) FROM search_history GROUP BY query);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_search_history_query ON search_history(query);
+CREATE INDEX IF NOT EXISTS idx_search_history_timestamp ON search_history(timestamp);
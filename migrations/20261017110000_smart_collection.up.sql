@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS smart_collection (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    -- JSON serialized filter definition
+    filter TEXT NOT NULL DEFAULT '{}',
+    sort_by TEXT,
+    sort_order TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
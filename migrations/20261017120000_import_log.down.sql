@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_import_log_created_at;
+DROP TABLE IF EXISTS import_log;
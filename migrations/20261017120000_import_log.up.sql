@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS import_log (
+    id TEXT PRIMARY KEY NOT NULL,
+    -- The source imported from, e.g. kobo or kindle
+    source TEXT NOT NULL,
+    path TEXT NOT NULL,
+    -- JSON serialized import report
+    report TEXT NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_log_created_at ON import_log(created_at);
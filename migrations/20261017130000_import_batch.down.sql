@@ -0,0 +1,18 @@
+DROP INDEX IF EXISTS idx_author_import_batch_id;
+DROP INDEX IF EXISTS idx_book_import_batch_id;
+DROP INDEX IF EXISTS idx_chapter_import_batch_id;
+DROP INDEX IF EXISTS idx_quote_import_batch_id;
+DROP INDEX IF EXISTS idx_note_import_batch_id;
+DROP INDEX IF EXISTS idx_tag_import_batch_id;
+
+ALTER TABLE author DROP COLUMN import_batch_id;
+ALTER TABLE book DROP COLUMN import_batch_id;
+ALTER TABLE chapter DROP COLUMN import_batch_id;
+ALTER TABLE quote DROP COLUMN import_batch_id;
+ALTER TABLE note DROP COLUMN import_batch_id;
+ALTER TABLE tag DROP COLUMN import_batch_id;
+
+DROP INDEX IF EXISTS idx_import_batch_created_at;
+ALTER TABLE import_batch DROP COLUMN file_hash;
+ALTER TABLE import_batch RENAME TO import_log;
+CREATE INDEX IF NOT EXISTS idx_import_log_created_at ON import_log(created_at);
@@ -0,0 +1,22 @@
+-- Every import is a batch that can be rolled back
+ALTER TABLE import_log RENAME TO import_batch;
+-- SHA-256 of the imported file, NULL for directories
+ALTER TABLE import_batch ADD COLUMN file_hash TEXT;
+
+DROP INDEX IF EXISTS idx_import_log_created_at;
+CREATE INDEX IF NOT EXISTS idx_import_batch_created_at ON import_batch(created_at);
+
+-- The batch that created the row, NULL for rows added by hand
+ALTER TABLE author ADD COLUMN import_batch_id TEXT;
+ALTER TABLE book ADD COLUMN import_batch_id TEXT;
+ALTER TABLE chapter ADD COLUMN import_batch_id TEXT;
+ALTER TABLE quote ADD COLUMN import_batch_id TEXT;
+ALTER TABLE note ADD COLUMN import_batch_id TEXT;
+ALTER TABLE tag ADD COLUMN import_batch_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_author_import_batch_id ON author(import_batch_id);
+CREATE INDEX IF NOT EXISTS idx_book_import_batch_id ON book(import_batch_id);
+CREATE INDEX IF NOT EXISTS idx_chapter_import_batch_id ON chapter(import_batch_id);
+CREATE INDEX IF NOT EXISTS idx_quote_import_batch_id ON quote(import_batch_id);
+CREATE INDEX IF NOT EXISTS idx_note_import_batch_id ON note(import_batch_id);
+CREATE INDEX IF NOT EXISTS idx_tag_import_batch_id ON tag(import_batch_id);
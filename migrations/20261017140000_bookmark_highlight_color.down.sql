@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS idx_bookmark_book_id;
+DROP INDEX IF EXISTS idx_bookmark_original_id;
+DROP INDEX IF EXISTS idx_bookmark_import_batch_id;
+DROP TABLE IF EXISTS bookmark;
+
+DROP INDEX IF EXISTS idx_quote_color;
+ALTER TABLE quote DROP COLUMN color;
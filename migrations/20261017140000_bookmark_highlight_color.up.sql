@@ -0,0 +1,24 @@
+-- Highlight color, a color name such as yellow or a hex code for custom colors
+ALTER TABLE quote ADD COLUMN color TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_quote_color ON quote(color);
+
+-- Positions saved in a book without any highlighted text
+CREATE TABLE IF NOT EXISTS bookmark (
+    id TEXT PRIMARY KEY NOT NULL,
+    book_id TEXT,
+    chapter_id TEXT,
+    chapter_progress REAL,
+    -- bookmark, or dogear for Kobo dog-ears
+    kind TEXT NOT NULL DEFAULT 'bookmark',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    original_id TEXT,
+    import_batch_id TEXT,
+    FOREIGN KEY (book_id) REFERENCES book(id),
+    FOREIGN KEY (chapter_id) REFERENCES chapter(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_bookmark_book_id ON bookmark(book_id);
+CREATE INDEX IF NOT EXISTS idx_bookmark_original_id ON bookmark(original_id);
+CREATE INDEX IF NOT EXISTS idx_bookmark_import_batch_id ON bookmark(import_batch_id);
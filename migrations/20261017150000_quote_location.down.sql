@@ -0,0 +1,4 @@
+ALTER TABLE quote DROP COLUMN location_end;
+ALTER TABLE quote DROP COLUMN location_start;
+ALTER TABLE quote DROP COLUMN page_end;
+ALTER TABLE quote DROP COLUMN page_start;
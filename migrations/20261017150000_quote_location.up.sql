@@ -0,0 +1,5 @@
+-- Page and location ranges of a quote, as given by Kindle clippings
+ALTER TABLE quote ADD COLUMN page_start INTEGER;
+ALTER TABLE quote ADD COLUMN page_end INTEGER;
+ALTER TABLE quote ADD COLUMN location_start INTEGER;
+ALTER TABLE quote ADD COLUMN location_end INTEGER;
@@ -0,0 +1,610 @@
+use crate::export::ExportError;
+use crate::models::{Note, QuoteFilter, QuoteWithTagsRedux};
+use crate::queries;
+
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqliteConnection};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder};
+
+/// Anki separates note fields with the unit separator.
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// Alphabet used by Anki to encode note GUIDs.
+const BASE91_TABLE: &[u8] =
+    b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!#$%&()*+,-./:;<=>?@[]^_`{|}~";
+
+/// Fixed ids so that re-imports reuse the same note types.
+const QUOTE_MODEL_ID: i64 = 1_700_000_000_001;
+const CLOZE_MODEL_ID: i64 = 1_700_000_000_002;
+
+const DEFAULT_DECK_ID: i64 = 1;
+const UNKNOWN_AUTHOR: &str = "Unknown Author";
+const UNKNOWN_BOOK: &str = "Unknown Book";
+
+const CARD_CSS: &str = ".card {\n font-family: Georgia, serif;\n font-size: 20px;\n text-align: center;\n color: black;\n background-color: white;\n}\n.source {\n font-size: 16px;\n color: #666;\n}\n.note {\n font-size: 14px;\n font-style: italic;\n}\n.cloze {\n font-weight: bold;\n color: blue;\n}\n";
+
+/// Schema of an Anki collection, version 11.
+const COLLECTION_SCHEMA: &str = r#"
+CREATE TABLE col (
+    id      integer primary key,
+    crt     integer not null,
+    mod     integer not null,
+    scm     integer not null,
+    ver     integer not null,
+    dty     integer not null,
+    usn     integer not null,
+    ls      integer not null,
+    conf    text not null,
+    models  text not null,
+    decks   text not null,
+    dconf   text not null,
+    tags    text not null
+);
+CREATE TABLE notes (
+    id      integer primary key,
+    guid    text not null,
+    mid     integer not null,
+    mod     integer not null,
+    usn     integer not null,
+    tags    text not null,
+    flds    text not null,
+    sfld    integer not null,
+    csum    integer not null,
+    flags   integer not null,
+    data    text not null
+);
+CREATE TABLE cards (
+    id      integer primary key,
+    nid     integer not null,
+    did     integer not null,
+    ord     integer not null,
+    mod     integer not null,
+    usn     integer not null,
+    type    integer not null,
+    queue   integer not null,
+    due     integer not null,
+    ivl     integer not null,
+    factor  integer not null,
+    reps    integer not null,
+    lapses  integer not null,
+    left    integer not null,
+    odue    integer not null,
+    odid    integer not null,
+    flags   integer not null,
+    data    text not null
+);
+CREATE TABLE revlog (
+    id      integer primary key,
+    cid     integer not null,
+    usn     integer not null,
+    ease    integer not null,
+    ivl     integer not null,
+    lastIvl integer not null,
+    factor  integer not null,
+    time    integer not null,
+    type    integer not null
+);
+CREATE TABLE graves (
+    usn     integer not null,
+    oid     integer not null,
+    type    integer not null
+);
+CREATE INDEX ix_notes_usn ON notes (usn);
+CREATE INDEX ix_cards_usn ON cards (usn);
+CREATE INDEX ix_revlog_usn ON revlog (usn);
+CREATE INDEX ix_cards_nid ON cards (nid);
+CREATE INDEX ix_cards_sched ON cards (did, queue, due);
+CREATE INDEX ix_revlog_cid ON revlog (cid);
+CREATE INDEX ix_notes_csum ON notes (csum);
+"#;
+
+/// The kind of card generated for each quote.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnkiNoteType {
+    /// The quote on the front, book and author on the back.
+    #[default]
+    Quote,
+    /// The quote with its key word hidden.
+    Cloze,
+}
+
+/// A quote ready to be written as an Anki note.
+#[derive(Debug, Clone)]
+pub struct AnkiNote {
+    pub guid: String,
+    pub deck: String,
+    pub fields: Vec<String>,
+    pub sort_field: String,
+    pub tags: Vec<String>,
+}
+
+/// First 8 bytes of the SHA-256 of `key`, as an integer.
+fn hash_key(key: &str) -> u64 {
+    let digest = Sha256::digest(key.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// Stable GUID for a quote, encoded the way Anki encodes its own.
+///
+/// Re-exporting the same quote yields the same GUID, so Anki updates the existing
+/// note instead of adding a duplicate.
+pub fn anki_guid(quote_id: &str) -> String {
+    let mut n = hash_key(quote_id);
+    let mut guid = Vec::new();
+    while n > 0 {
+        guid.push(BASE91_TABLE[(n % BASE91_TABLE.len() as u64) as usize]);
+        n /= BASE91_TABLE.len() as u64;
+    }
+    guid.reverse();
+    String::from_utf8(guid).unwrap_or_default()
+}
+
+/// Stable positive id, small enough to survive a round trip through JavaScript.
+fn stable_id(key: &str) -> i64 {
+    ((hash_key(key) >> 12) as i64) + 2
+}
+
+/// Checksum of the sort field, used by Anki to find duplicates.
+fn field_checksum(field: &str) -> i64 {
+    let digest = Sha1::digest(field.as_bytes());
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) as i64
+}
+
+/// Escape text for an Anki field, which is HTML.
+fn escape_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\n' => html.push_str("<br>"),
+            '\r' | FIELD_SEPARATOR => {}
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+fn field_html(text: &str) -> String {
+    escape_html(text.trim())
+}
+
+/// Deck name component, Anki uses "::" to nest decks.
+fn deck_component(name: Option<&str>, fallback: &str) -> String {
+    let name = name.map(|n| n.trim().replace("::", ":")).unwrap_or_default();
+    if name.is_empty() {
+        fallback.to_string()
+    } else {
+        name
+    }
+}
+
+/// Anki tags are space separated, so spaces inside a tag become underscores.
+fn anki_tag(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// Hide the longest word of the quote behind a cloze deletion.
+/// Quotes without any word are hidden entirely.
+pub fn cloze_text(content: &str) -> String {
+    let text = content.trim();
+
+    // Byte range of the first longest word
+    let mut longest: Option<(usize, usize)> = None;
+    let mut word_start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (c.is_alphanumeric(), word_start) {
+            (true, None) => word_start = Some(i),
+            (false, Some(start)) => {
+                let len = text[start..i].chars().count();
+                if longest.is_none_or(|(s, e)| len > text[s..e].chars().count()) {
+                    longest = Some((start, i));
+                }
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    match longest {
+        Some((start, end)) => format!(
+            "{}{{{{c1::{}}}}}{}",
+            escape_html(&text[..start]),
+            &text[start..end],
+            escape_html(&text[end..])
+        ),
+        None => format!("{{{{c1::{}}}}}", escape_html(text)),
+    }
+}
+
+/// Turn quotes into Anki notes. Quotes without content are left out.
+pub fn quotes_to_notes(
+    quotes: &[QuoteWithTagsRedux],
+    notes: &[Note],
+    note_type: AnkiNoteType,
+) -> Vec<AnkiNote> {
+    let mut notes_by_quote: HashMap<&str, Vec<String>> = HashMap::new();
+    for note in notes {
+        if let (Some(quote_id), Some(content)) = (note.quote_id.as_deref(), note.content.as_deref()) {
+            if !content.trim().is_empty() {
+                notes_by_quote
+                    .entry(quote_id)
+                    .or_default()
+                    .push(field_html(content));
+            }
+        }
+    }
+
+    quotes
+        .iter()
+        .filter_map(|quote| {
+            let content = quote.content.as_deref().filter(|c| !c.trim().is_empty())?;
+            let author = deck_component(quote.author_name.as_deref(), UNKNOWN_AUTHOR);
+            let book = deck_component(quote.book_title.as_deref(), UNKNOWN_BOOK);
+            let note = notes_by_quote
+                .get(quote.id.as_str())
+                .map(|n| n.join("<br><br>"))
+                .unwrap_or_default();
+            let text = match note_type {
+                AnkiNoteType::Quote => field_html(content),
+                AnkiNoteType::Cloze => cloze_text(content),
+            };
+
+            let mut tags: Vec<String> = quote
+                .tags
+                .iter()
+                .map(|t| anki_tag(&t.name))
+                .filter(|t| !t.is_empty())
+                .collect();
+            tags.sort();
+            tags.dedup();
+
+            Some(AnkiNote {
+                guid: anki_guid(&quote.id),
+                fields: vec![
+                    text,
+                    field_html(quote.book_title.as_deref().unwrap_or_default()),
+                    field_html(quote.author_name.as_deref().unwrap_or_default()),
+                    note,
+                ],
+                sort_field: content.trim().to_string(),
+                deck: format!("{}::{}", author, book),
+                tags,
+            })
+        })
+        .collect()
+}
+
+fn model_fields(first: &str) -> Value {
+    let fields: Vec<Value> = [first, "Book", "Author", "Note"]
+        .iter()
+        .enumerate()
+        .map(|(ord, name)| {
+            json!({
+                "name": name,
+                "ord": ord,
+                "font": "Arial",
+                "size": 20,
+                "rtl": false,
+                "sticky": false,
+                "media": [],
+            })
+        })
+        .collect();
+    Value::Array(fields)
+}
+
+/// The note types, both share the Book, Author and Note fields.
+fn models_json(now: i64) -> Value {
+    let back = "<div class=\"source\">{{Book}}<br>{{Author}}</div>{{#Note}}<hr><div class=\"note\">{{Note}}</div>{{/Note}}";
+    let latex_pre = "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n";
+
+    json!({
+        QUOTE_MODEL_ID.to_string(): {
+            "id": QUOTE_MODEL_ID,
+            "name": "Liture Quote",
+            "type": 0,
+            "mod": now,
+            "usn": -1,
+            "sortf": 0,
+            "did": DEFAULT_DECK_ID,
+            "flds": model_fields("Quote"),
+            "tmpls": [{
+                "name": "Quote → Source",
+                "ord": 0,
+                "qfmt": "{{Quote}}",
+                "afmt": format!("{{{{FrontSide}}}}<hr id=\"answer\">{}", back),
+                "did": null,
+                "bqfmt": "",
+                "bafmt": "",
+            }],
+            "req": [[0, "all", [0]]],
+            "tags": [],
+            "vers": [],
+            "css": CARD_CSS,
+            "latexPre": latex_pre,
+            "latexPost": "\\end{document}",
+        },
+        CLOZE_MODEL_ID.to_string(): {
+            "id": CLOZE_MODEL_ID,
+            "name": "Liture Cloze",
+            "type": 1,
+            "mod": now,
+            "usn": -1,
+            "sortf": 0,
+            "did": DEFAULT_DECK_ID,
+            "flds": model_fields("Text"),
+            "tmpls": [{
+                "name": "Cloze",
+                "ord": 0,
+                "qfmt": "{{cloze:Text}}",
+                "afmt": format!("{{{{cloze:Text}}}}<hr id=\"answer\">{}", back),
+                "did": null,
+                "bqfmt": "",
+                "bafmt": "",
+            }],
+            "tags": [],
+            "vers": [],
+            "css": CARD_CSS,
+            "latexPre": latex_pre,
+            "latexPost": "\\end{document}",
+        },
+    })
+}
+
+fn deck_json(id: i64, name: &str, now: i64) -> Value {
+    json!({
+        "id": id,
+        "name": name,
+        "desc": "",
+        "mod": now,
+        "usn": -1,
+        "conf": 1,
+        "dyn": 0,
+        "collapsed": false,
+        "browserCollapsed": false,
+        "extendNew": 10,
+        "extendRev": 50,
+        "newToday": [0, 0],
+        "revToday": [0, 0],
+        "lrnToday": [0, 0],
+        "timeToday": [0, 0],
+    })
+}
+
+fn deck_config_json(now: i64) -> Value {
+    json!({
+        "1": {
+            "id": 1,
+            "name": "Default",
+            "mod": now,
+            "usn": 0,
+            "maxTaken": 60,
+            "autoplay": true,
+            "timer": 0,
+            "replayq": true,
+            "dyn": false,
+            "new": {
+                "bury": true,
+                "delays": [1, 10],
+                "initialFactor": 2500,
+                "ints": [1, 4, 7],
+                "order": 1,
+                "perDay": 20,
+                "separate": true,
+            },
+            "rev": {
+                "bury": true,
+                "ease4": 1.3,
+                "fuzz": 0.05,
+                "ivlFct": 1,
+                "maxIvl": 36500,
+                "minSpace": 1,
+                "perDay": 100,
+            },
+            "lapse": {
+                "delays": [10],
+                "leechAction": 0,
+                "leechFails": 8,
+                "minInt": 1,
+                "mult": 0,
+            },
+        }
+    })
+}
+
+/// Write the notes as an Anki collection in the SQLite database at `path`.
+async fn write_collection(
+    notes: &[AnkiNote],
+    note_type: AnkiNoteType,
+    path: &Path,
+) -> Result<(), sqlx::Error> {
+    let mut conn = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .connect()
+        .await?;
+
+    let now = chrono::Utc::now();
+    let now_secs = now.timestamp();
+    let now_millis = now.timestamp_millis();
+    let model_id = match note_type {
+        AnkiNoteType::Quote => QUOTE_MODEL_ID,
+        AnkiNoteType::Cloze => CLOZE_MODEL_ID,
+    };
+
+    // Parent decks are created too, Anki expects every level to exist
+    let mut decks = BTreeMap::new();
+    decks.insert(
+        DEFAULT_DECK_ID.to_string(),
+        deck_json(DEFAULT_DECK_ID, "Default", now_secs),
+    );
+    for note in notes {
+        let mut name = String::new();
+        for part in note.deck.split("::") {
+            if !name.is_empty() {
+                name.push_str("::");
+            }
+            name.push_str(part);
+            let id = stable_id(&format!("deck:{}", name));
+            decks.insert(id.to_string(), deck_json(id, &name, now_secs));
+        }
+    }
+
+    let conf = json!({
+        "activeDecks": [DEFAULT_DECK_ID],
+        "curDeck": DEFAULT_DECK_ID,
+        "curModel": model_id.to_string(),
+        "newSpread": 0,
+        "collapseTime": 1200,
+        "timeLim": 0,
+        "estTimes": true,
+        "dueCounts": true,
+        "nextPos": notes.len() + 1,
+        "sortType": "noteFld",
+        "sortBackwards": false,
+        "addToCur": true,
+    });
+
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(COLLECTION_SCHEMA).execute(&mut *tx).await?;
+
+    sqlx::query(
+        "INSERT INTO col (id, crt, mod, scm, ver, dty, usn, ls, conf, models, decks, dconf, tags)
+            VALUES (1, ?, ?, ?, 11, 0, 0, 0, ?, ?, ?, ?, '{}')",
+    )
+    .bind(now_secs)
+    .bind(now_millis)
+    .bind(now_millis)
+    .bind(conf.to_string())
+    .bind(models_json(now_secs).to_string())
+    .bind(serde_json::to_string(&decks).unwrap_or_default())
+    .bind(deck_config_json(now_secs).to_string())
+    .execute(&mut *tx)
+    .await?;
+
+    for (position, note) in notes.iter().enumerate() {
+        let note_id = stable_id(&format!("note:{}", note.guid));
+        let deck_id = stable_id(&format!("deck:{}", note.deck));
+        let tags = if note.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" {} ", note.tags.join(" "))
+        };
+        let fields = note
+            .fields
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(&FIELD_SEPARATOR.to_string());
+
+        sqlx::query(
+            "INSERT INTO notes (id, guid, mid, mod, usn, tags, flds, sfld, csum, flags, data)
+                VALUES (?, ?, ?, ?, -1, ?, ?, ?, ?, 0, '')",
+        )
+        .bind(note_id)
+        .bind(&note.guid)
+        .bind(model_id)
+        .bind(now_secs)
+        .bind(tags)
+        .bind(fields)
+        .bind(&note.sort_field)
+        .bind(field_checksum(&note.sort_field))
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO cards
+                (id, nid, did, ord, mod, usn, type, queue, due, ivl, factor, reps, lapses, left, odue, odid, flags, data)
+                VALUES (?, ?, ?, 0, ?, -1, 0, 0, ?, 0, 0, 0, 0, 0, 0, 0, 0, '')",
+        )
+        .bind(stable_id(&format!("card:{}", note.guid)))
+        .bind(note_id)
+        .bind(deck_id)
+        .bind(now_secs)
+        .bind(position as i64 + 1)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    conn.close().await
+}
+
+/// Export the quotes matching `filter` as an Anki package at `path`.
+///
+/// Returns the number of notes written.
+pub async fn export_anki(
+    filter: &QuoteFilter,
+    note_type: AnkiNoteType,
+    path: &Path,
+    conn: &mut SqliteConnection,
+) -> Result<usize, ExportError> {
+    let quotes =
+        queries::query_quotes(filter, Some("date_created"), Some("ASC"), None, None, &mut *conn)
+            .await?;
+    let quote_ids: Vec<String> = quotes.iter().map(|q| q.id.clone()).collect();
+    let notes = queries::get_notes_by_quote_ids(&quote_ids, &mut *conn).await?;
+    let anki_notes = quotes_to_notes(&quotes, &notes, note_type);
+
+    // The collection is built in a scratch database, then zipped into the package
+    let collection_path =
+        std::env::temp_dir().join(format!("liture-anki-{}.anki2", uuid::Uuid::new_v4()));
+    let result = async {
+        write_collection(&anki_notes, note_type, &collection_path).await?;
+        let collection = std::fs::read(&collection_path)?;
+
+        let file = std::fs::File::create(path)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file("collection.anki2", options)?;
+        zip.write_all(&collection)?;
+        zip.start_file("media", options)?;
+        zip.write_all(b"{}")?;
+        zip.finish()?;
+
+        Ok::<_, ExportError>(())
+    }
+    .await;
+    let _ = std::fs::remove_file(&collection_path);
+    result?;
+
+    Ok(anki_notes.len())
+}
+
+/// Open a save dialog and return the path of the Anki package.
+pub async fn anki_dialog(app: &AppHandle) -> Result<String, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    let window = app.get_window("main").ok_or("No main window found")?;
+
+    let dialog = FileDialogBuilder::new(app.dialog().clone())
+        .set_parent(&window)
+        .set_title("Export to Anki")
+        .set_file_name("Liture.apkg")
+        .add_filter("Anki package", &["apkg"]);
+
+    dialog.save_file(move |file_path| {
+        let result = match file_path {
+            Some(path) => Ok(path.to_string()),
+            None => Err("No file selected".to_string()),
+        };
+        let _ = tx.send(result);
+    });
+
+    rx.await.map_err(|_| "Dialog channel error".to_string())?
+}
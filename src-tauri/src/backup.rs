@@ -0,0 +1,798 @@
+use crate::models::{
+    Author, Book, Bookmark, Chapter, ImportBatch, Note, Quote, QuoteTag, SearchHistory, Setting,
+    SmartCollection, Tag,
+};
+use crate::queries;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::query_builder::Separated;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder};
+
+/// Version of the backup format, bump it on breaking changes.
+pub const BACKUP_VERSION: u32 = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Database error: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("Invalid backup file: {0}")]
+    InvalidFormat(#[from] serde_json::Error),
+    #[error("Unsupported backup version {0}, latest supported is {BACKUP_VERSION}")]
+    UnsupportedVersion(u32),
+}
+
+/// How a backup is restored.
+///
+/// * `Replace` - The library is emptied before restoring the backup.
+/// * `Merge` - Rows are matched by id or `original_id`, the newer `updated_at` wins.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    Replace,
+    Merge,
+}
+
+/// Number of rows created, updated and skipped by a restore.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RestoreSummary {
+    pub created: u64,
+    pub updated: u64,
+    pub skipped: u64,
+}
+
+/// Every row of the library, soft-deleted ones included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryBackup {
+    pub version: u32,
+    pub created_at: NaiveDateTime,
+    #[serde(default)]
+    pub authors: Vec<Author>,
+    #[serde(default)]
+    pub books: Vec<Book>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+    #[serde(default)]
+    pub quotes: Vec<Quote>,
+    #[serde(default)]
+    pub notes: Vec<Note>,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub quote_tags: Vec<QuoteTag>,
+    #[serde(default)]
+    pub search_history: Vec<SearchHistory>,
+    /// Since version 2, with the `import_batch_id` of the rows
+    #[serde(default)]
+    pub import_batches: Vec<ImportBatch>,
+    /// Since version 3
+    #[serde(default)]
+    pub smart_collections: Vec<SmartCollection>,
+    /// Since version 3
+    #[serde(default)]
+    pub settings: Vec<Setting>,
+}
+
+/// A table row written back as is by a restore.
+trait BackupRow: Send + Sync {
+    const TABLE: &'static str;
+    /// Columns in the order of `push_values`
+    const COLUMNS: &'static [&'static str];
+    const HAS_ORIGINAL_ID: bool;
+
+    fn id(&self) -> &str;
+    fn set_id(&mut self, id: String);
+    fn original_id(&self) -> Option<&str>;
+    fn updated_at(&self) -> NaiveDateTime;
+    /// Point the foreign keys to the rows merged into existing ones
+    fn remap(&mut self, ids: &HashMap<String, String>);
+    fn push_values<'a>(&'a self, values: &mut Separated<'_, 'a, Sqlite, &'static str>);
+}
+
+fn remap_id(id: &mut Option<String>, ids: &HashMap<String, String>) {
+    if let Some(new_id) = id.as_ref().and_then(|id| ids.get(id)) {
+        *id = Some(new_id.clone());
+    }
+}
+
+impl BackupRow for Author {
+    const TABLE: &'static str = "author";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "created_at",
+        "updated_at",
+        "deleted_at",
+        "original_id",
+        "import_batch_id",
+    ];
+    const HAS_ORIGINAL_ID: bool = true;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+    fn original_id(&self) -> Option<&str> {
+        self.original_id.as_deref()
+    }
+    fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+    fn remap(&mut self, _ids: &HashMap<String, String>) {}
+    fn push_values<'a>(&'a self, values: &mut Separated<'_, 'a, Sqlite, &'static str>) {
+        values
+            .push_bind(&self.id)
+            .push_bind(&self.name)
+            .push_bind(self.created_at)
+            .push_bind(self.updated_at)
+            .push_bind(self.deleted_at)
+            .push_bind(&self.original_id)
+            .push_bind(&self.import_batch_id);
+    }
+}
+
+impl BackupRow for Book {
+    const TABLE: &'static str = "book";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "author_id",
+        "title",
+        "publication_year",
+        "created_at",
+        "updated_at",
+        "deleted_at",
+        "original_id",
+        "import_batch_id",
+    ];
+    const HAS_ORIGINAL_ID: bool = true;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+    fn original_id(&self) -> Option<&str> {
+        self.original_id.as_deref()
+    }
+    fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+    fn remap(&mut self, ids: &HashMap<String, String>) {
+        remap_id(&mut self.author_id, ids);
+    }
+    fn push_values<'a>(&'a self, values: &mut Separated<'_, 'a, Sqlite, &'static str>) {
+        values
+            .push_bind(&self.id)
+            .push_bind(&self.author_id)
+            .push_bind(&self.title)
+            .push_bind(&self.publication_year)
+            .push_bind(self.created_at)
+            .push_bind(self.updated_at)
+            .push_bind(self.deleted_at)
+            .push_bind(&self.original_id)
+            .push_bind(&self.import_batch_id);
+    }
+}
+
+impl BackupRow for Chapter {
+    const TABLE: &'static str = "chapter";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "book_id",
+        "title",
+        "created_at",
+        "updated_at",
+        "deleted_at",
+        "original_id",
+        "volume_index",
+        "import_batch_id",
+    ];
+    const HAS_ORIGINAL_ID: bool = true;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+    fn original_id(&self) -> Option<&str> {
+        self.original_id.as_deref()
+    }
+    fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+    fn remap(&mut self, ids: &HashMap<String, String>) {
+        remap_id(&mut self.book_id, ids);
+    }
+    fn push_values<'a>(&'a self, values: &mut Separated<'_, 'a, Sqlite, &'static str>) {
+        values
+            .push_bind(&self.id)
+            .push_bind(&self.book_id)
+            .push_bind(&self.title)
+            .push_bind(self.created_at)
+            .push_bind(self.updated_at)
+            .push_bind(self.deleted_at)
+            .push_bind(&self.original_id)
+            .push_bind(self.volume_index)
+            .push_bind(&self.import_batch_id);
+    }
+}
+
+impl BackupRow for Quote {
+    const TABLE: &'static str = "quote";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "book_id",
+        "author_id",
+        "chapter_id",
+        "chapter_progress",
+        "content",
+        "starred",
+        "created_at",
+        "updated_at",
+        "imported_at",
+        "deleted_at",
+        "original_id",
+        "color",
+        "page_start",
+        "page_end",
+        "location_start",
+        "location_end",
+        "import_batch_id",
+    ];
+    const HAS_ORIGINAL_ID: bool = true;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+    fn original_id(&self) -> Option<&str> {
+        self.original_id.as_deref()
+    }
+    fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+    fn remap(&mut self, ids: &HashMap<String, String>) {
+        remap_id(&mut self.book_id, ids);
+        remap_id(&mut self.author_id, ids);
+        remap_id(&mut self.chapter_id, ids);
+    }
+    fn push_values<'a>(&'a self, values: &mut Separated<'_, 'a, Sqlite, &'static str>) {
+        values
+            .push_bind(&self.id)
+            .push_bind(&self.book_id)
+            .push_bind(&self.author_id)
+            .push_bind(&self.chapter_id)
+            .push_bind(self.chapter_progress)
+            .push_bind(&self.content)
+            .push_bind(self.starred)
+            .push_bind(self.created_at)
+            .push_bind(self.updated_at)
+            .push_bind(self.imported_at)
+            .push_bind(self.deleted_at)
+            .push_bind(&self.original_id)
+            .push_bind(&self.color)
+            .push_bind(self.page_start)
+            .push_bind(self.page_end)
+            .push_bind(self.location_start)
+            .push_bind(self.location_end)
+            .push_bind(&self.import_batch_id);
+    }
+}
+
+impl BackupRow for Note {
+    const TABLE: &'static str = "note";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "book_id",
+        "author_id",
+        "quote_id",
+        "content",
+        "created_at",
+        "updated_at",
+        "deleted_at",
+        "import_batch_id",
+    ];
+    const HAS_ORIGINAL_ID: bool = false;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+    fn original_id(&self) -> Option<&str> {
+        None
+    }
+    fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+    fn remap(&mut self, ids: &HashMap<String, String>) {
+        remap_id(&mut self.book_id, ids);
+        remap_id(&mut self.author_id, ids);
+        remap_id(&mut self.quote_id, ids);
+    }
+    fn push_values<'a>(&'a self, values: &mut Separated<'_, 'a, Sqlite, &'static str>) {
+        values
+            .push_bind(&self.id)
+            .push_bind(&self.book_id)
+            .push_bind(&self.author_id)
+            .push_bind(&self.quote_id)
+            .push_bind(&self.content)
+            .push_bind(self.created_at)
+            .push_bind(self.updated_at)
+            .push_bind(self.deleted_at)
+            .push_bind(&self.import_batch_id);
+    }
+}
+
+impl BackupRow for Bookmark {
+    const TABLE: &'static str = "bookmark";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "book_id",
+        "chapter_id",
+        "chapter_progress",
+        "kind",
+        "created_at",
+        "updated_at",
+        "original_id",
+        "import_batch_id",
+    ];
+    const HAS_ORIGINAL_ID: bool = true;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+    fn original_id(&self) -> Option<&str> {
+        self.original_id.as_deref()
+    }
+    fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+    fn remap(&mut self, ids: &HashMap<String, String>) {
+        remap_id(&mut self.book_id, ids);
+        remap_id(&mut self.chapter_id, ids);
+    }
+    fn push_values<'a>(&'a self, values: &mut Separated<'_, 'a, Sqlite, &'static str>) {
+        values
+            .push_bind(&self.id)
+            .push_bind(&self.book_id)
+            .push_bind(&self.chapter_id)
+            .push_bind(self.chapter_progress)
+            .push_bind(&self.kind)
+            .push_bind(self.created_at)
+            .push_bind(self.updated_at)
+            .push_bind(&self.original_id)
+            .push_bind(&self.import_batch_id);
+    }
+}
+
+impl BackupRow for ImportBatch {
+    const TABLE: &'static str = "import_batch";
+    const COLUMNS: &'static [&'static str] =
+        &["id", "source", "path", "file_hash", "report", "created_at"];
+    const HAS_ORIGINAL_ID: bool = false;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+    fn original_id(&self) -> Option<&str> {
+        None
+    }
+    fn updated_at(&self) -> NaiveDateTime {
+        self.created_at
+    }
+    fn remap(&mut self, _ids: &HashMap<String, String>) {}
+    fn push_values<'a>(&'a self, values: &mut Separated<'_, 'a, Sqlite, &'static str>) {
+        values
+            .push_bind(&self.id)
+            .push_bind(&self.source)
+            .push_bind(&self.path)
+            .push_bind(&self.file_hash)
+            .push_bind(serde_json::to_string(&self.report).unwrap_or_else(|_| "{}".to_string()))
+            .push_bind(self.created_at);
+    }
+}
+
+impl BackupRow for SmartCollection {
+    const TABLE: &'static str = "smart_collection";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "filter",
+        "sort_by",
+        "sort_order",
+        "created_at",
+        "updated_at",
+    ];
+    const HAS_ORIGINAL_ID: bool = false;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+    fn original_id(&self) -> Option<&str> {
+        None
+    }
+    fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+    fn remap(&mut self, _ids: &HashMap<String, String>) {}
+    fn push_values<'a>(&'a self, values: &mut Separated<'_, 'a, Sqlite, &'static str>) {
+        values
+            .push_bind(&self.id)
+            .push_bind(&self.name)
+            .push_bind(serde_json::to_string(&self.filter).unwrap_or_else(|_| "{}".to_string()))
+            .push_bind(&self.sort_by)
+            .push_bind(&self.sort_order)
+            .push_bind(self.created_at)
+            .push_bind(self.updated_at);
+    }
+}
+
+/// Dump every row of the library, soft-deleted ones included.
+pub async fn dump_library(conn: &mut SqliteConnection) -> Result<LibraryBackup, sqlx::Error> {
+    Ok(LibraryBackup {
+        version: BACKUP_VERSION,
+        created_at: chrono::Utc::now().naive_utc(),
+        authors: sqlx::query_as("SELECT * FROM author ORDER BY rowid")
+            .fetch_all(&mut *conn)
+            .await?,
+        books: sqlx::query_as("SELECT * FROM book ORDER BY rowid")
+            .fetch_all(&mut *conn)
+            .await?,
+        chapters: sqlx::query_as("SELECT * FROM chapter ORDER BY rowid")
+            .fetch_all(&mut *conn)
+            .await?,
+        quotes: sqlx::query_as("SELECT * FROM quote ORDER BY rowid")
+            .fetch_all(&mut *conn)
+            .await?,
+        notes: sqlx::query_as("SELECT * FROM note ORDER BY rowid")
+            .fetch_all(&mut *conn)
+            .await?,
+        bookmarks: sqlx::query_as("SELECT * FROM bookmark ORDER BY rowid")
+            .fetch_all(&mut *conn)
+            .await?,
+        tags: sqlx::query_as("SELECT * FROM tag ORDER BY rowid")
+            .fetch_all(&mut *conn)
+            .await?,
+        quote_tags: sqlx::query_as("SELECT * FROM quote_tag ORDER BY rowid")
+            .fetch_all(&mut *conn)
+            .await?,
+        search_history: sqlx::query_as("SELECT * FROM search_history ORDER BY id")
+            .fetch_all(&mut *conn)
+            .await?,
+        import_batches: queries::get_import_batches(&mut *conn).await?,
+        smart_collections: queries::get_smart_collections(&mut *conn).await?,
+        settings: sqlx::query_as("SELECT * FROM setting ORDER BY key")
+            .fetch_all(&mut *conn)
+            .await?,
+    })
+}
+
+/// Write a backup of the library as JSON.
+pub async fn write_backup(path: &Path, conn: &mut SqliteConnection) -> Result<(), BackupError> {
+    let backup = dump_library(conn).await?;
+    let file = io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer(file, &backup)?;
+    Ok(())
+}
+
+/// Read a backup written by `write_backup`, checking its version.
+pub fn read_backup(path: &Path) -> Result<LibraryBackup, BackupError> {
+    let file = io::BufReader::new(std::fs::File::open(path)?);
+    let backup: LibraryBackup = serde_json::from_reader(file)?;
+    if backup.version > BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(backup.version));
+    }
+    Ok(backup)
+}
+
+async fn insert_row<T: BackupRow>(row: &T, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        "INSERT INTO {} ({}) VALUES (",
+        T::TABLE,
+        T::COLUMNS.join(", ")
+    ));
+    row.push_values(&mut query.separated(", "));
+    query.push(")");
+    query.build().execute(&mut *conn).await?;
+    Ok(())
+}
+
+async fn update_row<T: BackupRow>(row: &T, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        "UPDATE {} SET ({}) = (",
+        T::TABLE,
+        T::COLUMNS.join(", ")
+    ));
+    row.push_values(&mut query.separated(", "));
+    query.push(") WHERE id = ").push_bind(row.id());
+    query.build().execute(&mut *conn).await?;
+    Ok(())
+}
+
+async fn insert_rows<T: BackupRow>(
+    rows: &[T],
+    summary: &mut RestoreSummary,
+    conn: &mut SqliteConnection,
+) -> Result<(), sqlx::Error> {
+    for row in rows {
+        insert_row(row, conn).await?;
+        summary.created += 1;
+    }
+    Ok(())
+}
+
+/// Merge rows into the existing ones matched by id, then by `original_id`.
+/// Rows matched by `original_id` keep the existing id, recorded in `ids`
+/// so the rows referencing them are merged too.
+async fn merge_rows<T: BackupRow>(
+    rows: Vec<T>,
+    ids: &mut HashMap<String, String>,
+    summary: &mut RestoreSummary,
+    conn: &mut SqliteConnection,
+) -> Result<(), sqlx::Error> {
+    let sql = if T::HAS_ORIGINAL_ID {
+        format!(
+            "SELECT id, updated_at FROM {}
+            WHERE id = ?1 OR (?2 IS NOT NULL AND original_id = ?2)
+            ORDER BY id = ?1 DESC
+            LIMIT 1",
+            T::TABLE
+        )
+    } else {
+        format!("SELECT id, updated_at FROM {} WHERE id = ?1", T::TABLE)
+    };
+
+    for mut row in rows {
+        row.remap(ids);
+
+        let mut query = sqlx::query_as::<_, (String, NaiveDateTime)>(&sql).bind(row.id().to_string());
+        if T::HAS_ORIGINAL_ID {
+            query = query.bind(row.original_id().map(|id| id.to_string()));
+        }
+
+        match query.fetch_optional(&mut *conn).await? {
+            None => {
+                insert_row(&row, conn).await?;
+                summary.created += 1;
+            }
+            Some((id, updated_at)) => {
+                if id != row.id() {
+                    ids.insert(row.id().to_string(), id.clone());
+                    row.set_id(id);
+                }
+                if row.updated_at() > updated_at {
+                    update_row(&row, conn).await?;
+                    summary.updated += 1;
+                } else {
+                    summary.skipped += 1;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Delete every row of the tables saved in a backup of `version`.
+async fn clear_library(version: u32, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    let mut tables = vec![
+        "quote_tag",
+        "note",
+        "quote",
+        "bookmark",
+        "chapter",
+        "book",
+        "author",
+        "tag",
+        "search_history",
+        "import_batch",
+    ];
+    // Older backups have neither, the current ones are kept
+    if version >= 3 {
+        tables.extend(["smart_collection", "setting"]);
+    }
+
+    for table in tables {
+        sqlx::query(&format!("DELETE FROM {}", table))
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Restore a backup, run it in a transaction to restore all or nothing.
+pub async fn restore_library(
+    backup: LibraryBackup,
+    mode: RestoreMode,
+    conn: &mut SqliteConnection,
+) -> Result<RestoreSummary, BackupError> {
+    if backup.version > BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(backup.version));
+    }
+
+    let mut summary = RestoreSummary::default();
+    let mut ids = HashMap::new();
+
+    match mode {
+        RestoreMode::Replace => {
+            clear_library(backup.version, conn).await?;
+            insert_rows(&backup.import_batches, &mut summary, conn).await?;
+            insert_rows(&backup.authors, &mut summary, conn).await?;
+            insert_rows(&backup.books, &mut summary, conn).await?;
+            insert_rows(&backup.chapters, &mut summary, conn).await?;
+            insert_rows(&backup.quotes, &mut summary, conn).await?;
+            insert_rows(&backup.notes, &mut summary, conn).await?;
+            insert_rows(&backup.bookmarks, &mut summary, conn).await?;
+            insert_rows(&backup.smart_collections, &mut summary, conn).await?;
+        }
+        RestoreMode::Merge => {
+            merge_rows(backup.import_batches, &mut ids, &mut summary, conn).await?;
+            merge_rows(backup.authors, &mut ids, &mut summary, conn).await?;
+            merge_rows(backup.books, &mut ids, &mut summary, conn).await?;
+            merge_rows(backup.chapters, &mut ids, &mut summary, conn).await?;
+            merge_rows(backup.quotes, &mut ids, &mut summary, conn).await?;
+            merge_rows(backup.notes, &mut ids, &mut summary, conn).await?;
+            merge_rows(backup.bookmarks, &mut ids, &mut summary, conn).await?;
+            merge_rows(backup.smart_collections, &mut ids, &mut summary, conn).await?;
+        }
+    }
+
+    // Tags have no timestamps, existing ones are kept. Match by name too to avoid duplicates.
+    for tag in backup.tags {
+        let existing: Option<String> =
+            sqlx::query_scalar("SELECT id FROM tag WHERE id = ?1 OR name = ?2 ORDER BY id = ?1 DESC LIMIT 1")
+                .bind(&tag.id)
+                .bind(&tag.name)
+                .fetch_optional(&mut *conn)
+                .await?;
+        match existing {
+            Some(id) => {
+                if id != tag.id {
+                    ids.insert(tag.id, id);
+                }
+                summary.skipped += 1;
+            }
+            None => {
+                sqlx::query("INSERT INTO tag (id, name, color, import_batch_id) VALUES (?, ?, ?, ?)")
+                    .bind(&tag.id)
+                    .bind(&tag.name)
+                    .bind(&tag.color)
+                    .bind(&tag.import_batch_id)
+                    .execute(&mut *conn)
+                    .await?;
+                summary.created += 1;
+            }
+        }
+    }
+
+    for quote_tag in backup.quote_tags {
+        let quote_id = ids.get(&quote_tag.quote_id).unwrap_or(&quote_tag.quote_id);
+        let tag_id = ids.get(&quote_tag.tag_id).unwrap_or(&quote_tag.tag_id);
+        let result = sqlx::query("INSERT OR IGNORE INTO quote_tag (quote_id, tag_id) VALUES (?, ?)")
+            .bind(quote_id)
+            .bind(tag_id)
+            .execute(&mut *conn)
+            .await?;
+        if result.rows_affected() > 0 {
+            summary.created += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+
+    // Searches are matched by query, keeping the latest timestamp and highest frequency
+    for search in backup.search_history {
+        let existing: Option<NaiveDateTime> =
+            sqlx::query_scalar("SELECT timestamp FROM search_history WHERE query = ?")
+                .bind(&search.query)
+                .fetch_optional(&mut *conn)
+                .await?;
+        match existing {
+            None => summary.created += 1,
+            Some(timestamp) if search.timestamp > timestamp => summary.updated += 1,
+            Some(_) => summary.skipped += 1,
+        }
+
+        let id = (mode == RestoreMode::Replace).then_some(search.id);
+        sqlx::query(
+            "INSERT INTO search_history (id, query, timestamp, frequency) VALUES (?, ?, ?, ?)
+            ON CONFLICT(query) DO UPDATE SET
+                timestamp = MAX(timestamp, excluded.timestamp),
+                frequency = MAX(frequency, excluded.frequency)",
+        )
+        .bind(id)
+        .bind(&search.query)
+        .bind(search.timestamp)
+        .bind(search.frequency)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    // Settings have no timestamps, a merge keeps the existing ones
+    for setting in backup.settings {
+        let result = sqlx::query("INSERT OR IGNORE INTO setting (key, value) VALUES (?, ?)")
+            .bind(&setting.key)
+            .bind(&setting.value)
+            .execute(&mut *conn)
+            .await?;
+        if result.rows_affected() > 0 {
+            summary.created += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Open a save dialog for the backup file and return its path.
+pub async fn backup_dialog(app: &AppHandle) -> Result<String, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    let window = app.get_window("main").ok_or("No main window found")?;
+
+    let dialog = FileDialogBuilder::new(app.dialog().clone())
+        .set_parent(&window)
+        .add_filter("Backup", &["json"])
+        .set_file_name(format!(
+            "liture-backup-{}.json",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+
+    dialog.save_file(move |file_path| {
+        let result = match file_path {
+            Some(path) => Ok(path.to_string()),
+            None => Err("No file selected".to_string()),
+        };
+        let _ = tx.send(result);
+    });
+
+    rx.await.map_err(|_| "Dialog channel error".to_string())?
+}
+
+/// Open a file dialog to pick the backup to restore and return its path.
+pub async fn restore_dialog(app: &AppHandle) -> Result<String, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    let window = app.get_window("main").ok_or("No main window found")?;
+
+    let dialog = FileDialogBuilder::new(app.dialog().clone())
+        .set_parent(&window)
+        .add_filter("Backup", &["json"]);
+
+    dialog.pick_file(move |file_path| {
+        let result = match file_path {
+            Some(path) => Ok(path.to_string()),
+            None => Err("No file selected".to_string()),
+        };
+        let _ = tx.send(result);
+    });
+
+    rx.await.map_err(|_| "Dialog channel error".to_string())?
+}
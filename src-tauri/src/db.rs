@@ -1,7 +1,9 @@
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::{Sqlite, SqlitePool, Transaction};
-use sqlx::migrate::MigrateError;
+use sqlx::migrate::{MigrateError, Migrator};
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use tauri::Manager; // required for tauri::AppHandle
 use uuid::Uuid;
@@ -11,6 +13,15 @@ use crate::queries;
 use crate::models::Tag;
 
 static DB_POOL: OnceLock<SqlitePool> = OnceLock::new();
+static DB_INIT_ERROR: OnceLock<DbError> = OnceLock::new();
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("../migrations");
+
+/// Number of pre-migration backups kept in the backups directory.
+const MAX_BACKUPS: usize = 5;
+
+/// Setting holding after how many days trashed items are purged on startup.
+pub const TRASH_RETENTION_DAYS_KEY: &str = "trash_retention_days";
 
 #[derive(Error, Debug)]
 pub enum DbInitError {
@@ -38,6 +49,49 @@ pub enum DbError {
     Migration(#[from] MigrateError),
     #[error("Failed to create app data directory")]
     DirectoryCreation,
+    #[error("Failed to back up the database before migrating: {0}")]
+    Backup(#[source] io::Error),
+    #[error("Database upgrade failed, your library was restored from {}: {source}", backup.display())]
+    MigrationRestored {
+        backup: PathBuf,
+        #[source]
+        source: MigrateError,
+    },
+    #[error("Database upgrade failed and the backup {} could not be restored: {source}", backup.display())]
+    Restore {
+        backup: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl DbError {
+    /// Stable identifier of the error, used by the frontend to pick a message.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DbError::Init(_) => "init",
+            DbError::Sqlx(_) => "sqlx",
+            DbError::Migration(_) => "migration",
+            DbError::DirectoryCreation => "directory_creation",
+            DbError::Backup(_) => "backup",
+            DbError::MigrationRestored { .. } => "migration_restored",
+            DbError::Restore { .. } => "restore",
+        }
+    }
+}
+
+impl serde::Serialize for DbError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("DbError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
 }
 
 pub async fn init_pool(app: tauri::AppHandle) -> Result<(), DbError> {
@@ -116,6 +170,10 @@ pub async fn init_pool(app: tauri::AppHandle) -> Result<(), DbError> {
         })?;
     log::info!("Connected to database successfully");
 
+    // Existing databases are backed up before being migrated
+    let backup_dir = db_exists.then(|| app_dir.join("backups"));
+    let pool = run_migrations(pool, &MIGRATOR, &db_path, backup_dir.as_deref()).await?;
+
     // Set the database pool
     match DB_POOL.set(pool.clone()) {
         Ok(_) => log::info!("Database pool set successfully"),
@@ -126,13 +184,6 @@ pub async fn init_pool(app: tauri::AppHandle) -> Result<(), DbError> {
     }
 
     if initialized {
-        log::info!("Running database migrations...");
-        sqlx::migrate!("../migrations").run(&pool).await.map_err(|e| {
-            log::error!("Failed to run migrations: {}", e);
-            e
-        })?;
-        log::info!("Migrations completed successfully");
-
         log::info!("Initializing database with defaults...");
         init_db_with_defaults(&pool).await.map_err(|e| {
             log::error!("Failed to initialize database with defaults: {}", e);
@@ -141,9 +192,156 @@ pub async fn init_pool(app: tauri::AppHandle) -> Result<(), DbError> {
         log::info!("Database initialized with defaults successfully");
     }
 
+    // Failing to empty the trash should not prevent the app from starting
+    if let Err(e) = empty_expired_trash(&pool).await {
+        log::error!("Failed to empty the trash: {}", e);
+    }
+
+    Ok(())
+}
+
+pub async fn get_trash_retention_days<'e, E>(executor: E) -> Result<Option<i64>, sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let value = queries::get_setting(TRASH_RETENTION_DAYS_KEY, executor).await?;
+    Ok(value.and_then(|v| v.parse().ok()))
+}
+
+/// Purge the trashed items older than the configured retention, if any.
+pub async fn empty_expired_trash(pool: &SqlitePool) -> Result<(), DbError> {
+    let Some(days) = get_trash_retention_days(pool).await? else {
+        return Ok(());
+    };
+
+    let mut tx = pool.begin().await?;
+    let purged = queries::purge_trash(Some(days), &mut tx).await?;
+    tx.commit().await?;
+
+    log::info!("Purged {} trashed items older than {} days", purged, days);
+    Ok(())
+}
+
+/// Returns true if the migrator contains migrations not yet applied to the database.
+async fn has_pending_migrations(pool: &SqlitePool, migrator: &Migrator) -> Result<bool, DbError> {
+    let table_exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if !table_exists {
+        return Ok(migrator.iter().next().is_some());
+    }
+
+    let applied: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = 1")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(migrator
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .any(|migration| !applied.contains(&migration.version)))
+}
+
+/// Copy the database into `backup_dir` and prune the oldest backups.
+async fn backup_database(pool: &SqlitePool, backup_dir: &Path) -> Result<PathBuf, DbError> {
+    fs::create_dir_all(backup_dir).map_err(DbError::Backup)?;
+
+    let backup_path = backup_dir.join(format!(
+        "main-{}.db",
+        chrono::Utc::now().format("%Y%m%d%H%M%S%3f")
+    ));
+
+    // VACUUM INTO writes a consistent copy even with open connections
+    sqlx::query("VACUUM INTO ?")
+        .bind(backup_path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| DbError::Backup(io::Error::other(e)))?;
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(backup_dir)
+        .map_err(DbError::Backup)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "db"))
+        .collect();
+    backups.sort();
+    for old_backup in backups.iter().rev().skip(MAX_BACKUPS) {
+        if let Err(e) = fs::remove_file(old_backup) {
+            log::warn!("Failed to remove old backup {}: {}", old_backup.display(), e);
+        }
+    }
+
+    Ok(backup_path)
+}
+
+/// Replace the database file with a backup, dropping any leftover journal files.
+fn restore_database(db_path: &Path, backup_path: &Path) -> Result<(), io::Error> {
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let journal = PathBuf::from(format!("{}{}", db_path.display(), suffix));
+        if journal.exists() {
+            fs::remove_file(journal)?;
+        }
+    }
+    fs::copy(backup_path, db_path)?;
     Ok(())
 }
 
+/// Apply the pending migrations.
+///
+/// If `backup_dir` is set, the database is backed up there before migrating and
+/// restored if a migration fails. The pool is closed in that case.
+pub async fn run_migrations(
+    pool: SqlitePool,
+    migrator: &Migrator,
+    db_path: &Path,
+    backup_dir: Option<&Path>,
+) -> Result<SqlitePool, DbError> {
+    if !has_pending_migrations(&pool, migrator).await? {
+        log::info!("Database schema is up to date");
+        return Ok(pool);
+    }
+
+    let backup_path = match backup_dir {
+        Some(dir) => {
+            let path = backup_database(&pool, dir).await?;
+            log::info!("Database backed up to {}", path.display());
+            Some(path)
+        }
+        None => None,
+    };
+
+    log::info!("Running database migrations...");
+    match migrator.run(&pool).await {
+        Ok(_) => {
+            log::info!("Migrations completed successfully");
+            Ok(pool)
+        }
+        Err(e) => {
+            log::error!("Failed to run migrations: {}", e);
+            let Some(backup) = backup_path else {
+                return Err(DbError::Migration(e));
+            };
+
+            pool.close().await;
+            match restore_database(db_path, &backup) {
+                Ok(_) => {
+                    log::info!("Database restored from {}", backup.display());
+                    Err(DbError::MigrationRestored { backup, source: e })
+                }
+                Err(restore_err) => {
+                    log::error!("Failed to restore database: {}", restore_err);
+                    Err(DbError::Restore {
+                        backup,
+                        source: restore_err,
+                    })
+                }
+            }
+        }
+    }
+}
+
 /// Initialize the database with some default data
 pub async fn init_db_with_defaults(pool: &SqlitePool) -> Result<(), DbInitError> {
     log::info!("Starting database initialization...");
@@ -215,6 +413,7 @@ pub async fn init_db_with_defaults(pool: &SqlitePool) -> Result<(), DbInitError>
         id: Uuid::new_v4().to_string(),
         name: "reading".to_string(),
         color: Some("#FF9800".to_string()),
+        import_batch_id: None,
     }, &mut *tx).await {
         Ok(tag) => {
             log::info!("Created reading tag with id: {}", tag.id);
@@ -231,6 +430,7 @@ pub async fn init_db_with_defaults(pool: &SqlitePool) -> Result<(), DbInitError>
         id: Uuid::new_v4().to_string(),
         name: "books".to_string(),
         color: Some("#3F51B5".to_string()),
+        import_batch_id: None,
     }, &mut *tx).await {
         Ok(tag) => {
             log::info!("Created books tag with id: {}", tag.id);
@@ -305,6 +505,15 @@ pub fn get_pool() -> &'static SqlitePool {
     }
 }
 
+/// Keep the error that prevented the database from starting, for the frontend to query.
+pub fn set_init_error(error: DbError) {
+    let _ = DB_INIT_ERROR.set(error);
+}
+
+pub fn get_init_error() -> Option<&'static DbError> {
+    DB_INIT_ERROR.get()
+}
+
 pub async fn close_pool() {
     if let Some(pool) = DB_POOL.get() {
         pool.close().await;
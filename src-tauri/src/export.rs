@@ -0,0 +1,527 @@
+use crate::db;
+use crate::import;
+use crate::models::{Author, Book, Chapter, Note, QuoteWithTags};
+use crate::queries;
+
+use sqlx::SqliteConnection;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::io;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder};
+
+/// Marker prepended to starred quotes.
+const STARRED_MARK: &str = "⭐";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Database error: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+}
+
+/// Payload for the export events.
+#[derive(Debug, Clone, serde::Serialize)]
+struct Payload {
+    message: String,
+}
+
+/// A book with everything needed to export it.
+#[derive(Debug, Clone)]
+pub struct BookExport {
+    pub book: Book,
+    pub author: Option<Author>,
+    pub chapters: Vec<Chapter>,
+    pub quotes: Vec<QuoteWithTags>,
+    pub notes: Vec<Note>,
+}
+
+/// Load a book, its author, chapters, quotes and notes.
+pub async fn load_book(book_id: &str, conn: &mut SqliteConnection) -> Result<BookExport, sqlx::Error> {
+    let book = queries::get_book_by_id(book_id.to_string(), &mut *conn).await?;
+    let author = match &book.author_id {
+        Some(author_id) => queries::get_author_by_id(author_id, &mut *conn).await.ok(),
+        None => None,
+    };
+    let chapters = queries::get_chapters_by_book(book_id, &mut *conn).await?;
+    let quotes =
+        queries::get_all_quotes_by_book_id(book_id, Some("date_created"), Some("ASC"), &mut *conn)
+            .await?;
+    let notes = queries::get_notes_by_book(book_id, &mut *conn).await?;
+
+    Ok(BookExport {
+        book,
+        author,
+        chapters,
+        quotes,
+        notes,
+    })
+}
+
+/// Quote a string for YAML, escaping what needs to be.
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Prefix every line of the text with `prefix`, blank lines included.
+fn blockquote(text: &str, prefix: &str) -> String {
+    text.trim()
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                prefix.trim_end().to_string()
+            } else {
+                format!("{}{}", prefix, line.trim_end())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Render a book as Markdown with YAML front matter.
+///
+/// Quotes are grouped by chapter, in `volume_index` then `chapter_progress` order.
+/// Quotes without a chapter come first. Notes are nested blockquotes under their quote,
+/// the notes of no exported quote are listed in a last "Notes" section.
+pub fn book_to_markdown(export: &BookExport) -> String {
+    let BookExport {
+        book,
+        author,
+        chapters,
+        quotes,
+        notes,
+    } = export;
+
+    let quotes: Vec<&QuoteWithTags> = quotes
+        .iter()
+        .filter(|q| q.content.as_deref().is_some_and(|c| !c.trim().is_empty()))
+        .collect();
+    let quote_ids: HashSet<&str> = quotes.iter().map(|q| q.id.as_str()).collect();
+
+    let mut notes_by_quote: HashMap<&str, Vec<&Note>> = HashMap::new();
+    let mut book_notes: Vec<&Note> = Vec::new();
+    for note in notes {
+        if note.content.as_deref().is_some_and(|c| !c.trim().is_empty()) {
+            match note.quote_id.as_deref().filter(|id| quote_ids.contains(id)) {
+                Some(quote_id) => notes_by_quote.entry(quote_id).or_default().push(note),
+                None => book_notes.push(note),
+            }
+        }
+    }
+    for quote_notes in notes_by_quote.values_mut() {
+        quote_notes.sort_by_key(|n| n.created_at);
+    }
+    book_notes.sort_by_key(|n| n.created_at);
+
+    let tags: BTreeSet<&str> = quotes
+        .iter()
+        .flat_map(|q| q.tags.iter().map(|t| t.name.as_str()))
+        .collect();
+    let starred = quotes.iter().filter(|q| q.starred.unwrap_or(0) != 0).count();
+    let notes_count: usize =
+        notes_by_quote.values().map(|n| n.len()).sum::<usize>() + book_notes.len();
+
+    let mut md = String::new();
+
+    // Front matter
+    let _ = writeln!(md, "---");
+    let _ = writeln!(md, "title: {}", yaml_string(&book.title));
+    match author {
+        Some(author) => {
+            let _ = writeln!(md, "author: {}", yaml_string(&author.name));
+        }
+        None => {
+            let _ = writeln!(md, "author: null");
+        }
+    }
+    match &book.publication_year {
+        Some(year) => {
+            let _ = writeln!(md, "publication_year: {}", yaml_string(year));
+        }
+        None => {
+            let _ = writeln!(md, "publication_year: null");
+        }
+    }
+    if tags.is_empty() {
+        let _ = writeln!(md, "tags: []");
+    } else {
+        let _ = writeln!(md, "tags:");
+        for tag in &tags {
+            let _ = writeln!(md, "  - {}", yaml_string(tag));
+        }
+    }
+    let _ = writeln!(md, "quotes: {}", quotes.len());
+    let _ = writeln!(md, "starred: {}", starred);
+    let _ = writeln!(md, "notes: {}", notes_count);
+    let _ = writeln!(md, "---");
+    let _ = writeln!(md);
+
+    let _ = writeln!(md, "# {}", book.title.trim());
+    if let Some(author) = author {
+        let _ = writeln!(md);
+        let _ = writeln!(md, "*{}*", author.name.trim());
+    }
+
+    // Chapters in reading order, quotes without a chapter first
+    let mut chapters: Vec<&Chapter> = chapters.iter().collect();
+    chapters.sort_by_key(|c| c.volume_index);
+    let mut sections: Vec<(Option<&Chapter>, Vec<&QuoteWithTags>)> = vec![(None, Vec::new())];
+    let mut section_index: HashMap<&str, usize> = HashMap::new();
+    for chapter in chapters {
+        section_index.insert(chapter.id.as_str(), sections.len());
+        sections.push((Some(chapter), Vec::new()));
+    }
+    for quote in quotes {
+        let index = quote
+            .chapter_id
+            .as_deref()
+            .and_then(|id| section_index.get(id))
+            .copied()
+            .unwrap_or(0);
+        sections[index].1.push(quote);
+    }
+
+    for (chapter, mut quotes) in sections {
+        if quotes.is_empty() {
+            continue;
+        }
+        quotes.sort_by(|a, b| {
+            a.position()
+                .total_cmp(&b.position())
+                .then(a.created_at.cmp(&b.created_at))
+        });
+
+        if let Some(chapter) = chapter {
+            let _ = writeln!(md);
+            let _ = writeln!(md, "## {}", chapter.title.trim());
+        }
+
+        for quote in quotes {
+            let content = quote.content.as_deref().unwrap_or_default();
+            let content = if quote.starred.unwrap_or(0) != 0 {
+                format!("{} {}", STARRED_MARK, content.trim())
+            } else {
+                content.to_string()
+            };
+
+            let _ = writeln!(md);
+            let _ = writeln!(md, "{}", blockquote(&content, "> "));
+            for note in notes_by_quote.get(quote.id.as_str()).into_iter().flatten() {
+                let _ = writeln!(md, ">");
+                let _ = writeln!(md, "{}", blockquote(note.content.as_deref().unwrap_or_default(), "> > "));
+            }
+        }
+    }
+
+    // Notes without a quote, e.g. a Kindle note that matches no highlight
+    if !book_notes.is_empty() {
+        let _ = writeln!(md);
+        let _ = writeln!(md, "## Notes");
+        for note in book_notes {
+            let _ = writeln!(md);
+            let _ = writeln!(md, "{}", blockquote(note.content.as_deref().unwrap_or_default(), "> "));
+        }
+    }
+
+    md
+}
+
+/// File name for a book, "Author - Title.md" without characters invalid on any platform.
+pub fn markdown_file_name(book: &Book, author: Option<&Author>) -> String {
+    let name = match author {
+        Some(author) => format!("{} - {}", author.name.trim(), book.title.trim()),
+        None => book.title.trim().to_string(),
+    };
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim_matches(|c: char| c == '.' || c.is_whitespace());
+
+    if name.is_empty() {
+        format!("{}.md", book.id)
+    } else {
+        format!("{}.md", name)
+    }
+}
+
+/// Export books as Markdown files in `dir`, one file per book.
+///
+/// * `book_ids` - The books to export, `None` exports the whole library.
+///
+/// Returns the paths of the files written.
+pub async fn export_markdown(
+    book_ids: Option<&[String]>,
+    dir: &Path,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<PathBuf>, ExportError> {
+    let book_ids = match book_ids {
+        Some(ids) => ids.to_vec(),
+        None => queries::get_books(&mut *conn)
+            .await?
+            .into_iter()
+            .map(|b| b.id)
+            .collect(),
+    };
+
+    std::fs::create_dir_all(dir)?;
+
+    let mut used_names = HashSet::new();
+    let mut paths = Vec::with_capacity(book_ids.len());
+    for book_id in book_ids {
+        let export = load_book(&book_id, conn).await?;
+
+        // Two books can share a title and author
+        let file_name = markdown_file_name(&export.book, export.author.as_ref());
+        let stem = file_name.trim_end_matches(".md").to_string();
+        let mut file_name = file_name;
+        let mut n = 2;
+        while !used_names.insert(file_name.to_lowercase()) {
+            file_name = format!("{} ({}).md", stem, n);
+            n += 1;
+        }
+
+        let path = dir.join(file_name);
+        std::fs::write(&path, book_to_markdown(&export))?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+/// Columns of the Readwise CSV upload template.
+const READWISE_HEADER: [&str; 7] = ["Highlight", "Title", "Author", "URL", "Note", "Location", "Date"];
+
+/// Readwise note for a quote, its tags come first as inline `.tag` words.
+fn readwise_note(quote: &QuoteWithTags, notes: &[&Note]) -> String {
+    let mut tags: Vec<String> = quote
+        .tags
+        .iter()
+        .map(|t| t.name.split_whitespace().collect::<Vec<_>>().join("_"))
+        .filter(|t| !t.is_empty())
+        .map(|t| format!(".{}", t))
+        .collect();
+    if quote.starred.unwrap_or(0) != 0 {
+        tags.insert(0, format!(".{}", import::READWISE_FAVORITE_TAG));
+    }
+
+    let text = notes
+        .iter()
+        .filter_map(|n| n.content.as_deref())
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    match (tags.is_empty(), text.is_empty()) {
+        (true, _) => text,
+        (false, true) => tags.join(" "),
+        (false, false) => format!("{} {}", tags.join(" "), text),
+    }
+}
+
+/// Export the whole library as a CSV in the Readwise upload format.
+///
+/// Quotes are written in reading order, `Location` is their location or else their page.
+/// Returns the number of highlights written.
+pub async fn export_readwise_csv(path: &Path, conn: &mut SqliteConnection) -> Result<usize, ExportError> {
+    let books = queries::get_books(&mut *conn).await?;
+
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(READWISE_HEADER)?;
+
+    let mut written = 0;
+    for book in books {
+        let export = load_book(&book.id, conn).await?;
+        let author = export.author.as_ref().map(|a| a.name.trim()).unwrap_or_default();
+
+        let volume_index: HashMap<&str, i64> = export
+            .chapters
+            .iter()
+            .map(|c| (c.id.as_str(), c.volume_index))
+            .collect();
+        let mut quotes: Vec<&QuoteWithTags> = export
+            .quotes
+            .iter()
+            .filter(|q| q.content.as_deref().is_some_and(|c| !c.trim().is_empty()))
+            .collect();
+        quotes.sort_by(|a, b| {
+            let volume = |q: &QuoteWithTags| {
+                q.chapter_id
+                    .as_deref()
+                    .and_then(|id| volume_index.get(id))
+                    .copied()
+                    .unwrap_or(-1)
+            };
+            volume(a)
+                .cmp(&volume(b))
+                .then(a.position().total_cmp(&b.position()))
+                .then(a.created_at.cmp(&b.created_at))
+        });
+
+        for quote in &quotes {
+            let notes: Vec<&Note> = export
+                .notes
+                .iter()
+                .filter(|n| n.quote_id.as_deref() == Some(quote.id.as_str()))
+                .collect();
+
+            writer.write_record([
+                quote.content.as_deref().unwrap_or_default().trim(),
+                export.book.title.trim(),
+                author,
+                "",
+                &readwise_note(quote, &notes),
+                &quote
+                    .location_start
+                    .or(quote.page_start)
+                    .map(|l| l.to_string())
+                    .unwrap_or_default(),
+                &quote.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            ])?;
+            written += 1;
+        }
+    }
+
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Open a save dialog and return the path of the Readwise CSV.
+pub async fn readwise_dialog(app: &AppHandle) -> Result<String, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    let window = app.get_window("main").ok_or("No main window found")?;
+
+    let dialog = FileDialogBuilder::new(app.dialog().clone())
+        .set_parent(&window)
+        .set_title("Export to Readwise")
+        .add_filter("Readwise CSV", &["csv"])
+        .set_file_name("readwise.csv");
+
+    dialog.save_file(move |file_path| {
+        let result = match file_path {
+            Some(path) => Ok(path.to_string()),
+            None => Err("No file selected".to_string()),
+        };
+        let _ = tx.send(result);
+    });
+
+    rx.await.map_err(|_| "Dialog channel error".to_string())?
+}
+
+/// Open a folder dialog and return the path of the selected folder.
+pub async fn export_dialog(app: &AppHandle) -> Result<String, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    let window = app.get_window("main").ok_or("No main window found")?;
+
+    let dialog = FileDialogBuilder::new(app.dialog().clone())
+        .set_parent(&window)
+        .set_title("Export to folder");
+
+    dialog.pick_folder(move |folder_path| {
+        let result = match folder_path {
+            Some(path) => Ok(path.to_string()),
+            None => Err("No folder selected".to_string()),
+        };
+        let _ = tx.send(result);
+    });
+
+    rx.await.map_err(|_| "Dialog channel error".to_string())?
+}
+
+/// Ask for a folder and export the whole library to Markdown, notifying the webview.
+pub async fn export_library_to_markdown(app: &AppHandle) {
+    let Some(webview) = app.get_webview_window("main") else {
+        log::error!("Error exporting to Markdown: unable to find window");
+        return;
+    };
+
+    let dir = match export_dialog(app).await {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::error!("Error exporting to Markdown: {}", e);
+            return;
+        }
+    };
+
+    let _ = webview.emit("exporting", ());
+
+    let result = async {
+        let mut conn = db::get_pool().acquire().await?;
+        export_markdown(None, Path::new(&dir), &mut conn).await
+    }
+    .await;
+
+    let event = match result {
+        Ok(paths) => (
+            "export-success",
+            format!("Exported {} books to {}", paths.len(), dir),
+        ),
+        Err(e) => {
+            log::error!("Error exporting to Markdown: {}", e);
+            ("export-error", e.to_string())
+        }
+    };
+    let _ = webview.emit(event.0, Payload { message: event.1 });
+}
+
+/// Ask for a file and export the whole library as a Readwise CSV, notifying the webview.
+pub async fn export_library_to_readwise(app: &AppHandle) {
+    let Some(webview) = app.get_webview_window("main") else {
+        log::error!("Error exporting to Readwise: unable to find window");
+        return;
+    };
+
+    let path = match readwise_dialog(app).await {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("Error exporting to Readwise: {}", e);
+            return;
+        }
+    };
+
+    let _ = webview.emit("exporting", ());
+
+    let result = async {
+        let mut conn = db::get_pool().acquire().await?;
+        export_readwise_csv(Path::new(&path), &mut conn).await
+    }
+    .await;
+
+    let event = match result {
+        Ok(count) => (
+            "export-success",
+            format!("Exported {} highlights to {}", count, path),
+        ),
+        Err(e) => {
+            log::error!("Error exporting to Readwise: {}", e);
+            ("export-error", e.to_string())
+        }
+    };
+    let _ = webview.emit(event.0, Payload { message: event.1 });
+}
@@ -1,126 +1,810 @@
 use crate::db;
-use crate::models;
+use crate::lua;
+use crate::models::{self, ImportReport};
 use crate::queries;
 use crate::utils::is_dev;
 
 use anyhow::Result;
-use chrono::{NaiveDateTime, TimeZone, Utc};
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
 use glob::glob;
+use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use sqlx::{sqlite::SqlitePool, FromRow, Row};
-use std::collections::HashMap;
+use sqlx::{sqlite::SqlitePool, FromRow, SqliteConnection};
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
 use tauri_plugin_dialog::{DialogExt, FileDialogBuilder};
 use uuid::Uuid;
 
 /// Payload for the import events.
 ///
+/// * `id` - The id of the import, see [`cancel_import`].
 /// * `device` - The device that is importing the data.
 /// * `message` - The message to display to the user. Can be a success message or an error message.
+/// * `report` - The report of a successful import.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 struct Payload {
+    id: Option<String>,
     device: Option<String>,
     message: Option<String>,
+    report: Option<ImportReport>,
 }
 
 fn create_payload(device: Option<String>, message: Option<String>) -> Payload {
-    Payload { device, message }
+    Payload {
+        id: None,
+        device,
+        message,
+        report: None,
+    }
 }
 
 fn get_webview(app: &AppHandle) -> Option<WebviewWindow> {
     app.get_webview_window("main")
 }
 
-pub async fn import_from_kobo(app: &AppHandle) {
-    let webview = get_webview(app).expect("unable to find window");
+/// The sources the library can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    Kobo,
+    Kindle,
+    IBooks,
+    Readwise,
+    KOReader,
+    Calibre,
+}
 
-    match import_dialog(app, DialogImportType::Kobo).await {
-        Ok(path) => {
-            webview
-                .emit("importing", create_payload(Some("kobo".to_string()), None))
-                .unwrap();
+impl ImportSource {
+    /// The name shown to the user.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportSource::Kobo => "Kobo",
+            ImportSource::Kindle => "Kindle Clippings",
+            ImportSource::IBooks => "iBooks",
+            ImportSource::Readwise => "Readwise",
+            ImportSource::KOReader => "KOReader",
+            ImportSource::Calibre => "Calibre",
+        }
+    }
 
-            match import_kobo(&path).await {
-                Ok(res) => {
-                    log::info!("Import result: {}", res);
-                    webview
-                        .emit("import-success", create_payload(None, Some(res)))
-                        .unwrap();
-                }
-                Err(e) => {
-                    log::error!("Error importing from Kobo: {}", e);
-                    webview
-                        .emit("import-error", create_payload(None, Some(e.to_string())))
-                        .unwrap();
-                }
+    /// The device sent with the `importing` event.
+    fn device(&self) -> &'static str {
+        match self {
+            ImportSource::Kobo => "kobo",
+            ImportSource::Kindle => "kindle",
+            ImportSource::IBooks => "ibooks",
+            ImportSource::Readwise => "readwise",
+            ImportSource::KOReader => "koreader",
+            ImportSource::Calibre => "calibre",
+        }
+    }
+
+    /// The dialog used to pick the path when none is given.
+    fn dialog_type(&self) -> Option<DialogImportType> {
+        match self {
+            ImportSource::Kobo => Some(DialogImportType::Kobo),
+            ImportSource::Kindle => Some(DialogImportType::Clippings),
+            // iBooks databases are found in the user's container
+            ImportSource::IBooks => None,
+            ImportSource::Readwise => Some(DialogImportType::Readwise),
+            ImportSource::KOReader => Some(DialogImportType::KOReader),
+            ImportSource::Calibre => Some(DialogImportType::Calibre),
+        }
+    }
+}
+
+/// Status of a book or quote of the source against the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewStatus {
+    /// Not in the library yet
+    New,
+    /// Already in the library
+    Duplicate,
+    /// In the library with a different content
+    Changed,
+    /// In the library as an older version, e.g. a Kindle highlight extended since
+    Newer,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewQuote {
+    /// Key of the quote in an [`ImportSelection`]
+    pub key: String,
+    pub content: Option<String>,
+    pub note: Option<String>,
+    pub status: PreviewStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewChapter {
+    pub title: Option<String>,
+    pub quotes: Vec<PreviewQuote>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewBook {
+    /// Key of the book in an [`ImportSelection`]
+    pub key: String,
+    pub title: String,
+    pub status: PreviewStatus,
+    pub chapters: Vec<PreviewChapter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewAuthor {
+    pub name: Option<String>,
+    pub books: Vec<PreviewBook>,
+}
+
+/// What importing a source would do, see [`preview_import`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub source: ImportSource,
+    pub input: ImportInput,
+    pub authors: Vec<PreviewAuthor>,
+    pub report: ImportReport,
+}
+
+/// The books and quotes to import, by their key in the [`ImportPreview`].
+///
+/// Changed quotes are only updated when selected, an import without a selection
+/// adds the new quotes, replaces the older versions of the newer ones
+/// and leaves the others as they are.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportSelection {
+    pub books: HashSet<String>,
+    pub quotes: HashSet<String>,
+}
+
+/// A book of the source, the quotes of the preview are grouped by it.
+#[derive(Debug, Clone)]
+struct SourceBook {
+    key: String,
+    title: String,
+    author: Option<String>,
+    status: PreviewStatus,
+}
+
+/// Step of an import, sent with its progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportPhase {
+    /// Reading the source
+    Reading,
+    /// Writing the books
+    Books,
+    /// Writing the quotes and notes
+    Quotes,
+}
+
+/// Payload of the `import-progress` event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportProgress {
+    pub id: String,
+    pub phase: ImportPhase,
+    pub processed: usize,
+    pub total: usize,
+    /// Title of the book being imported
+    pub book: Option<String>,
+}
+
+/// Minimum delay between two progress reports of a phase.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The imports running in the background, by id.
+static RUNNING_IMPORTS: Lazy<Mutex<HashMap<String, ImportHandle>>> = Lazy::new(Default::default);
+
+/// An import running in the background, that can be cancelled with [`cancel_import`].
+#[derive(Debug, Clone, Default)]
+pub struct ImportHandle {
+    pub id: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ImportHandle {
+    /// A new import, known to [`cancel_import`] until [`ImportHandle::finish`].
+    pub fn register() -> Self {
+        let handle = ImportHandle {
+            id: Uuid::new_v4().to_string(),
+            cancelled: Arc::default(),
+        };
+        RUNNING_IMPORTS
+            .lock()
+            .unwrap()
+            .insert(handle.id.clone(), handle.clone());
+        handle
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Forget the import once done.
+    pub fn finish(&self) {
+        // Also called while unwinding, see `FinishGuard`, where a poisoned lock must not panic
+        RUNNING_IMPORTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.id);
+    }
+}
+
+/// Finishes the import when dropped, so it is forgotten even when its task panics.
+struct FinishGuard(ImportHandle);
+
+impl Drop for FinishGuard {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+/// Cancel the running import `id`, its transaction is rolled back.
+///
+/// Returns `false` when no such import is running.
+pub fn cancel_import(id: &str) -> bool {
+    match RUNNING_IMPORTS.lock().unwrap().get(id) {
+        Some(handle) => {
+            handle.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+type ProgressCallback = Box<dyn Fn(ImportProgress) + Send + Sync>;
+
+/// State of an import: its report, the selection to import and the preview of the source.
+#[derive(Default)]
+pub struct ImportRun {
+    pub report: ImportReport,
+    selection: Option<ImportSelection>,
+    authors: Vec<PreviewAuthor>,
+    /// The batch the rows are inserted with, see [`import_run_into_library`]
+    batch_id: Option<String>,
+    handle: ImportHandle,
+    on_progress: Option<ProgressCallback>,
+    last_progress: Option<(ImportPhase, Instant)>,
+}
+
+impl ImportRun {
+    pub fn new(selection: Option<ImportSelection>) -> Self {
+        ImportRun {
+            selection,
+            ..Default::default()
+        }
+    }
+
+    /// Report the progress of the import to `on_progress`, and stop it once `handle` is cancelled.
+    pub fn with_progress(
+        mut self,
+        handle: ImportHandle,
+        on_progress: impl Fn(ImportProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.handle = handle;
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Report `processed` items out of `total` in `phase`, at most every [`PROGRESS_INTERVAL`]
+    /// besides the first and last items.
+    ///
+    /// Fails with [`ImportError::Cancelled`] once the import is cancelled.
+    fn progress(
+        &mut self,
+        phase: ImportPhase,
+        processed: usize,
+        total: usize,
+        book: Option<&str>,
+    ) -> Result<(), ImportError> {
+        if self.handle.is_cancelled() {
+            return Err(ImportError::Cancelled);
+        }
+
+        let Some(on_progress) = &self.on_progress else {
+            return Ok(());
+        };
+        let due = match self.last_progress {
+            Some((last_phase, at)) => {
+                last_phase != phase || processed >= total || at.elapsed() >= PROGRESS_INTERVAL
             }
+            None => true,
+        };
+        if due {
+            on_progress(ImportProgress {
+                id: self.handle.id.clone(),
+                phase,
+                processed,
+                total,
+                book: book.map(str::to_string),
+            });
+            self.last_progress = Some((phase, Instant::now()));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the book with `key` is imported.
+    fn imports_book(&self, key: &str) -> bool {
+        self.selection
+            .as_ref()
+            .is_none_or(|selection| selection.books.contains(key))
+    }
+
+    /// Add `quote` of `book` to the preview and tell whether it is written.
+    ///
+    /// Duplicates are counted as skipped.
+    fn add_quote(&mut self, book: &SourceBook, chapter: Option<&str>, quote: PreviewQuote) -> bool {
+        let imported = match (quote.status, &self.selection) {
+            (PreviewStatus::Duplicate, _) => false,
+            (PreviewStatus::New, None) => true,
+            (PreviewStatus::Changed, None) => false,
+            (PreviewStatus::Newer, None) => true,
+            (_, Some(selection)) => selection.quotes.contains(&quote.key),
+        };
+        if !imported && quote.status != PreviewStatus::New {
+            self.report.quotes.skipped += 1;
         }
-        Err(e) => log::error!("Error importing from Kobo: {}", e),
+
+        let author = match self.authors.iter().position(|a| a.name == book.author) {
+            Some(i) => &mut self.authors[i],
+            None => {
+                self.authors.push(PreviewAuthor {
+                    name: book.author.clone(),
+                    books: Vec::new(),
+                });
+                self.authors.last_mut().unwrap()
+            }
+        };
+        let preview_book = match author.books.iter().position(|b| b.key == book.key) {
+            Some(i) => &mut author.books[i],
+            None => {
+                author.books.push(PreviewBook {
+                    key: book.key.clone(),
+                    title: book.title.clone(),
+                    status: book.status,
+                    chapters: Vec::new(),
+                });
+                author.books.last_mut().unwrap()
+            }
+        };
+        let preview_chapter = match preview_book
+            .chapters
+            .iter()
+            .position(|c| c.title.as_deref() == chapter)
+        {
+            Some(i) => &mut preview_book.chapters[i],
+            None => {
+                preview_book.chapters.push(PreviewChapter {
+                    title: chapter.map(str::to_string),
+                    quotes: Vec::new(),
+                });
+                preview_book.chapters.last_mut().unwrap()
+            }
+        };
+        preview_chapter.quotes.push(quote);
+
+        imported
     }
 }
 
-pub async fn import_from_kindle(app: &AppHandle) {
-    let webview = get_webview(app).expect("unable to find window");
+/// Status of a quote against the library quote with the same original id.
+fn quote_status(existing: Option<&models::Quote>, content: Option<&str>) -> PreviewStatus {
+    match existing {
+        Some(quote) if quote.content.as_deref() != content => PreviewStatus::Changed,
+        Some(_) => PreviewStatus::Duplicate,
+        None => PreviewStatus::New,
+    }
+}
 
-    match import_dialog(app, DialogImportType::Clippings).await {
-        Ok(path) => {
-            webview
-                .emit(
-                    "importing",
-                    create_payload(Some("kindle".to_string()), None),
-                )
-                .unwrap();
+/// Whether `path` is a Kindle notebook export rather than a `My Clippings.txt` file.
+fn is_kindle_notebook(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"))
+}
 
-            match import_clippings(&path).await {
-                Ok(res) => webview
-                    .emit("import-success", create_payload(None, Some(res)))
-                    .unwrap(),
-                Err(e) => {
-                    log::error!("Error importing from Kindle Clippings: {}", e);
-                    webview
-                        .emit("import-error", create_payload(None, Some(e.to_string())))
-                        .unwrap();
-                }
+/// What an import reads, a file or folder for every source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ImportInput {
+    Path(String),
+    /// Copies of the iBooks databases, e.g. from a backup
+    IBooksDatabases(IBooksDatabases),
+}
+
+impl ImportInput {
+    /// The path kept with the import batch, the annotation database for iBooks.
+    fn path(&self) -> String {
+        match self {
+            ImportInput::Path(path) => path.clone(),
+            ImportInput::IBooksDatabases(databases) => {
+                databases.annotation.to_string_lossy().to_string()
             }
         }
-        Err(e) => log::error!("Error importing from Kindle Clippings: {}", e),
     }
 }
 
-pub async fn import_from_ibooks(app: &AppHandle) {
-    let webview = get_webview(app).expect("unable to find window");
+impl From<String> for ImportInput {
+    fn from(path: String) -> Self {
+        ImportInput::Path(path)
+    }
+}
 
-    webview
-        .emit(
-            "importing",
-            create_payload(Some("ibooks".to_string()), None),
-        )
-        .unwrap();
+impl From<&str> for ImportInput {
+    fn from(path: &str) -> Self {
+        ImportInput::Path(path.to_string())
+    }
+}
+
+async fn run_import(
+    source: ImportSource,
+    input: &ImportInput,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    let path = match input {
+        ImportInput::Path(path) => path.as_str(),
+        ImportInput::IBooksDatabases(databases) if source == ImportSource::IBooks => {
+            return import_ibooks_databases(databases, run, conn).await;
+        }
+        ImportInput::IBooksDatabases(_) => {
+            return Err(ImportError::InvalidFormat(format!(
+                "iBooks databases can't be imported from {}",
+                source.name()
+            )));
+        }
+    };
+
+    match source {
+        ImportSource::Kobo => import_kobo(path, run, conn).await,
+        ImportSource::Kindle if is_kindle_notebook(path) => {
+            import_kindle_notebook(path, run, conn).await
+        }
+        ImportSource::Kindle => import_clippings(path, run, conn).await,
+        ImportSource::IBooks => import_ibooks(path, run, conn).await,
+        ImportSource::Readwise => import_readwise(path, run, conn).await,
+        ImportSource::KOReader => import_koreader(path, run, conn).await,
+        ImportSource::Calibre => import_calibre(path, run, conn).await,
+    }
+}
+
+/// Import `input` from `source` through `conn`, without any window or dialog.
+///
+/// Only the books and quotes of `selection` are imported when given.
+/// The caller owns the transaction, nothing is committed here.
+pub async fn import_path(
+    source: ImportSource,
+    input: ImportInput,
+    selection: Option<ImportSelection>,
+    conn: &mut SqliteConnection,
+) -> Result<ImportReport, ImportError> {
+    let mut run = ImportRun::new(selection);
+    run_import(source, &input, &mut run, conn).await?;
+
+    Ok(run.report)
+}
+
+/// Preview importing `input` from `source`, see [`preview_run`].
+pub async fn preview_import(
+    source: ImportSource,
+    input: ImportInput,
+    pool: &SqlitePool,
+) -> Result<ImportPreview, ImportError> {
+    preview_run(source, input, ImportRun::default(), pool).await
+}
+
+/// The search index triggers, dropped in the preview snapshot.
+const FTS_TRIGGERS: [&str; 4] = [
+    "quote_fts_after_insert",
+    "quote_fts_before_update",
+    "quote_fts_after_update",
+    "quote_fts_before_delete",
+];
+
+/// Same as [`preview_import`] for a prepared `run`, e.g. one that can be cancelled.
+///
+/// The import runs on a snapshot of the library, copied with `VACUUM INTO` to a temporary
+/// file and deleted after, so the library is neither locked nor written.
+pub async fn preview_run(
+    source: ImportSource,
+    input: ImportInput,
+    mut run: ImportRun,
+    pool: &SqlitePool,
+) -> Result<ImportPreview, ImportError> {
+    let snapshot_path =
+        std::env::temp_dir().join(format!("liture-preview-{}.db", Uuid::new_v4()));
+    sqlx::query("VACUUM INTO ?")
+        .bind(snapshot_path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| ImportError::DbError(e, "Failed to copy the library".to_string()))?;
+
+    let result = preview_snapshot(source, &input, &mut run, &snapshot_path).await;
+    if let Err(e) = std::fs::remove_file(&snapshot_path) {
+        log::warn!("Failed to remove preview snapshot {}: {}", snapshot_path.display(), e);
+    }
+    result?;
+
+    Ok(ImportPreview {
+        source,
+        input,
+        authors: run.authors,
+        report: run.report,
+    })
+}
 
-    match import_ibooks().await {
-        Ok(res) => webview
-            .emit("import-success", create_payload(None, Some(res)))
-            .unwrap(),
+/// Run the import of a preview on the library copy at `snapshot_path`.
+async fn preview_snapshot(
+    source: ImportSource,
+    input: &ImportInput,
+    run: &mut ImportRun,
+    snapshot_path: &Path,
+) -> Result<(), ImportError> {
+    let snapshot = SqlitePool::connect_with(
+        sqlx::sqlite::SqliteConnectOptions::new().filename(snapshot_path),
+    )
+    .await
+    .map_err(|e| ImportError::DbError(e, "Failed to open the library copy".to_string()))?;
+
+    let result = async {
+        let mut conn = snapshot
+            .acquire()
+            .await
+            .map_err(|e| ImportError::DbError(e, "Failed to open the library copy".to_string()))?;
+        // Nothing searches the copy, no need to index what the preview writes
+        for trigger in FTS_TRIGGERS {
+            sqlx::query(&format!("DROP TRIGGER IF EXISTS {}", trigger))
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to drop trigger".to_string()))?;
+        }
+        match run_import(source, input, run, &mut conn).await {
+            Ok(()) if run.handle.is_cancelled() => Err(ImportError::Cancelled),
+            result => result,
+        }
+    }
+    .await;
+    snapshot.close().await;
+
+    result
+}
+
+/// Import `input` from `source` into the library in a single transaction.
+///
+/// The import is kept as a batch with its report, the rows it creates are tagged with it
+/// so it can be rolled back, see [`queries::rollback_import_batch`].
+pub async fn import_into_library(
+    source: ImportSource,
+    input: ImportInput,
+    selection: Option<ImportSelection>,
+    pool: &SqlitePool,
+) -> Result<ImportReport, ImportError> {
+    import_run_into_library(source, input, ImportRun::new(selection), pool).await
+}
+
+/// Same as [`import_into_library`] for a prepared `run`, e.g. one reporting its progress.
+///
+/// The transaction is rolled back when the run fails or is cancelled.
+pub async fn import_run_into_library(
+    source: ImportSource,
+    input: ImportInput,
+    mut run: ImportRun,
+    pool: &SqlitePool,
+) -> Result<ImportReport, ImportError> {
+    let path = input.path();
+    let file_hash = file_hash(&path)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ImportError::DbError(e, "Failed to begin transaction".to_string()))?;
+    let batch = queries::insert_import_batch(
+        source.device(),
+        &path,
+        file_hash.as_deref(),
+        &run.report,
+        &mut *tx,
+    )
+    .await
+    .map_err(|e| ImportError::DbError(e, "Failed to save import batch".to_string()))?;
+    run.batch_id = Some(batch.id.clone());
+
+    let result = match run_import(source, &input, &mut run, &mut tx).await {
+        Ok(()) if run.handle.is_cancelled() => Err(ImportError::Cancelled),
+        result => result,
+    };
+    if let Err(e) = result {
+        tx.rollback()
+            .await
+            .map_err(|e| ImportError::DbError(e, "Failed to rollback transaction".to_string()))?;
+        return Err(e);
+    }
+
+    queries::update_import_batch_report(&batch.id, &run.report, &mut *tx)
+        .await
+        .map_err(|e| ImportError::DbError(e, "Failed to save import batch".to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| ImportError::DbError(e, "Failed to commit transaction".to_string()))?;
+
+    Ok(run.report)
+}
+
+/// SHA-256 of the file at `path`, `None` for directories.
+fn file_hash(path: &str) -> Result<Option<String>, ImportError> {
+    use sha2::{Digest, Sha256};
+
+    if !Path::new(path).is_file() {
+        return Ok(None);
+    }
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+/// The path to import from `source`, picked by the user with a dialog.
+///
+/// iBooks uses its default container.
+pub async fn pick_source_path(app: &AppHandle, source: ImportSource) -> Result<String, String> {
+    match source.dialog_type() {
+        Some(dialog_type) => import_dialog(app, dialog_type).await,
+        None => ibooks_documents_dir()
+            .map(|p| p.to_string_lossy().to_string())
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// Start importing from `source` in the background, see [`import_from_source`].
+///
+/// Returns the id of the import, to match its events and cancel it.
+pub fn spawn_import(
+    app: AppHandle,
+    source: ImportSource,
+    input: Option<ImportInput>,
+    selection: Option<ImportSelection>,
+) -> String {
+    let handle = ImportHandle::register();
+    let id = handle.id.clone();
+
+    tokio::spawn(async move {
+        import_from_source(&app, handle, source, input, selection).await;
+    });
+
+    id
+}
+
+/// Import from `source` and report the progress to the main window.
+///
+/// Without an `input` the user picks a path, see [`pick_source_path`].
+/// The import stops when `handle` is cancelled, see [`cancel_import`].
+pub async fn import_from_source(
+    app: &AppHandle,
+    handle: ImportHandle,
+    source: ImportSource,
+    input: Option<ImportInput>,
+    selection: Option<ImportSelection>,
+) {
+    let _finish = FinishGuard(handle.clone());
+    let Some(webview) = get_webview(app) else {
+        log::error!("Error importing from {}: main window not found", source.name());
+        return;
+    };
+    let emit = |event: &str, payload: Payload| {
+        if let Err(e) = webview.emit(event, payload) {
+            log::error!("Failed to send {} event: {}", event, e);
+        }
+    };
+
+    let input = match input {
+        Some(input) => input,
+        None => match pick_source_path(app, source).await {
+            Ok(path) => path.into(),
+            Err(e) => {
+                log::error!("Error importing from {}: {}", source.name(), e);
+                return;
+            }
+        },
+    };
+
+    let id = Some(handle.id.clone());
+    emit(
+        "importing",
+        Payload {
+            id: id.clone(),
+            ..create_payload(Some(source.device().to_string()), None)
+        },
+    );
+
+    let progress_webview = webview.clone();
+    let run = ImportRun::new(selection).with_progress(handle.clone(), move |progress| {
+        let _ = progress_webview.emit("import-progress", progress);
+    });
+
+    match import_run_into_library(source, input, run, db::get_pool()).await {
+        Ok(report) => {
+            log::info!("Import result: {}", report);
+            let payload = Payload {
+                id,
+                report: Some(report.clone()),
+                ..create_payload(None, Some(report.to_string()))
+            };
+            emit("import-success", payload);
+        }
+        Err(ImportError::Cancelled) => {
+            log::info!("Import from {} cancelled", source.name());
+            let payload = Payload {
+                id,
+                ..create_payload(None, Some(ImportError::Cancelled.to_string()))
+            };
+            emit("import-cancelled", payload);
+        }
         Err(e) => {
-            log::error!("Error importing from iBooks: {}", e);
-            webview
-                .emit("import-error", create_payload(None, Some(e.to_string())))
-                .unwrap();
+            log::error!("Error importing from {}: {}", source.name(), e);
+            let payload = Payload {
+                id,
+                ..create_payload(None, Some(e.to_string()))
+            };
+            emit("import-error", payload);
         }
     }
 }
 
+/// Preview importing from `source`, reporting the progress to the main window.
+///
+/// Without an `input` the user picks a path, see [`pick_source_path`].
+/// The preview stops when cancelled with the id of its progress events, see [`cancel_import`].
+pub async fn preview_from_source(
+    app: &AppHandle,
+    source: ImportSource,
+    input: Option<ImportInput>,
+) -> Result<ImportPreview, String> {
+    let input = match input {
+        Some(input) => input,
+        None => pick_source_path(app, source).await?.into(),
+    };
+
+    let handle = ImportHandle::register();
+    let _finish = FinishGuard(handle.clone());
+    let webview = get_webview(app);
+    let run = ImportRun::default().with_progress(handle, move |progress| {
+        if let Some(webview) = &webview {
+            let _ = webview.emit("import-progress", progress);
+        }
+    });
+
+    preview_run(source, input, run, db::get_pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// The type of import that have a Dialog.
 ///
 /// * `Kobo` - Import from Kobo.
-/// * `Clippings` - Import from clippings.
+/// * `Clippings` - Import from Kindle clippings or a Kindle notebook export.
+/// * `Readwise` - Import from a Readwise CSV export.
+/// * `KOReader` - Import from a folder of KOReader sidecars.
+/// * `Calibre` - Import from a Calibre library folder.
 pub enum DialogImportType {
     Kobo,
     Clippings,
+    Readwise,
+    KOReader,
+    Calibre,
 }
 
 /// Open a file dialog and return the path of the selected file.
@@ -142,23 +826,31 @@ pub async fn import_dialog(
 
     let window = app.get_window("main").ok_or("No main window found")?;
 
-    let (dialog_name, dialog_extension) = match import_type {
-        DialogImportType::Kobo => ("KoboReader", &["sqlite"]),
-        DialogImportType::Clippings => ("Clippings", &["txt"]),
-    };
-
-    let dialog = FileDialogBuilder::new(app.dialog().clone())
-        .set_parent(&window)
-        .add_filter(dialog_name, dialog_extension);
-
-    dialog.pick_file(move |file_path| {
-        let result = match file_path {
+    let send_result = move |path: Option<tauri_plugin_dialog::FilePath>| {
+        let result = match path {
             Some(path) => Ok(path.to_string()),
             None => Err("No file selected".to_string()),
         };
         // Send the result through the channel (ignore send errors if receiver is dropped)
         let _ = tx.send(result);
-    });
+    };
+
+    let dialog = FileDialogBuilder::new(app.dialog().clone()).set_parent(&window);
+
+    let (dialog_name, dialog_extension): (&str, Option<&[&str]>) = match import_type {
+        DialogImportType::Kobo => ("KoboReader", Some(&["sqlite"])),
+        // My Clippings.txt, or the HTML of "Export Notebook" in the Kindle apps
+        DialogImportType::Clippings => ("Clippings", Some(&["txt", "html", "htm"])),
+        DialogImportType::Readwise => ("Readwise", Some(&["csv"])),
+        // KOReader sidecars are spread across a folder
+        DialogImportType::KOReader => ("KOReader folder", None),
+        DialogImportType::Calibre => ("Calibre library", None),
+    };
+
+    match dialog_extension {
+        Some(extension) => dialog.add_filter(dialog_name, extension).pick_file(send_result),
+        None => dialog.set_title(dialog_name).pick_folder(send_result),
+    }
 
     // Await the result from the channel
     rx.await.map_err(|_| "Dialog channel error".to_string())?
@@ -208,10 +900,11 @@ fn core_data_to_naive_datetime(core_data_ts: f64) -> Option<NaiveDateTime> {
 }
 
 #[derive(Debug)]
-enum ImportError {
+pub enum ImportError {
     IoError(io::Error),
     DbError(sqlx::Error, String),
     InvalidFormat(String),
+    Cancelled,
 }
 
 impl From<io::Error> for ImportError {
@@ -238,14 +931,59 @@ impl std::fmt::Display for ImportError {
                 }
             }
             ImportError::InvalidFormat(msg) => write!(f, "{}", msg),
+            ImportError::Cancelled => write!(f, "Import cancelled"),
         }
     }
 }
 
+/// Hash of `parts`, for records of sources without ids.
+fn hash_parts(parts: &[&str]) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.trim().as_bytes());
+        hasher.update([0x1f]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Id of the author named `name`, inserted if missing.
 ///
-/// Kobo
-///
-///
+/// `authors_id_map` holds the authors already seen by the import, so each is counted once.
+async fn get_or_insert_author(
+    name: &str,
+    authors_id_map: &mut HashMap<String, String>,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<String, ImportError> {
+    if let Some(author_id) = authors_id_map.get(name) {
+        return Ok(author_id.clone());
+    }
+
+    let author = match queries::get_author_by_name(name.to_string(), &mut *conn).await {
+        Ok(existing_author) => {
+            run.report.authors.skipped += 1;
+            existing_author
+        }
+        Err(_) => {
+            let batch_id = run.batch_id.as_deref();
+            let author = queries::insert_imported_author(name.to_string(), batch_id, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to insert author".to_string()))?;
+            run.report.authors.created += 1;
+            author
+        }
+    };
+
+    authors_id_map.insert(name.to_string(), author.id.clone());
+    Ok(author.id)
+}
+
+//
+// Kobo
+//
+//
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 struct KoboBook {
@@ -276,104 +1014,244 @@ struct KoboQuote {
     chapter: String,
     bookmark_id: String,
     item_type: String,
+    color: Option<i64>,
 }
 
-const QUERY_KOBO_BOOKS_AUTHORS: &str = r#"
-    SELECT DISTINCT
-        b.VolumeID as volume_id,
-        c.Title as title,
-        c.Attribution as author
-    FROM
-        Bookmark b
-        INNER JOIN content c ON b.VolumeID = c.ContentID
-    ORDER BY
-        c.Title;
-"#;
-
-const QUERY_KOBO_CHAPTERS: &str = r#"
-    SELECT DISTINCT
-    	c.ContentID as content_id,
-    	c.BookID as book_id,
-    	c.Booktitle as book_title,
-        c.Title as title,
-        c.VolumeIndex as volume_index
-    FROM
-        content c
-    WHERE c.BookID = ?
-    ORDER BY c.VolumeIndex;
-"#;
-
-const QUERY_KOBO_ITEMS_V175: &str = r#"
-    SELECT 
-        b.VolumeID as volume_id,
-        c.ContentID as content_id,
-        b.Text as text, 
-        b.Annotation as annotation, 
-        b.DateCreated as date_created, 
-        b.DateModified as date_modified, 
-        b.ChapterProgress as chapter_progress,
-        c.BookTitle as book_title, 
-        c.Title as chapter, 
-        b.BookmarkID as bookmark_id,
-        b.Type as item_type
-    FROM Bookmark b INNER JOIN content c
-    ON b.VolumeID = c.BookID 
-    GROUP BY b.DateCreated 
-    ORDER BY b.ChapterProgress ASC, b.DateCreated ASC;
-"#;
-
-const QUERY_KOBO_ITEMS_V174: &str = r#"
-    SELECT 
-        b.VolumeID as volume_id,
-        c.ContentID as content_id,
-        b.Text as text, 
-        b.Annotation as annotation, 
-        b.DateCreated as date_created, 
-        b.DateModified as date_modified,
-        b.ChapterProgress as chapter_progress, 
-        c.BookTitle as book_title, 
-        c.Title as chapter, 
-        b.BookmarkID as bookmark_id,
-        b.Type as item_type
-    FROM Bookmark b LEFT JOIN content c
-    ON b.ContentID = c.ContentID 
-    GROUP BY b.DateCreated 
-    ORDER BY b.ChapterProgress ASC, b.DateCreated ASC;
-"#;
+impl KoboQuote {
+    /// Kind of the [`models::Bookmark`] for dog-ears and bookmarks, which have no text.
+    fn bookmark_kind(&self) -> Option<&'static str> {
+        let blank = |text: &Option<String>| text.as_deref().is_none_or(|t| t.trim().is_empty());
+        match self.item_type.as_str() {
+            "dogear" => Some("dogear"),
+            "bookmark" => Some("bookmark"),
+            _ if blank(&self.text) && blank(&self.annotation) => Some("bookmark"),
+            _ => None,
+        }
+    }
 
-/// Import books from Kobo.
-///
-/// # Arguments
-///
-/// * `str_path` - The path to the Kobo database file.
-///
-/// # Returns
-///
-/// `Result<String, String>` - A success message or an error.
-async fn import_kobo(str_path: &str) -> Result<String, ImportError> {
-    let path = Path::new(str_path);
-    if !path.exists() || !path.is_file() {
-        return Err(ImportError::IoError(io::Error::new(
-            io::ErrorKind::NotFound,
-            "Database file not found",
-        )));
+    /// Highlight color, set by firmware with colored highlights.
+    fn color(&self) -> Option<&'static str> {
+        match self.color? {
+            0 => Some("yellow"),
+            1 => Some("pink"),
+            2 => Some("blue"),
+            3 => Some("green"),
+            _ => None,
+        }
     }
+}
 
-    let kobo_file_db_conn = SqlitePool::connect(&format!("sqlite:{}", str_path))
-        .await
-        .expect("Failed to connect to database");
+/// Tables and columns of a Kobo database, which change between firmware versions.
+///
+/// The queries are built from what the database has rather than from its `DbVersion`,
+/// so older and newer firmware read the same way.
+#[derive(Debug, Default)]
+struct KoboSchema {
+    version: Option<i64>,
+    bookmark: HashSet<String>,
+    content: HashSet<String>,
+    /// Whether bookmarks point to the content row of their chapter, as before `DbVersion` 175.
+    chapter_content_ids: bool,
+}
 
-    // Kobo has changed the database saving format between versions,
-    // so we need to use two different queries to fetch the data.
-    // Currently supported versions are: 174, 175.
-    let db_version: i32 = sqlx::query("SELECT version FROM DbVersion;")
-        .fetch_one(&kobo_file_db_conn)
+const KOBO_BOOKMARK_COLUMNS: &[&str] =
+    &["BookmarkID", "VolumeID", "ContentID", "Text", "DateCreated"];
+const KOBO_CONTENT_COLUMNS: &[&str] = &["ContentID", "BookID", "Title"];
+
+impl KoboSchema {
+    /// Read the schema of the Kobo database `db`.
+    ///
+    /// Fails when it misses the tables or columns every firmware has.
+    async fn probe(db: &SqlitePool) -> Result<Self, ImportError> {
+        let bookmark = Self::columns("Bookmark", db).await?;
+        let content = Self::columns("content", db).await?;
+        for (table, columns, required) in [
+            ("Bookmark", &bookmark, KOBO_BOOKMARK_COLUMNS),
+            ("content", &content, KOBO_CONTENT_COLUMNS),
+        ] {
+            if let Some(missing) = required.iter().find(|c| !columns.contains(**c)) {
+                return Err(ImportError::InvalidFormat(format!(
+                    "Unsupported Kobo database: missing column {}.{}",
+                    table, missing
+                )));
+            }
+        }
+
+        // Older databases have no version table
+        let version = sqlx::query_scalar("SELECT version FROM DbVersion")
+            .fetch_optional(db)
+            .await
+            .ok()
+            .flatten();
+
+        let chapter_content_ids = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1 FROM Bookmark b INNER JOIN content c ON b.ContentID = c.ContentID
+            )",
+        )
+        .fetch_one(db)
         .await
-        .map_err(|e| ImportError::DbError(e, "Failed to get Kobo database version".to_string()))?
-        .get(0);
+        .map_err(|e| ImportError::DbError(e, "Failed to read Kobo bookmarks".to_string()))?;
+
+        Ok(KoboSchema {
+            version,
+            bookmark,
+            content,
+            chapter_content_ids,
+        })
+    }
+
+    async fn columns(table: &str, db: &SqlitePool) -> Result<HashSet<String>, ImportError> {
+        let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(db)
+            .await
+            .map_err(|e| ImportError::DbError(e, "Not a Kobo database".to_string()))?;
+        if columns.is_empty() {
+            return Err(ImportError::InvalidFormat(format!(
+                "Not a Kobo database: missing table {}",
+                table
+            )));
+        }
+
+        Ok(columns.into_iter().collect())
+    }
+
+    /// `b.column` when bookmarks have `column`, `fallback` otherwise.
+    fn bookmark_column(&self, column: &str, fallback: &str) -> String {
+        if self.bookmark.contains(column) {
+            format!("b.{}", column)
+        } else {
+            fallback.to_string()
+        }
+    }
+
+    /// `c.column` when contents have `column`, `fallback` otherwise.
+    fn content_column(&self, column: &str, fallback: &str) -> String {
+        if self.content.contains(column) {
+            format!("c.{}", column)
+        } else {
+            fallback.to_string()
+        }
+    }
+
+    fn books_query(&self) -> String {
+        format!(
+            "SELECT DISTINCT
+                b.VolumeID as volume_id,
+                c.Title as title,
+                {} as author
+            FROM Bookmark b
+            INNER JOIN content c ON b.VolumeID = c.ContentID
+            ORDER BY c.Title",
+            self.content_column("Attribution", "NULL"),
+        )
+    }
+
+    fn chapters_query(&self) -> String {
+        format!(
+            "SELECT DISTINCT
+                c.ContentID as content_id,
+                c.BookID as book_id,
+                COALESCE({}, '') as book_title,
+                COALESCE(c.Title, '') as title,
+                COALESCE({}, 0) as volume_index
+            FROM content c
+            WHERE c.BookID = ?
+            ORDER BY volume_index",
+            self.content_column("BookTitle", "NULL"),
+            self.content_column("VolumeIndex", "NULL"),
+        )
+    }
+
+    /// The highlights and notes, with their chapter.
+    fn items_query(&self) -> String {
+        // Newer bookmarks point to a part of the chapter, matched to the book instead
+        let join = if self.chapter_content_ids {
+            "LEFT JOIN content c ON b.ContentID = c.ContentID"
+        } else {
+            "INNER JOIN content c ON b.VolumeID = c.BookID"
+        };
+        // Without a type, bookmarks with an annotation are notes
+        let untyped = if self.bookmark.contains("Annotation") {
+            "CASE WHEN COALESCE(b.Annotation, '') <> '' THEN 'note' ELSE 'highlight' END"
+        } else {
+            "'highlight'"
+        };
+        let item_type = self.bookmark_column("Type", untyped);
+        // Highlights removed on the device are kept hidden until synced
+        let hidden = if self.bookmark.contains("Hidden") {
+            "WHERE COALESCE(b.Hidden, 'false') NOT IN ('true', '1')"
+        } else {
+            ""
+        };
+
+        format!(
+            "SELECT
+                b.VolumeID as volume_id,
+                COALESCE(c.ContentID, b.ContentID) as content_id,
+                b.Text as text,
+                {annotation} as annotation,
+                b.DateCreated as date_created,
+                {date_modified} as date_modified,
+                COALESCE({chapter_progress}, 0.0) as chapter_progress,
+                COALESCE({book_title}, '') as book_title,
+                COALESCE(c.Title, '') as chapter,
+                b.BookmarkID as bookmark_id,
+                {item_type} as item_type,
+                {color} as color
+            FROM Bookmark b {join}
+            {hidden}
+            GROUP BY b.BookmarkID
+            ORDER BY chapter_progress ASC, b.DateCreated ASC",
+            annotation = self.bookmark_column("Annotation", "NULL"),
+            date_modified = self.bookmark_column("DateModified", "NULL"),
+            chapter_progress = self.bookmark_column("ChapterProgress", "NULL"),
+            book_title = self.content_column("BookTitle", "NULL"),
+            color = self.bookmark_column("Color", "NULL"),
+        )
+    }
+}
+
+/// Import books from Kobo.
+///
+/// The database is opened read-only, the firmware versions it may come from are
+/// handled by [`KoboSchema`].
+///
+/// # Arguments
+///
+/// * `str_path` - The path to the Kobo database file.
+/// * `conn` - The connection the library is written through.
+///
+/// # Returns
+///
+/// `Result<ImportReport, ImportError>` - The import report or an error.
+pub async fn import_kobo(
+    str_path: &str,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    let path = Path::new(str_path);
+    if !path.exists() || !path.is_file() {
+        return Err(ImportError::IoError(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Database file not found",
+        )));
+    }
+
+    let kobo_file_db_conn = SqlitePool::connect_with(
+        sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(path)
+            .read_only(true),
+    )
+    .await
+    .map_err(|e| ImportError::DbError(e, "Failed to open Kobo database".to_string()))?;
+
+    let schema = KoboSchema::probe(&kobo_file_db_conn).await?;
+    log::info!("Kobo database version: {:?}", schema.version);
 
     // Fetch all the books with their authors first.
-    let books = sqlx::query_as::<_, KoboBook>(QUERY_KOBO_BOOKS_AUTHORS)
+    let books = sqlx::query_as::<_, KoboBook>(&schema.books_query())
         .fetch_all(&kobo_file_db_conn)
         .await
         .map_err(|e| ImportError::DbError(e, "Failed to fetch books".to_string()))?;
@@ -382,8 +1260,10 @@ async fn import_kobo(str_path: &str) -> Result<String, ImportError> {
     let mut chapters = HashMap::new();
 
     // Loop through all books and fetch their chapters.
-    for book in books.iter() {
-        let book_chapters = sqlx::query_as::<_, KoboChapter>(QUERY_KOBO_CHAPTERS)
+    let chapters_query = schema.chapters_query();
+    for (i, book) in books.iter().enumerate() {
+        run.progress(ImportPhase::Reading, i + 1, books.len(), Some(&book.title))?;
+        let book_chapters = sqlx::query_as::<_, KoboChapter>(&chapters_query)
             .bind(book.volume_id.clone())
             .fetch_all(&kobo_file_db_conn)
             .await
@@ -392,74 +1272,72 @@ async fn import_kobo(str_path: &str) -> Result<String, ImportError> {
     }
 
     // Fetch all the highlights/notes.
-    let items = if db_version >= 175 {
-        sqlx::query_as::<_, KoboQuote>(QUERY_KOBO_ITEMS_V175)
-            .fetch_all(&kobo_file_db_conn)
-            .await
-            .map_err(|e| ImportError::DbError(e, "Failed to fetch items".to_string()))?
-    } else {
-        sqlx::query_as::<_, KoboQuote>(QUERY_KOBO_ITEMS_V174)
-            .fetch_all(&kobo_file_db_conn)
-            .await
-            .map_err(|e| ImportError::DbError(e, "Failed to fetch items".to_string()))?
-    };
+    let items = sqlx::query_as::<_, KoboQuote>(&schema.items_query())
+        .fetch_all(&kobo_file_db_conn)
+        .await
+        .map_err(|e| ImportError::DbError(e, "Failed to fetch items".to_string()))?;
 
     kobo_file_db_conn.close().await;
 
-    let pool = db::get_pool();
-    let mut tx = pool
-        .begin()
-        .await
-        .map_err(|e| ImportError::DbError(e, "Failed to begin transaction".to_string()))?;
-
     // Map of chapter id to chapter.
     let mut chapters_id_map = HashMap::new();
     // Map of book id to book.
     let mut books_id_map = HashMap::new();
-    // Map of author name to author.
+    // Map of book id to author.
     let mut authors_id_map = HashMap::new();
-    let mut imported_books = 0;
-    let mut imported_quotes = 0;
+    // Map of author name to author.
+    let mut author_names_map = HashMap::new();
+    // Map of book id to the book in the preview.
+    let mut source_books = HashMap::new();
+
+    for (i, book) in books.iter().enumerate() {
+        run.progress(ImportPhase::Books, i + 1, books.len(), Some(&book.title))?;
+        let mut source_book = SourceBook {
+            key: book.volume_id.clone(),
+            title: book.title.clone(),
+            author: book.author.clone(),
+            status: PreviewStatus::New,
+        };
 
-    for book in books.iter() {
         // Skip if book already exists
         if let Ok(existing_book) =
-            queries::get_book_by_original_id(book.volume_id.clone(), &mut *tx).await
+            queries::get_book_by_original_id(book.volume_id.clone(), &mut *conn).await
         {
             books_id_map.insert(book.volume_id.clone(), existing_book.id.clone());
+            run.report.books.skipped += 1;
+            source_book.status = PreviewStatus::Duplicate;
+            source_books.insert(book.volume_id.clone(), source_book);
+            continue;
+        }
+
+        source_books.insert(book.volume_id.clone(), source_book);
+        if !run.imports_book(&book.volume_id) {
             continue;
         }
 
         // Author
-        let author_id = match book.author.clone() {
-            Some(book_author) => {
-                let author = match queries::get_author_by_name(book_author.clone(), &mut *tx).await
-                {
-                    Ok(existing_author) => existing_author,
-                    Err(_) => queries::insert_author(book_author.clone(), &mut *tx)
-                        .await
-                        .map_err(|e| {
-                            ImportError::DbError(e, "Failed to insert author".to_string())
-                        })?,
-                };
-                Some(author.id)
-            }
+        let author_id = match &book.author {
+            Some(book_author) => Some(
+                get_or_insert_author(book_author, &mut author_names_map, run, &mut *conn)
+                    .await?,
+            ),
             None => None,
         };
 
         authors_id_map.insert(book.volume_id.clone(), author_id.clone());
 
         // Book
-        let db_book = queries::insert_book_with_defaults(
+        let db_book = queries::insert_imported_book(
             book.title.clone(),
             author_id.clone(),
             Some(book.volume_id.clone()),
-            &mut *tx,
+            run.batch_id.as_deref(),
+            &mut *conn,
         )
         .await
         .map_err(|e| ImportError::DbError(e, "Failed to insert book".to_string()))?;
 
-        imported_books += 1;
+        run.report.books.created += 1;
 
         books_id_map.insert(book.volume_id.clone(), db_book.id.clone());
 
@@ -470,9 +1348,10 @@ async fn import_kobo(str_path: &str) -> Result<String, ImportError> {
         for chapter in book_chapters.iter() {
             // Skip if chapter already exists
             if let Ok(existing_chapter) =
-                queries::get_chapter_by_original_id(chapter.content_id.clone(), &mut *tx).await
+                queries::get_chapter_by_original_id(chapter.content_id.clone(), &mut *conn).await
             {
                 chapters_id_map.insert(chapter.content_id.clone(), existing_chapter.id.clone());
+                run.report.chapters.skipped += 1;
                 continue;
             }
 
@@ -485,18 +1364,94 @@ async fn import_kobo(str_path: &str) -> Result<String, ImportError> {
                 created_at: now,
                 updated_at: now,
                 deleted_at: None,
+                import_batch_id: run.batch_id.clone(),
             };
 
-            let _ = queries::insert_chapter(&new_chapter, &mut *tx)
+            let _ = queries::insert_chapter(&new_chapter, &mut *conn)
                 .await
                 .map_err(|e| ImportError::DbError(e, "Failed to insert chapter".to_string()))?;
             chapters_id_map.insert(chapter.content_id.clone(), new_chapter.id.clone());
+            run.report.chapters.created += 1;
         }
     }
 
-    for item in items.iter() {
-        // Skip if quote already exists
-        if let Ok(_) = queries::get_quote_by_original_id(item.bookmark_id.clone(), &mut *tx).await {
+    for (i, item) in items.iter().enumerate() {
+        run.progress(ImportPhase::Quotes, i + 1, items.len(), Some(&item.book_title))?;
+        let book = source_books
+            .get(&item.volume_id)
+            .cloned()
+            .unwrap_or_else(|| SourceBook {
+                key: item.volume_id.clone(),
+                title: item.book_title.clone(),
+                author: None,
+                status: PreviewStatus::New,
+            });
+        if !run.imports_book(&book.key) {
+            continue;
+        }
+
+        let location = format!("bookmark {}", item.bookmark_id);
+
+        // Dog-ears and bookmarks have no text, they are not quotes
+        if let Some(kind) = item.bookmark_kind() {
+            if queries::get_bookmark_by_original_id(&item.bookmark_id, &mut *conn)
+                .await
+                .is_ok()
+            {
+                run.report.bookmarks.skipped += 1;
+                continue;
+            }
+            let created_at = match parse_datetime(&item.date_created) {
+                Ok(created_at) => created_at,
+                Err(e) => {
+                    run.report.error(location, format!("Error parsing datetime => {}", e));
+                    continue;
+                }
+            };
+            let bookmark = models::Bookmark {
+                id: Uuid::new_v4().to_string(),
+                book_id: books_id_map.get(&item.volume_id).cloned(),
+                chapter_id: chapters_id_map.get(&item.content_id).cloned(),
+                chapter_progress: Some(item.chapter_progress),
+                kind: kind.to_string(),
+                created_at,
+                updated_at: item
+                    .date_modified
+                    .as_deref()
+                    .and_then(|d| parse_datetime(d).ok())
+                    .unwrap_or(created_at),
+                original_id: Some(item.bookmark_id.clone()),
+                import_batch_id: run.batch_id.clone(),
+            };
+            queries::insert_bookmark(&bookmark, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to insert bookmark".to_string()))?;
+            run.report.bookmarks.created += 1;
+            continue;
+        }
+
+        // Skip if quote already exists, unless it changed and is selected
+        let existing = queries::get_quote_by_original_id(item.bookmark_id.clone(), &mut *conn)
+            .await
+            .ok();
+        let preview = PreviewQuote {
+            key: item.bookmark_id.clone(),
+            content: item.text.clone(),
+            note: item.annotation.clone().filter(|_| item.item_type == "note"),
+            status: quote_status(existing.as_ref(), item.text.as_deref()),
+        };
+        if !run.add_quote(&book, Some(&item.chapter), preview) {
+            continue;
+        }
+        if let Some(existing) = existing {
+            queries::update_quote_content(
+                &existing.id,
+                item.text.as_deref().unwrap_or_default(),
+                &mut *conn,
+            )
+            .await
+            .map_err(|e| ImportError::DbError(e, "Failed to update quote".to_string()))?;
+            run.report.quotes.updated += 1;
             continue;
         }
 
@@ -508,12 +1463,19 @@ async fn import_kobo(str_path: &str) -> Result<String, ImportError> {
             log::debug!("Raw date_modified from Kobo: {}", date_modified);
         }
 
-        let created_at = parse_datetime(&item.date_created)
-            .map_err(|e| ImportError::InvalidFormat(format!("Error parsing datetime => {}", e)))?;
-        let updated_at = match item.date_modified.clone() {
-            Some(date_modified) => parse_datetime(&date_modified).map_err(|e| {
-                ImportError::InvalidFormat(format!("Error parsing datetime => {}", e))
-            })?,
+        let created_at = match parse_datetime(&item.date_created) {
+            Ok(created_at) => created_at,
+            Err(e) => {
+                run.report.error(location, format!("Error parsing datetime => {}", e));
+                continue;
+            }
+        };
+        let updated_at = match item.date_modified.as_deref().map(parse_datetime) {
+            Some(Ok(updated_at)) => updated_at,
+            Some(Err(e)) => {
+                run.report.warning(location, format!("Error parsing modified datetime => {}", e));
+                created_at
+            }
             None => created_at,
         };
 
@@ -537,13 +1499,19 @@ async fn import_kobo(str_path: &str) -> Result<String, ImportError> {
             imported_at: Some(now),
             deleted_at: None,
             original_id: Some(item.bookmark_id.clone()),
+            color: item.color().map(str::to_string),
+            page_start: None,
+            page_end: None,
+            location_start: None,
+            location_end: None,
+            import_batch_id: run.batch_id.clone(),
         };
 
-        let db_quote = queries::insert_quote(&quote, &mut *tx)
+        let db_quote = queries::insert_quote(&quote, &mut *conn)
             .await
             .map_err(|e| ImportError::DbError(e, "Failed to insert quote".to_string()))?;
 
-        imported_quotes += 1;
+        run.report.quotes.created += 1;
 
         // Note
         if item.item_type == "note" && item.annotation.is_some() {
@@ -556,40 +1524,450 @@ async fn import_kobo(str_path: &str) -> Result<String, ImportError> {
                 created_at,
                 updated_at,
                 deleted_at: None,
+                import_batch_id: run.batch_id.clone(),
             };
 
-            queries::insert_note(&note, &mut *tx)
+            queries::insert_note(&note, &mut *conn)
                 .await
                 .map_err(|e| ImportError::DbError(e, "Failed to insert note".to_string()))?;
+
+            run.report.notes.created += 1;
         }
     }
 
-    tx.commit()
-        .await
-        .map_err(|e| ImportError::DbError(e, "Failed to commit transaction".to_string()))?;
-
-    Ok(format!(
-        "Imported successfully {} new books and {} new quotes",
-        imported_books, imported_quotes
-    ))
+    Ok(())
 }
 
-///
-/// Kindle My Clippings.txt
-///
-///
+//
+// Kindle My Clippings.txt
+//
+//
 
 // Define a struct to represent a single clipping entry
 #[derive(Debug)]
 struct Clipping {
+    /// Line of the title in the file
+    line: usize,
     title: String,
     author: Option<String>,
     entry_type: String,
     added_at: NaiveDateTime,
     content: Option<String>, // Absent for bookmarks
+    /// Page range, only for books with page numbers
+    page: Option<(i64, i64)>,
+    /// Location range, a single location for notes and bookmarks
+    location: Option<(i64, i64)>,
+}
+
+impl Clipping {
+    /// Whether the highlight `self` covers the position of `note`,
+    /// by location or by page when the clippings have no location.
+    fn covers(&self, note: &Clipping) -> bool {
+        let contains = |(start, end): (i64, i64), (at, _): (i64, i64)| start <= at && at <= end;
+        match (self.location, note.location) {
+            (Some(range), Some(at)) => contains(range, at),
+            (None, None) => match (self.page, note.page) {
+                (Some(range), Some(at)) => contains(range, at),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the highlight `self` and the text at `location` are versions of the same highlight,
+    /// Kindle appends a clipping each time a highlight is extended or made again.
+    ///
+    /// They are when one text contains the other, or when the last words of one text are
+    /// the first words of the other. Both need their location ranges to overlap when known,
+    /// the same words may be highlighted elsewhere in the book.
+    fn is_version_of(&self, content: &str, location: Option<(i64, i64)>) -> bool {
+        let (a, b) = (self.content.as_deref().unwrap_or_default().trim(), content.trim());
+        if a.is_empty() || b.is_empty() {
+            return false;
+        }
+        let overlap = match (self.location, location) {
+            (Some((a_start, a_end)), Some((b_start, b_end))) => {
+                Some(a_start <= b_end && b_start <= a_end)
+            }
+            _ => None,
+        };
+        match overlap {
+            Some(false) => false,
+            _ if a.contains(b) || b.contains(a) => true,
+            Some(true) => words_overlap(a, b) || words_overlap(b, a),
+            None => false,
+        }
+    }
+
+    /// Order of the versions of a highlight, the newest then the longest is kept.
+    fn version(&self) -> (NaiveDateTime, usize) {
+        (self.added_at, self.content.as_deref().map_or(0, |c| c.trim().len()))
+    }
+}
+
+/// Whether the last words of `a` are the first words of `b`, for at least half of the shorter text.
+fn words_overlap(a: &str, b: &str) -> bool {
+    let a: Vec<&str> = a.split_whitespace().collect();
+    let b: Vec<&str> = b.split_whitespace().collect();
+    let shortest = a.len().min(b.len());
+    (shortest.div_ceil(2).max(1)..=shortest).any(|n| a[a.len() - n..] == b[..n])
+}
+
+/// The highlights of `clippings` superseded by a newer version in the same file,
+/// by index with the index of the version kept. The newest version is kept, then the longest.
+fn superseded_highlights(clippings: &[Clipping]) -> HashMap<usize, usize> {
+    let mut highlights: Vec<usize> = (0..clippings.len())
+        .filter(|&i| clippings[i].entry_type == "Highlight" && clippings[i].content.is_some())
+        .collect();
+    highlights.sort_by_key(|&i| std::cmp::Reverse(clippings[i].version()));
+
+    // Kept versions by title
+    let mut kept: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut superseded = HashMap::new();
+    for i in highlights {
+        let clipping = &clippings[i];
+        let versions = kept.entry(clipping.title.as_str()).or_default();
+        let content = clipping.content.as_deref().unwrap_or_default();
+        let newer = versions
+            .iter()
+            .find(|&&k| clippings[k].is_version_of(content, clipping.location));
+        match newer {
+            Some(&k) => {
+                superseded.insert(i, k);
+            }
+            None => versions.push(i),
+        }
+    }
+
+    superseded
+}
+
+/// Parse a page or location range such as `180-182`. Old firmware shortens the end
+/// of a range to its last digits, `1022-24` is `1022-1024`. Roman page numbers are ignored.
+fn parse_clipping_range(start: &str, end: Option<&str>) -> Option<(i64, i64)> {
+    let start_value: i64 = start.parse().ok()?;
+    let Some(end) = end else {
+        return Some((start_value, start_value));
+    };
+    let mut end_value: i64 = end.parse().ok()?;
+    if end_value < start_value && end.len() < start.len() {
+        let prefix = &start[..start.len() - end.len()];
+        end_value = format!("{}{}", prefix, end).parse().ok()?;
+    }
+    Some((start_value, end_value.max(start_value)))
+}
+
+/// Words and formats of the metadata line of the clippings,
+/// which depend on the language of the Kindle.
+///
+/// The metadata line looks like `- Your Highlight on page 12 | Location 170-171 | Added on <date>`.
+struct ClippingLocale {
+    /// Language code, for the errors
+    language: &'static str,
+    highlight: &'static [&'static str],
+    note: &'static [&'static str],
+    bookmark: &'static [&'static str],
+    /// Words before the date, also used to detect the language of a file
+    added: &'static [&'static str],
+    /// Patterns of the page and location ranges, capturing their start and end
+    page: &'static str,
+    location: &'static str,
+    /// Patterns of the date, with the `year`, `month`, `day`, `hour`, `minute`
+    /// and optional `second` and `ampm` named groups
+    dates: &'static [&'static str],
+    /// Month names in lowercase, empty when months are numbers
+    months: [&'static str; 12],
+}
+
+const NUMERIC_MONTHS: [&str; 12] = [""; 12];
+
+/// Time of a day-month-year date such as `26 March 2016 14:59:39`.
+const CLIPPING_TIME: &str = r"(?P<hour>\d{1,2}):(?P<minute>\d{2})(?::(?P<second>\d{2}))?";
+
+const CLIPPING_LOCALES: &[ClippingLocale] = &[
+    ClippingLocale {
+        language: "en",
+        highlight: &["highlight"],
+        note: &["note"],
+        bookmark: &["bookmark"],
+        added: &["Added on"],
+        page: r"\bpage\s+(\w+)(?:-(\w+))?",
+        location: r"\b(?:location|loc\.)\s+(\d+)(?:-(\d+))?",
+        dates: &[
+            // Saturday, 26 March 2016 14:59:39
+            r"(?P<day>\d{1,2}) (?P<month>\p{L}+) (?P<year>\d{4}),? TIME",
+            // Saturday, March 26, 2016 2:59:39 PM
+            r"(?P<month>\p{L}+) (?P<day>\d{1,2}), (?P<year>\d{4}),? TIME(?:\s*(?P<ampm>[AP]M))?",
+        ],
+        months: [
+            "january", "february", "march", "april", "may", "june",
+            "july", "august", "september", "october", "november", "december",
+        ],
+    },
+    ClippingLocale {
+        language: "de",
+        highlight: &["markierung"],
+        note: &["notiz"],
+        bookmark: &["lesezeichen"],
+        added: &["Hinzugefügt am"],
+        page: r"\bseite\s+(\w+)(?:-(\w+))?",
+        location: r"\bposition\s+(\d+)(?:-(\d+))?",
+        // Samstag, 26. März 2016 14:59:39
+        dates: &[r"(?P<day>\d{1,2})\. (?P<month>\p{L}+) (?P<year>\d{4}) TIME"],
+        months: [
+            "januar", "februar", "märz", "april", "mai", "juni",
+            "juli", "august", "september", "oktober", "november", "dezember",
+        ],
+    },
+    ClippingLocale {
+        language: "fr",
+        highlight: &["surlignement"],
+        note: &["note"],
+        bookmark: &["signet"],
+        added: &["Ajouté le"],
+        page: r"\bpage\s+(\w+)(?:-(\w+))?",
+        location: r"\bemplacement\s+(\d+)(?:-(\d+))?",
+        // samedi 26 mars 2016 14:59:39
+        dates: &[r"(?P<day>\d{1,2}) (?P<month>\p{L}+) (?P<year>\d{4}) (?:à )?TIME"],
+        months: [
+            "janvier", "février", "mars", "avril", "mai", "juin",
+            "juillet", "août", "septembre", "octobre", "novembre", "décembre",
+        ],
+    },
+    ClippingLocale {
+        language: "it",
+        highlight: &["evidenziazione"],
+        note: &["nota"],
+        bookmark: &["segnalibro"],
+        added: &["Aggiunto in data", "Aggiunto il"],
+        page: r"\bpagina\s+(\w+)(?:-(\w+))?",
+        location: r"\bposizione\s+(\d+)(?:-(\d+))?",
+        // sabato 26 marzo 2016 14:59:39
+        dates: &[r"(?P<day>\d{1,2}) (?P<month>\p{L}+) (?P<year>\d{4}) TIME"],
+        months: [
+            "gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno",
+            "luglio", "agosto", "settembre", "ottobre", "novembre", "dicembre",
+        ],
+    },
+    ClippingLocale {
+        language: "es",
+        highlight: &["subrayado"],
+        note: &["nota"],
+        bookmark: &["marcador"],
+        added: &["Añadido el", "Agregado el"],
+        page: r"\bpágina\s+(\w+)(?:-(\w+))?",
+        location: r"\bposición\s+(\d+)(?:-(\d+))?",
+        // sábado, 26 de marzo de 2016 14:59:39
+        dates: &[r"(?P<day>\d{1,2}) de (?P<month>\p{L}+) de (?P<year>\d{4}),? TIME"],
+        months: [
+            "enero", "febrero", "marzo", "abril", "mayo", "junio",
+            "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre",
+        ],
+    },
+    ClippingLocale {
+        language: "pt",
+        highlight: &["destaque"],
+        note: &["nota"],
+        bookmark: &["marcador"],
+        added: &["Adicionado:", "Adicionado em"],
+        page: r"\bpágina\s+(\w+)(?:-(\w+))?",
+        location: r"\bposição\s+(\d+)(?:-(\d+))?",
+        // sábado, 26 de março de 2016 14:59:39
+        dates: &[r"(?P<day>\d{1,2}) de (?P<month>\p{L}+) de (?P<year>\d{4}),? TIME"],
+        months: [
+            "janeiro", "fevereiro", "março", "abril", "maio", "junho",
+            "julho", "agosto", "setembro", "outubro", "novembro", "dezembro",
+        ],
+    },
+    ClippingLocale {
+        language: "nl",
+        highlight: &["markering"],
+        note: &["notitie"],
+        bookmark: &["bladwijzer"],
+        added: &["Toegevoegd op"],
+        page: r"\bpagina\s+(\w+)(?:-(\w+))?",
+        location: r"\blocatie\s+(\d+)(?:-(\d+))?",
+        // zaterdag 26 maart 2016 14:59:39
+        dates: &[r"(?P<day>\d{1,2}) (?P<month>\p{L}+) (?P<year>\d{4}) TIME"],
+        months: [
+            "januari", "februari", "maart", "april", "mei", "juni",
+            "juli", "augustus", "september", "oktober", "november", "december",
+        ],
+    },
+    ClippingLocale {
+        language: "ja",
+        highlight: &["ハイライト"],
+        note: &["メモ"],
+        bookmark: &["ブックマーク"],
+        added: &["作成日"],
+        page: r"(\d+)(?:-(\d+))?\s*ページ",
+        location: r"位置No\.\s*(\d+)(?:-(\d+))?",
+        // 2016年3月26日土曜日 14:59:39
+        dates: &[concat!(
+            r"(?P<year>\d{4})年(?P<month>\d{1,2})月(?P<day>\d{1,2})日\S*?\s*",
+            r"(?P<ampm>午前|午後)?TIME",
+        )],
+        months: NUMERIC_MONTHS,
+    },
+    ClippingLocale {
+        language: "zh",
+        highlight: &["标注"],
+        note: &["笔记"],
+        bookmark: &["书签"],
+        added: &["添加于"],
+        page: r"第\s*(\d+)(?:-(\d+))?\s*页",
+        location: r"位置\s*#(\d+)(?:-(\d+))?",
+        // 2016年3月26日星期六 下午2:59:39
+        dates: &[concat!(
+            r"(?P<year>\d{4})年(?P<month>\d{1,2})月(?P<day>\d{1,2})日\S*?\s*",
+            r"(?P<ampm>上午|下午|中午|晚上|凌晨|早上)?TIME",
+        )],
+        months: NUMERIC_MONTHS,
+    },
+];
+
+/// Markers of the afternoon in 12-hour times, the others are of the morning.
+const CLIPPING_PM: &[&str] = &["pm", "午後", "下午", "中午", "晚上"];
+
+/// Parser of the clippings of one file, in the language detected for the file.
+struct ClippingParser {
+    locale: &'static ClippingLocale,
+    title_author: Regex,
+    page: Regex,
+    location: Regex,
+    dates: Vec<Regex>,
+}
+
+impl ClippingParser {
+    /// Parser for the language of most of the `metadata` lines, English when none is recognized.
+    fn detect<'a>(metadata: impl Iterator<Item = &'a str>) -> Self {
+        let mut counts = vec![0; CLIPPING_LOCALES.len()];
+        for line in metadata {
+            for (i, locale) in CLIPPING_LOCALES.iter().enumerate() {
+                if locale.added.iter().any(|added| line.contains(added)) {
+                    counts[i] += 1;
+                }
+            }
+        }
+        let index = (0..counts.len()).rev().max_by_key(|&i| counts[i]).unwrap_or(0);
+        let index = if counts[index] == 0 { 0 } else { index };
+
+        Self::new(&CLIPPING_LOCALES[index])
+    }
+
+    fn new(locale: &'static ClippingLocale) -> Self {
+        let regex = |pattern: &str| {
+            Regex::new(&format!("(?i){}", pattern.replace("TIME", CLIPPING_TIME))).unwrap()
+        };
+        ClippingParser {
+            locale,
+            title_author: Regex::new(r"^(.*?)(?:\s*[(（](.*?)[)）])?$").unwrap(),
+            page: regex(locale.page),
+            location: regex(locale.location),
+            dates: locale.dates.iter().map(|d| regex(d)).collect(),
+        }
+    }
+
+    /// Entry type of the start of a metadata line, "Highlight", "Note" or "Bookmark"
+    fn entry_type(&self, metadata: &str) -> Option<&'static str> {
+        let metadata = metadata.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|w| metadata.contains(w));
+        if has(self.locale.highlight) {
+            Some("Highlight")
+        } else if has(self.locale.note) {
+            Some("Note")
+        } else if has(self.locale.bookmark) {
+            Some("Bookmark")
+        } else {
+            None
+        }
+    }
+
+    fn parse_date(&self, date: &str) -> Option<NaiveDateTime> {
+        let caps = self.dates.iter().find_map(|re| re.captures(date))?;
+        let number = |name: &str| caps.name(name).and_then(|m| m.as_str().parse::<u32>().ok());
+
+        let month = caps.name("month")?.as_str().to_lowercase();
+        let month = match self.locale.months.iter().position(|m| *m == month) {
+            Some(i) => i as u32 + 1,
+            None => month.parse().ok()?,
+        };
+        let mut hour = number("hour")?;
+        if let Some(ampm) = caps.name("ampm") {
+            let pm = CLIPPING_PM.contains(&ampm.as_str().to_lowercase().as_str());
+            hour = match (pm, hour) {
+                (false, 12) => 0,
+                (true, h) if h < 12 => h + 12,
+                (_, h) => h,
+            };
+        }
+
+        NaiveDate::from_ymd_opt(number("year")? as i32, month, number("day")?)?.and_hms_opt(
+            hour,
+            number("minute")?,
+            number("second").unwrap_or(0),
+        )
+    }
+
+    /// Parse a single clipping from its lines, starting at `line` in the file
+    fn parse(&self, line: usize, lines: &[String]) -> Result<Clipping, String> {
+        if lines.len() < 2 {
+            return Err(format!("Incomplete clipping: {}", lines.join(" ")));
+        }
+
+        // Parse title and author from first line
+        let caps = self
+            .title_author
+            .captures(lines[0].trim())
+            .ok_or_else(|| format!("Unable to parse line: {}", lines[0]))?;
+        let book_title = caps[1].trim().to_string();
+        let author = caps.get(2).map(|m| m.as_str().trim().to_string());
+
+        // Parse entry type, position and date from the metadata line
+        let metadata = lines[1].trim();
+        let invalid = || format!("Invalid metadata format on line: {}", lines[1]);
+        let (position, date_str) = self
+            .locale
+            .added
+            .iter()
+            .find_map(|added| metadata.split_once(added))
+            .ok_or_else(invalid)?;
+        let entry_type = self.entry_type(position).ok_or_else(invalid)?.to_string();
+        let date_str = date_str.trim_start_matches([':', '：', ' ']).trim();
+        let added_at = self.parse_date(date_str).ok_or_else(|| {
+            format!("Unable to parse date: {} (language {})", date_str, self.locale.language)
+        })?;
+
+        let range = |caps: regex::Captures| {
+            parse_clipping_range(&caps[1], caps.get(2).map(|m| m.as_str()))
+        };
+        let page = self.page.captures(position).and_then(range);
+        let location = self.location.captures(position).and_then(range);
+
+        // Line[2] is empty by specification
+        let content = if lines.len() > 2 && lines[2].is_empty() {
+            Some(lines[3..].join("\n")) // Join content lines if multi-line
+        } else {
+            None
+        };
+
+        Ok(Clipping {
+            line,
+            title: book_title,
+            author,
+            entry_type,
+            added_at,
+            content,
+            page,
+            location,
+        })
+    }
 }
 
-fn read_clippings_file(path: &str) -> Result<Vec<Clipping>, ImportError> {
+/// Read the clippings of `path`, the clippings that cannot be parsed are added to `report`.
+///
+/// The language of the clippings is detected from the whole file.
+fn read_clippings_file(path: &str, report: &mut ImportReport) -> Result<Vec<Clipping>, ImportError> {
     let path = Path::new(path);
     if !path.exists() || !path.is_file() {
         return Err(ImportError::IoError(io::Error::new(
@@ -602,9 +1980,13 @@ fn read_clippings_file(path: &str) -> Result<Vec<Clipping>, ImportError> {
     let file = File::open(path).map_err(|e| ImportError::IoError(e))?;
     let mut reader = BufReader::new(file);
 
-    let mut clippings = Vec::new();
+    // Lines of each clipping with the line number of its first line
+    let mut entries = Vec::new();
     let mut lines = Vec::new();
     let mut buffer = String::new();
+    // Line number of the current line and of the first line of the current clipping
+    let mut line_number = 1;
+    let mut clipping_start = 1;
 
     // Check and skip UTF-8 BOM if present, but keep the first line
     reader
@@ -621,13 +2003,12 @@ fn read_clippings_file(path: &str) -> Result<Vec<Clipping>, ImportError> {
     // Read the file line-by-line
     for line in reader.lines() {
         let line = line.map_err(|e| ImportError::IoError(e))?;
+        line_number += 1;
         if line.trim() == "==========" {
             if !lines.is_empty() {
-                // Process the accumulated lines into a Clipping
-                let clipping = parse_clipping(&lines).map_err(|e| ImportError::InvalidFormat(e))?;
-                clippings.push(clipping);
-                lines.clear();
+                entries.push((clipping_start, std::mem::take(&mut lines)));
             }
+            clipping_start = line_number + 1;
         } else {
             lines.push(line);
         }
@@ -635,126 +2016,194 @@ fn read_clippings_file(path: &str) -> Result<Vec<Clipping>, ImportError> {
 
     // Handle any remaining lines (in case file ends without delimiter)
     if !lines.is_empty() {
-        let clipping = parse_clipping(&lines).map_err(|e| ImportError::InvalidFormat(e))?;
-        clippings.push(clipping);
+        entries.push((clipping_start, lines));
     }
 
-    Ok(clippings)
-}
-
-// Parse a single clipping from a vector of lines
-fn parse_clipping(lines: &[String]) -> Result<Clipping, String> {
-    // Parse title and author from first line
-    let re_title_author = Regex::new(r"^(.*?)(?:\s*\((.*?)\))?$").unwrap();
-    let caps = re_title_author
-        .captures(lines[0].trim())
-        .ok_or_else(|| format!("Unable to parse line: {}", lines[0]))?;
-    let book_title = caps[1].trim().to_string();
-    let author = caps.get(2).map(|m| m.as_str().trim().to_string());
-
-    // Parse entry_type and date from second line
-    let re_metadata =
-        Regex::new(r"(?:- )?(?:Your )?(Highlight|Note|Bookmark).*?Added on (.*?)$").unwrap();
-    let Some(caps) = re_metadata.captures(lines[1].trim()) else {
-        return Err(format!("Invalid metadata format on line: {}", lines[1]));
-    };
-    let entry_type = caps[1].to_string(); // Captures "Highlight", "Note", or "Bookmark"
-    let date_str = caps[2].trim();
-
-    // Parse date into NaiveDateTime
-    let added_at = parse_datetime(date_str).map_err(|e| format!("Error parsing date => {}", e))?;
-
-    // Line[2] is empty by specification
-    let content = if lines.len() > 2 && lines[2].is_empty() {
-        Some(lines[3..].join("\n")) // Join content lines if multi-line
-    } else {
-        None
-    };
+    let metadata = entries.iter().filter_map(|(_, lines)| lines.get(1));
+    let parser = ClippingParser::detect(metadata.map(|line| line.as_str()));
+    let mut clippings = Vec::new();
+    for (start, lines) in entries {
+        match parser.parse(start, &lines) {
+            Ok(clipping) => clippings.push(clipping),
+            Err(e) => report.error(format!("line {}", start), e),
+        }
+    }
 
-    Ok(Clipping {
-        title: book_title,
-        author,
-        entry_type,
-        added_at,
-        content,
-    })
+    Ok(clippings)
 }
 
-async fn import_clippings(path: &str) -> Result<String, ImportError> {
-    let clippings = read_clippings_file(path).map_err(|e| e)?;
+/// Import a Kindle `My Clippings.txt` file.
+pub async fn import_clippings(
+    path: &str,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    let mut clippings = read_clippings_file(path, &mut run.report)?;
+    // Notes are imported after the highlights, to be linked to the highlight they annotate
+    clippings.sort_by_key(|c| c.entry_type == "Note");
+    let superseded = superseded_highlights(&clippings);
 
     let mut books_id_map = HashMap::new();
     let mut authors_id_map = HashMap::new();
-    let mut tx = db::get_pool()
-        .begin()
-        .await
-        .map_err(|e| ImportError::DbError(e, "Failed to begin transaction".to_string()))?;
-    let mut imported_books = 0;
-    let mut imported_quotes = 0;
-
-    for clipping in clippings.iter() {
-        let mut book_exists = false;
-        // Check if book already exists, and add it to the books_id_map
-        if !books_id_map.contains_key(&clipping.title) {
-            if let Ok(existing_book) =
-                queries::get_book_by_original_id(clipping.title.clone(), &mut *tx).await
-            {
-                books_id_map.insert(clipping.title.clone(), existing_book.id.clone());
-                book_exists = true;
-            }
-        } else {
-            book_exists = true;
+    // Map of title to the book in the preview.
+    let mut source_books = HashMap::new();
+    // Map of title to the highlights of the book with the id of their quote
+    let mut highlights: HashMap<&str, Vec<(&Clipping, String)>> = HashMap::new();
+    // Quotes and notes of the books already in the library, by book id
+    let mut library_quotes: HashMap<String, Vec<models::QuoteWithTags>> = HashMap::new();
+    let mut library_notes: HashMap<String, Vec<models::Note>> = HashMap::new();
+
+    for (i, clipping) in clippings.iter().enumerate() {
+        run.progress(ImportPhase::Quotes, i + 1, clippings.len(), Some(&clipping.title))?;
+        if !run.imports_book(&clipping.title) {
+            continue;
         }
-
-        // Create author if it doesn't exist
-        if let Some(author_name) = &clipping.author {
-            if !authors_id_map.contains_key(author_name) {
-                match queries::get_author_by_name(author_name.clone(), &mut *tx).await {
-                    Ok(existing_author) => {
-                        authors_id_map.insert(author_name.clone(), existing_author.id.clone());
-                    }
-                    Err(_) => {
-                        let author = queries::insert_author(author_name.clone(), &mut *tx)
-                            .await
-                            .map_err(|e| {
-                                ImportError::DbError(e, "Failed to insert author".to_string())
-                            })?;
-                        authors_id_map.insert(author_name.clone(), author.id.clone());
-                    }
-                }
-            }
+        if let Some(&newer) = superseded.get(&i) {
+            run.report.quotes.skipped += 1;
+            let message = format!(
+                "Superseded by a newer version of the highlight at line {}",
+                clippings[newer].line
+            );
+            run.report.info(format!("line {}", clipping.line), message);
+            continue;
         }
 
-        let author_id: Option<String> = match &clipping.author {
-            Some(author_name) => authors_id_map.get(author_name).cloned(),
+        // Create author if it doesn't exist
+        let author_id = match &clipping.author {
+            Some(author_name) => Some(
+                get_or_insert_author(author_name, &mut authors_id_map, run, &mut *conn)
+                    .await?,
+            ),
             None => None,
         };
 
-        if !book_exists {
-            let book = queries::insert_book_with_defaults(
-                clipping.title.clone(),
-                author_id.clone(),
-                None,
-                &mut *tx,
-            )
-            .await
-            .map_err(|e| ImportError::DbError(e, "Failed to insert book".to_string()))?;
-            books_id_map.insert(clipping.title.clone(), book.id.clone());
-            imported_books += 1;
+        // Check if book already exists, and add it to the books_id_map
+        if !books_id_map.contains_key(&clipping.title) {
+            let mut source_book = SourceBook {
+                key: clipping.title.clone(),
+                title: clipping.title.clone(),
+                author: clipping.author.clone(),
+                status: PreviewStatus::New,
+            };
+            let existing_book =
+                find_kindle_book(&clipping.title, author_id.as_deref(), &mut *conn).await;
+            let book_id = match existing_book {
+                Some(existing_book) => {
+                    run.report.books.skipped += 1;
+                    source_book.status = PreviewStatus::Duplicate;
+                    let quotes = queries::get_all_quotes_by_book_id(
+                        &existing_book.id,
+                        None,
+                        None,
+                        &mut *conn,
+                    )
+                    .await
+                    .map_err(|e| ImportError::DbError(e, "Failed to fetch quotes".to_string()))?;
+                    let notes = queries::get_notes_by_book(&existing_book.id, &mut *conn)
+                        .await
+                        .map_err(|e| ImportError::DbError(e, "Failed to fetch notes".to_string()))?;
+                    library_quotes.insert(existing_book.id.clone(), quotes);
+                    library_notes.insert(existing_book.id.clone(), notes);
+                    existing_book.id
+                }
+                None => {
+                    let book = queries::insert_imported_book(
+                        clipping.title.clone(),
+                        author_id.clone(),
+                        Some(clipping.title.clone()),
+                        run.batch_id.as_deref(),
+                        &mut *conn,
+                    )
+                    .await
+                    .map_err(|e| ImportError::DbError(e, "Failed to insert book".to_string()))?;
+                    run.report.books.created += 1;
+                    book.id
+                }
+            };
+            books_id_map.insert(clipping.title.clone(), book_id);
+            source_books.insert(clipping.title.clone(), source_book);
         }
 
         let book_id = books_id_map.get(&clipping.title).unwrap();
+        let book = &source_books[&clipping.title];
 
         if clipping.entry_type == "Highlight" {
             if let Some(content) = &clipping.content {
                 // Check if the quote already exists by comparing the content and book id
-                if let Ok(_) = queries::get_quote_by_book_and_content(
+                let existing = queries::get_quote_by_book_and_content(
                     book_id.clone(),
                     content.clone(),
-                    &mut *tx,
+                    &mut *conn,
                 )
                 .await
-                {
+                .ok();
+                if let Some(existing) = &existing {
+                    highlights
+                        .entry(&clipping.title)
+                        .or_default()
+                        .push((clipping, existing.id.clone()));
+                }
+
+                // Another version of the highlight in the library,
+                // Kindle quotes have no original id
+                let version = match &existing {
+                    Some(_) => None,
+                    None => library_quotes.get(book_id).and_then(|quotes| {
+                        quotes.iter().find(|q| {
+                            let text = q.content.as_deref().unwrap_or_default();
+                            let location = q.location_start.zip(q.location_end);
+                            q.original_id.is_none() && clipping.is_version_of(text, location)
+                        })
+                    }),
+                };
+                if let Some(version) = version {
+                    highlights
+                        .entry(&clipping.title)
+                        .or_default()
+                        .push((clipping, version.id.clone()));
+                    let version_content = version.content.as_deref().unwrap_or_default();
+                    if clipping.version() <= (version.updated_at, version_content.trim().len()) {
+                        run.report.quotes.skipped += 1;
+                        run.report.info(
+                            format!("line {}", clipping.line),
+                            "Superseded by a newer version of the highlight in the library",
+                        );
+                        continue;
+                    }
+
+                    let preview = PreviewQuote {
+                        key: format!("kindle:{}", hash_parts(&[&clipping.title, content])),
+                        content: Some(content.clone()),
+                        note: None,
+                        status: PreviewStatus::Newer,
+                    };
+                    if !run.add_quote(book, None, preview) {
+                        continue;
+                    }
+
+                    let mut quote = queries::get_quote_by_id(&version.id, &mut *conn)
+                        .await
+                        .map_err(|e| ImportError::DbError(e, "Failed to fetch quote".to_string()))?;
+                    quote.content = Some(content.clone());
+                    quote.page_start = clipping.page.map(|(start, _)| start);
+                    quote.page_end = clipping.page.map(|(_, end)| end);
+                    quote.location_start = clipping.location.map(|(start, _)| start);
+                    quote.location_end = clipping.location.map(|(_, end)| end);
+                    quote.updated_at = clipping.added_at;
+                    queries::update_quote_version(&quote, &mut *conn).await.map_err(|e| {
+                        ImportError::DbError(e, "Failed to update quote".to_string())
+                    })?;
+                    run.report.quotes.updated += 1;
+                    continue;
+                }
+
+                let preview = PreviewQuote {
+                    key: format!("kindle:{}", hash_parts(&[&clipping.title, content])),
+                    content: Some(content.clone()),
+                    note: None,
+                    status: quote_status(existing.as_ref(), Some(content)),
+                };
+                if !run.add_quote(book, None, preview) {
                     continue;
                 }
 
@@ -771,50 +2220,512 @@ async fn import_clippings(path: &str) -> Result<String, ImportError> {
                     imported_at: Some(Utc::now().naive_utc()),
                     deleted_at: None,
                     original_id: None,
+                    color: None,
+                    page_start: clipping.page.map(|(start, _)| start),
+                    page_end: clipping.page.map(|(_, end)| end),
+                    location_start: clipping.location.map(|(start, _)| start),
+                    location_end: clipping.location.map(|(_, end)| end),
+                    import_batch_id: run.batch_id.clone(),
                 };
 
-                let _ = queries::insert_quote(&quote, &mut *tx)
+                let _ = queries::insert_quote(&quote, &mut *conn)
                     .await
                     .map_err(|e| ImportError::DbError(e, "Failed to insert quote".to_string()))?;
-                imported_quotes += 1;
+                run.report.quotes.created += 1;
+                highlights.entry(&clipping.title).or_default().push((clipping, quote.id));
             }
         } else if clipping.entry_type == "Note" {
             if let Some(content) = &clipping.content {
-                let note = models::Note {
-                    id: Uuid::new_v4().to_string(),
-                    book_id: Some(book_id.clone()),
-                    author_id: author_id,
-                    quote_id: None,
-                    content: Some(content.clone()),
-                    created_at: clipping.added_at,
+                let imported = library_notes.get(book_id).is_some_and(|notes| {
+                    notes.iter().any(|n| n.content.as_deref() == Some(content.as_str()))
+                });
+                if imported {
+                    run.report.notes.skipped += 1;
+                    continue;
+                }
+
+                let preview = PreviewQuote {
+                    key: format!("kindle:{}", hash_parts(&["note", &clipping.title, content])),
+                    content: None,
+                    note: Some(content.clone()),
+                    status: PreviewStatus::New,
+                };
+                if !run.add_quote(book, None, preview) {
+                    continue;
+                }
+
+                // The highlight a note annotates covers its location, the latest one wins
+                let quote_id = highlights.get(clipping.title.as_str()).and_then(|quotes| {
+                    quotes
+                        .iter()
+                        .rev()
+                        .find(|(highlight, _)| highlight.covers(clipping))
+                        .map(|(_, id)| id.clone())
+                });
+
+                let note = models::Note {
+                    id: Uuid::new_v4().to_string(),
+                    book_id: Some(book_id.clone()),
+                    author_id: author_id,
+                    quote_id,
+                    content: Some(content.clone()),
+                    created_at: clipping.added_at,
                     updated_at: clipping.added_at,
                     deleted_at: None,
+                    import_batch_id: run.batch_id.clone(),
                 };
 
-                let _ = queries::insert_note(&note, &mut *tx)
+                let _ = queries::insert_note(&note, &mut *conn)
                     .await
                     .map_err(|e| ImportError::DbError(e, "Failed to insert note".to_string()))?;
+                run.report.notes.created += 1;
             }
         }
     }
 
-    tx.commit()
-        .await
-        .map_err(|e| ImportError::DbError(e, "Failed to commit transaction".to_string()))?;
-    Ok(format!(
-        "Imported successfully {} new books and {} new quotes",
-        imported_books, imported_quotes
-    ))
+    Ok(())
 }
 
+/// The library book of a Kindle title, Kindle books are kept with their title as original id.
 ///
-/// iBooks
-///
+/// Books imported before their title was kept as original id are found by title.
+async fn find_kindle_book(
+    title: &str,
+    author_id: Option<&str>,
+    conn: &mut SqliteConnection,
+) -> Option<models::Book> {
+    match queries::get_book_by_original_id(title.to_string(), &mut *conn).await {
+        Ok(book) => Some(book),
+        Err(_) => queries::get_book_by_title_and_author(title, author_id, &mut *conn)
+            .await
+            .ok(),
+    }
+}
+
+//
+// Kindle notebook export
+//
+//
+
+/// A highlight of a Kindle notebook export, with the note that follows it.
+#[derive(Debug, Default)]
+struct NotebookHighlight {
+    chapter: Option<String>,
+    text: String,
+    note: Option<String>,
+    color: Option<String>,
+    page: Option<(i64, i64)>,
+    location: Option<(i64, i64)>,
+}
+
+impl NotebookHighlight {
+    /// Whether the highlight adds to `quote`, the same highlight imported from the clippings
+    /// lacks its chapter and color.
+    fn enriches(&self, quote: &models::Quote, chapter_id: Option<&str>) -> bool {
+        let differs = |range: Option<(i64, i64)>, start: Option<i64>, end: Option<i64>| {
+            range.is_some_and(|(s, e)| (Some(s), Some(e)) != (start, end))
+        };
+        (chapter_id.is_some() && quote.chapter_id.as_deref() != chapter_id)
+            || (self.color.is_some() && quote.color != self.color)
+            || differs(self.page, quote.page_start, quote.page_end)
+            || differs(self.location, quote.location_start, quote.location_end)
+    }
+}
+
+/// A book exported with "Export Notebook" from the Kindle apps.
+#[derive(Debug, Default)]
+struct KindleNotebook {
+    title: String,
+    author: Option<String>,
+    highlights: Vec<NotebookHighlight>,
+    /// Notes without a highlight before them
+    notes: Vec<String>,
+}
+
+/// Text of an HTML fragment, without its tags and with its entities decoded.
+fn html_text(html: &str) -> String {
+    let re_break = Regex::new(r"(?i)<br\s*/?>").unwrap();
+    let re_tag = Regex::new(r"<[^>]*>").unwrap();
+    let re_entity = Regex::new(r"&(#[xX][0-9a-fA-F]+|#[0-9]+|[a-zA-Z]+);").unwrap();
+
+    let text = re_break.replace_all(html, "\n");
+    let text = re_tag.replace_all(&text, "");
+    let text = re_entity.replace_all(&text, |caps: &regex::Captures| {
+        let entity = &caps[1];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        decoded.map_or_else(|| caps[0].to_string(), |c| c.to_string())
+    });
+
+    text.lines().map(str::trim).collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
+/// Parse the HTML of a Kindle notebook export.
 ///
+/// The export is a flat list of `div` elements: the title and authors of the book,
+/// then section headings, and note headings each followed by the text of the highlight or note.
+fn parse_kindle_notebook(
+    html: &str,
+    report: &mut ImportReport,
+) -> Result<KindleNotebook, ImportError> {
+    let re_element = Regex::new(concat!(
+        r#"(?is)<(?:div|h\d)\s+class=['"]"#,
+        r#"(bookTitle|authors|sectionHeading|noteHeading|noteText)"#,
+        r#"['"]\s*>(.*?)</(?:div|h\d)>"#,
+    ))
+    .unwrap();
+    let re_color = Regex::new(r#"highlight_([a-zA-Z]+)"#).unwrap();
+    let parsers: Vec<ClippingParser> = CLIPPING_LOCALES.iter().map(ClippingParser::new).collect();
+
+    let mut notebook = KindleNotebook::default();
+    let mut chapter = None;
+    // Kind of the last note heading, the text that follows belongs to it
+    let mut heading: Option<(&str, NotebookHighlight)> = None;
+
+    for (i, caps) in re_element.captures_iter(html).enumerate() {
+        let text = html_text(&caps[2]);
+        match &caps[1] {
+            "bookTitle" => notebook.title = text,
+            "authors" => notebook.author = Some(text).filter(|a| !a.is_empty()),
+            "sectionHeading" => chapter = Some(text).filter(|c| !c.is_empty()),
+            "noteHeading" => {
+                let color = re_color.captures(&caps[2]).map(|c| c[1].to_lowercase());
+                let kind = match parsers.iter().find_map(|p| p.entry_type(&text)) {
+                    _ if color.is_some() => "Highlight",
+                    Some(kind) => kind,
+                    None => {
+                        report.error(
+                            format!("heading {}", i),
+                            format!("Unknown heading: {}", text),
+                        );
+                        heading = None;
+                        continue;
+                    }
+                };
+                let range = |caps: regex::Captures| {
+                    parse_clipping_range(&caps[1], caps.get(2).map(|m| m.as_str()))
+                };
+                heading = Some((
+                    kind,
+                    NotebookHighlight {
+                        chapter: chapter.clone(),
+                        color,
+                        page: parsers.iter().find_map(|p| p.page.captures(&text)).and_then(range),
+                        location: parsers
+                            .iter()
+                            .find_map(|p| p.location.captures(&text))
+                            .and_then(range),
+                        ..Default::default()
+                    },
+                ));
+            }
+            "noteText" => match heading.take() {
+                Some(("Highlight", highlight)) => {
+                    notebook.highlights.push(NotebookHighlight { text, ..highlight })
+                }
+                Some(("Note", _)) => match notebook.highlights.last_mut() {
+                    Some(highlight) => {
+                        highlight.note = Some(match highlight.note.take() {
+                            Some(note) => format!("{}\n\n{}", note, text),
+                            None => text,
+                        })
+                    }
+                    None => notebook.notes.push(text),
+                },
+                // Bookmarks have no text
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    if notebook.title.is_empty() {
+        return Err(ImportError::InvalidFormat(
+            "Not a Kindle notebook export: missing book title".to_string(),
+        ));
+    }
+    notebook.highlights.retain(|h| !h.text.is_empty());
+
+    Ok(notebook)
+}
+
+/// Insert the note of a notebook highlight, attached to its quote.
+async fn insert_notebook_note(
+    content: &str,
+    quote: &models::Quote,
+    now: NaiveDateTime,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    let note = models::Note {
+        id: Uuid::new_v4().to_string(),
+        book_id: quote.book_id.clone(),
+        author_id: quote.author_id.clone(),
+        quote_id: Some(quote.id.clone()),
+        content: Some(content.to_string()),
+        created_at: now,
+        updated_at: now,
+        deleted_at: None,
+        import_batch_id: run.batch_id.clone(),
+    };
+
+    queries::insert_note(&note, &mut *conn)
+        .await
+        .map_err(|e| ImportError::DbError(e, "Failed to insert note".to_string()))?;
+    run.report.notes.created += 1;
+
+    Ok(())
+}
+
+/// Import a Kindle notebook export, an HTML file with the highlights and notes of a book.
+pub async fn import_kindle_notebook(
+    path: &str,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    run.progress(ImportPhase::Reading, 0, 1, None)?;
+    let html = std::fs::read_to_string(path)?;
+    let notebook = parse_kindle_notebook(&html, &mut run.report)?;
+    run.progress(ImportPhase::Reading, 1, 1, None)?;
+
+    if !run.imports_book(&notebook.title) {
+        return Ok(());
+    }
+    let mut source_book = SourceBook {
+        key: notebook.title.clone(),
+        title: notebook.title.clone(),
+        author: notebook.author.clone(),
+        status: PreviewStatus::Duplicate,
+    };
+
+    // Author
+    let mut authors_id_map = HashMap::new();
+    let author_id = match &notebook.author {
+        Some(name) => Some(get_or_insert_author(name, &mut authors_id_map, run, &mut *conn).await?),
+        None => None,
+    };
+
+    // Book, shared with the clippings of the same title
+    let book = match find_kindle_book(&notebook.title, author_id.as_deref(), &mut *conn).await {
+        Some(existing_book) => {
+            run.report.books.skipped += 1;
+            existing_book
+        }
+        None => {
+            let book = queries::insert_imported_book(
+                notebook.title.clone(),
+                author_id.clone(),
+                Some(notebook.title.clone()),
+                run.batch_id.as_deref(),
+                &mut *conn,
+            )
+            .await
+            .map_err(|e| ImportError::DbError(e, "Failed to insert book".to_string()))?;
+            run.report.books.created += 1;
+            source_book.status = PreviewStatus::New;
+            book
+        }
+    };
+
+    // Chapters, from the section headings
+    let mut chapters_id_map = HashMap::new();
+    let now = Utc::now().naive_utc();
+    for title in notebook.highlights.iter().filter_map(|h| h.chapter.as_ref()) {
+        if chapters_id_map.contains_key(title) {
+            continue;
+        }
+
+        let original_id = format!("{}#{}", notebook.title, title);
+        if let Ok(existing_chapter) =
+            queries::get_chapter_by_original_id(original_id.clone(), &mut *conn).await
+        {
+            chapters_id_map.insert(title.clone(), existing_chapter.id.clone());
+            run.report.chapters.skipped += 1;
+            continue;
+        }
+
+        let new_chapter = models::Chapter {
+            id: Uuid::new_v4().to_string(),
+            book_id: Some(book.id.clone()),
+            title: title.clone(),
+            volume_index: chapters_id_map.len() as i64,
+            original_id: Some(original_id),
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            import_batch_id: run.batch_id.clone(),
+        };
+
+        queries::insert_chapter(&new_chapter, &mut *conn)
+            .await
+            .map_err(|e| ImportError::DbError(e, "Failed to insert chapter".to_string()))?;
+        chapters_id_map.insert(title.clone(), new_chapter.id.clone());
+        run.report.chapters.created += 1;
+    }
+
+    for (i, highlight) in notebook.highlights.iter().enumerate() {
+        run.progress(ImportPhase::Quotes, i + 1, notebook.highlights.len(), Some(&notebook.title))?;
+
+        // Same key and duplicate check as the clippings, which have the same highlights
+        let existing = queries::get_quote_by_book_and_content(
+            book.id.clone(),
+            highlight.text.clone(),
+            &mut *conn,
+        )
+        .await
+        .ok();
+        let chapter_id = highlight
+            .chapter
+            .as_ref()
+            .and_then(|c| chapters_id_map.get(c).cloned());
+
+        // A duplicate gets the chapter, color, position and note it lacks
+        let library_notes = match &existing {
+            Some(quote) => {
+                queries::get_notes_by_quote_ids(std::slice::from_ref(&quote.id), &mut *conn)
+                    .await
+                    .map_err(|e| ImportError::DbError(e, "Failed to fetch notes".to_string()))?
+            }
+            None => Vec::new(),
+        };
+        let note = highlight.note.as_ref().filter(|note| {
+            !library_notes
+                .iter()
+                .any(|n| n.content.as_deref() == Some(note.as_str()))
+        });
+        let status = match &existing {
+            None => PreviewStatus::New,
+            Some(quote) if note.is_some() || highlight.enriches(quote, chapter_id.as_deref()) => {
+                PreviewStatus::Newer
+            }
+            Some(_) => PreviewStatus::Duplicate,
+        };
+
+        let preview = PreviewQuote {
+            key: format!("kindle:{}", hash_parts(&[&notebook.title, &highlight.text])),
+            content: Some(highlight.text.clone()),
+            note: highlight.note.clone(),
+            status,
+        };
+        if !run.add_quote(&source_book, highlight.chapter.as_deref(), preview) {
+            continue;
+        }
+
+        if let Some(existing) = existing {
+            let (page_start, page_end) = highlight
+                .page
+                .map_or((existing.page_start, existing.page_end), |(s, e)| (Some(s), Some(e)));
+            let (location_start, location_end) = highlight.location.map_or(
+                (existing.location_start, existing.location_end),
+                |(s, e)| (Some(s), Some(e)),
+            );
+            let quote = models::Quote {
+                chapter_id: chapter_id.or(existing.chapter_id.clone()),
+                color: highlight.color.clone().or(existing.color.clone()),
+                page_start,
+                page_end,
+                location_start,
+                location_end,
+                updated_at: now,
+                ..existing
+            };
+            queries::update_quote_details(&quote, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to update quote".to_string()))?;
+            run.report.quotes.updated += 1;
+
+            if let Some(content) = note {
+                insert_notebook_note(content, &quote, now, run, &mut *conn).await?;
+            }
+            continue;
+        }
+
+        // Quote, the export has no dates
+        let quote = models::Quote {
+            id: Uuid::new_v4().to_string(),
+            book_id: Some(book.id.clone()),
+            author_id: author_id.clone(),
+            chapter_id,
+            chapter_progress: None,
+            content: Some(highlight.text.clone()),
+            starred: Some(0),
+            created_at: now,
+            updated_at: now,
+            imported_at: Some(now),
+            deleted_at: None,
+            original_id: None,
+            color: highlight.color.clone(),
+            page_start: highlight.page.map(|(start, _)| start),
+            page_end: highlight.page.map(|(_, end)| end),
+            location_start: highlight.location.map(|(start, _)| start),
+            location_end: highlight.location.map(|(_, end)| end),
+            import_batch_id: run.batch_id.clone(),
+        };
+
+        queries::insert_quote(&quote, &mut *conn)
+            .await
+            .map_err(|e| ImportError::DbError(e, "Failed to insert quote".to_string()))?;
+        run.report.quotes.created += 1;
+
+        if let Some(content) = note {
+            insert_notebook_note(content, &quote, now, run, &mut *conn).await?;
+        }
+    }
+
+    // Notes before the first highlight are notes on the book
+    let library_notes = queries::get_notes_by_book(&book.id, &mut *conn)
+        .await
+        .map_err(|e| ImportError::DbError(e, "Failed to fetch notes".to_string()))?;
+    for content in &notebook.notes {
+        if library_notes.iter().any(|n| n.content.as_deref() == Some(content.as_str())) {
+            run.report.notes.skipped += 1;
+            continue;
+        }
+
+        let note = models::Note {
+            id: Uuid::new_v4().to_string(),
+            book_id: Some(book.id.clone()),
+            author_id: author_id.clone(),
+            quote_id: None,
+            content: Some(content.clone()),
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            import_batch_id: run.batch_id.clone(),
+        };
+
+        queries::insert_note(&note, &mut *conn)
+            .await
+            .map_err(|e| ImportError::DbError(e, "Failed to insert note".to_string()))?;
+        run.report.notes.created += 1;
+    }
+
+    Ok(())
+}
+
+//
+// iBooks
+//
+//
 
 const IBOOKS_DB_PATH: &str = "/Library/Containers/com.apple.iBooksX/Data/Documents/";
-const IBOOKS_DB_ANNOTATION_PATH: &str = "AEAnnotation/AEAnnotation*.sqlite";
-const IBOOKS_DB_ASSET_PATH: &str = "BKLibrary/BKLibrary*.sqlite";
+/// Folder and file name of the annotation database in the iBooks container
+const IBOOKS_DB_ANNOTATION_PATH: (&str, &str) = ("AEAnnotation", "AEAnnotation*.sqlite");
+/// Folder and file name of the asset database in the iBooks container
+const IBOOKS_DB_ASSET_PATH: (&str, &str) = ("BKLibrary", "BKLibrary*.sqlite");
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 struct IBooksBookAuthor {
@@ -831,6 +2742,19 @@ struct IBooksQuote {
     created_at: f64,
     modified_at: Option<f64>,
     annotation: Option<String>,
+    style: Option<i64>,
+}
+
+/// Highlight color of an iBooks annotation style, underlines have none.
+fn ibooks_color(style: i64) -> Option<&'static str> {
+    match style {
+        1 => Some("green"),
+        2 => Some("blue"),
+        3 => Some("yellow"),
+        4 => Some("pink"),
+        5 => Some("purple"),
+        _ => None,
+    }
 }
 
 const QUERY_IBOOKS_BOOKS: &str = r#"
@@ -848,9 +2772,11 @@ const QUERY_IBOOKS_ANNOTATIONS: &str = r#"
         ZANNOTATIONASSETID AS book_id,       -- Verify column name (Links to Book)
         COALESCE(ZANNOTATIONSELECTEDTEXT, ZFUTUREPROOFING5) AS content, -- Verify column names (The highlighted text)
         -- Timestamps need conversion from Core Data (add 978307200 for Unix Epoch in your app)
-        ZANNOTATIONCREATIONDATE AS created_at, -- Verify column name
-        ZANNOTATIONMODIFICATIONDATE AS modified_at, -- Verify column name
-        ZANNOTATIONNOTE as annotation
+        -- Whole seconds are stored as integers
+        CAST(ZANNOTATIONCREATIONDATE AS REAL) AS created_at, -- Verify column name
+        CAST(ZANNOTATIONMODIFICATIONDATE AS REAL) AS modified_at, -- Verify column name
+        ZANNOTATIONNOTE as annotation,
+        ZANNOTATIONSTYLE as style
     FROM
         ZAEANNOTATION               -- Verify table name
     WHERE
@@ -859,65 +2785,96 @@ const QUERY_IBOOKS_ANNOTATIONS: &str = r#"
         AND COALESCE(ZANNOTATIONSELECTEDTEXT, ZFUTUREPROOFING5) != '';
     "#;
 
-async fn import_ibooks() -> Result<String, ImportError> {
-    let home_path = dirs::home_dir()
-        .ok_or_else(|| {
-            ImportError::IoError(io::Error::new(
-                io::ErrorKind::NotFound,
-                "Home directory not found",
-            ))
-        })?
-        .to_str()
-        .ok_or(ImportError::IoError(io::Error::new(
-            io::ErrorKind::NotFound,
-            "Error converting PathBuf to String",
-        )))?
-        .to_string();
-
-    let annotation_pattern = format!(
-        "{}{}{}",
-        home_path, IBOOKS_DB_PATH, IBOOKS_DB_ANNOTATION_PATH
-    );
-    let asset_pattern = format!("{}{}{}", home_path, IBOOKS_DB_PATH, IBOOKS_DB_ASSET_PATH);
-
-    let annotation_path = resolve_path(annotation_pattern.as_str()).map_err(|e| {
+/// The iBooks container folder of the current user, holding both databases.
+pub fn ibooks_documents_dir() -> Result<PathBuf, ImportError> {
+    let home_path = dirs::home_dir().ok_or_else(|| {
         ImportError::IoError(io::Error::new(
             io::ErrorKind::NotFound,
-            format!("iBooks annotation database not found: {}", e),
+            "Home directory not found",
         ))
     })?;
 
-    let asset_path = resolve_path(asset_pattern.as_str()).map_err(|e| {
-        ImportError::IoError(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!("iBooks asset database not found: {}", e),
-        ))
-    })?;
+    Ok(home_path.join(IBOOKS_DB_PATH.trim_start_matches('/')))
+}
 
-    if !annotation_path.exists()
-        || !annotation_path.is_file()
-        || !asset_path.exists()
-        || !asset_path.is_file()
-    {
-        return Err(ImportError::IoError(io::Error::new(
-            io::ErrorKind::NotFound,
-            "iBooks database files not found",
-        )));
+/// The two databases iBooks keeps its highlights in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IBooksDatabases {
+    /// `AEAnnotation*.sqlite`, the highlights and notes
+    pub annotation: PathBuf,
+    /// `BKLibrary*.sqlite`, the books
+    pub asset: PathBuf,
+}
+
+impl IBooksDatabases {
+    /// Find the databases in `dir`, the iBooks container folder, see [`ibooks_documents_dir`],
+    /// or a folder holding copies of both, e.g. from a backup.
+    pub fn find(dir: &Path) -> Result<Self, ImportError> {
+        let find = |(folder, file): (&str, &str), name: &str| {
+            let dir = glob::Pattern::escape(&dir.to_string_lossy());
+            resolve_path(&format!("{}/{}/{}", dir, folder, file))
+                .or_else(|_| resolve_path(&format!("{}/{}", dir, file)))
+                .map_err(|e| {
+                    ImportError::IoError(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("iBooks {} database not found: {}", name, e),
+                    ))
+                })
+        };
+
+        Ok(IBooksDatabases {
+            annotation: find(IBOOKS_DB_ANNOTATION_PATH, "annotation")?,
+            asset: find(IBOOKS_DB_ASSET_PATH, "asset")?,
+        })
+    }
+}
+
+/// Import the highlights of iBooks.
+///
+/// * `path` - The iBooks container folder, see [`ibooks_documents_dir`], or a folder with
+///   copies of its databases, see [`IBooksDatabases::find`].
+pub async fn import_ibooks(
+    path: &str,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    let databases = IBooksDatabases::find(Path::new(path))?;
+    import_ibooks_databases(&databases, run, conn).await
+}
+
+/// Import the highlights of iBooks from its two databases.
+pub async fn import_ibooks_databases(
+    databases: &IBooksDatabases,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    for (database, name) in [(&databases.annotation, "annotation"), (&databases.asset, "asset")] {
+        if !database.is_file() {
+            return Err(ImportError::IoError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("iBooks {} database not found: {}", name, database.display()),
+            )));
+        }
     }
 
-    let annotation_db = SqlitePool::connect(annotation_path.to_str().unwrap())
-        .await
-        .map_err(|e| {
-            ImportError::DbError(
-                e,
-                "Failed to connect to iBooks annotation database".to_string(),
-            )
-        })?;
-    let asset_db = SqlitePool::connect(asset_path.to_str().unwrap())
-        .await
-        .map_err(|e| {
-            ImportError::DbError(e, "Failed to connect to iBooks asset database".to_string())
-        })?;
+    let annotation_db = SqlitePool::connect_with(
+        sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(&databases.annotation)
+            .read_only(true),
+    )
+    .await
+    .map_err(|e| {
+        ImportError::DbError(e, "Failed to connect to iBooks annotation database".to_string())
+    })?;
+    let asset_db = SqlitePool::connect_with(
+        sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(&databases.asset)
+            .read_only(true),
+    )
+    .await
+    .map_err(|e| {
+        ImportError::DbError(e, "Failed to connect to iBooks asset database".to_string())
+    })?;
 
     let mut books_author = sqlx::query_as::<_, IBooksBookAuthor>(QUERY_IBOOKS_BOOKS)
         .fetch_all(&asset_db)
@@ -942,74 +2899,104 @@ async fn import_ibooks() -> Result<String, ImportError> {
         })
         .collect();
 
-    let pool = db::get_pool();
-    let mut tx = pool
-        .begin()
-        .await
-        .map_err(|e| ImportError::DbError(e, "Failed to begin transaction".to_string()))?;
-
     // Map of book id to book.
     let mut books_id_map = HashMap::new();
-    // Map of author name to author.
+    // Map of book id to author.
     let mut authors_id_map = HashMap::new();
-    let mut imported_books = 0;
-    let mut imported_quotes = 0;
+    // Map of author name to author.
+    let mut author_names_map = HashMap::new();
+    // Map of book id to the book in the preview.
+    let mut source_books = HashMap::new();
+
+    for (i, book) in books_author.iter().enumerate() {
+        run.progress(ImportPhase::Books, i + 1, books_author.len(), Some(&book.title))?;
+        let mut source_book = SourceBook {
+            key: book.id.clone(),
+            title: book.title.clone(),
+            author: book.author.clone(),
+            status: PreviewStatus::New,
+        };
 
-    for book in books_author.iter() {
         // Skip if book already exists
-        if let Ok(existing_book) = queries::get_book_by_original_id(book.id.clone(), &mut *tx).await
+        if let Ok(existing_book) = queries::get_book_by_original_id(book.id.clone(), &mut *conn).await
         {
             books_id_map.insert(book.id.clone(), existing_book.id.clone());
+            run.report.books.skipped += 1;
+            source_book.status = PreviewStatus::Duplicate;
+            source_books.insert(book.id.clone(), source_book);
+            continue;
+        }
+
+        source_books.insert(book.id.clone(), source_book);
+        if !run.imports_book(&book.id) {
             continue;
         }
 
         // Author
-        let author_id = match book.author.clone() {
-            Some(book_author) => {
-                let author = match queries::get_author_by_name(book_author.clone(), &mut *tx).await
-                {
-                    Ok(existing_author) => existing_author,
-                    Err(_) => queries::insert_author(book_author.clone(), &mut *tx)
-                        .await
-                        .map_err(|e| {
-                            ImportError::DbError(e, "Failed to insert author".to_string())
-                        })?,
-                };
-                Some(author.id)
-            }
+        let author_id = match &book.author {
+            Some(book_author) => Some(
+                get_or_insert_author(book_author, &mut author_names_map, run, &mut *conn)
+                    .await?,
+            ),
             None => None,
         };
 
         authors_id_map.insert(book.id.clone(), author_id.clone());
 
         // Book
-        let db_book = queries::insert_book_with_defaults(
+        let db_book = queries::insert_imported_book(
             book.title.clone(),
             author_id.clone(),
             Some(book.id.clone()),
-            &mut *tx,
+            run.batch_id.as_deref(),
+            &mut *conn,
         )
         .await
         .map_err(|e| ImportError::DbError(e, "Failed to insert book".to_string()))?;
 
         books_id_map.insert(book.id.clone(), db_book.id.clone());
 
-        imported_books += 1;
+        run.report.books.created += 1;
     }
 
-    for quote in quotes_annotations.iter() {
-        // Skip if quote already exists
-        if let Ok(_) = queries::get_quote_by_original_id(quote.id.clone(), &mut *tx).await {
+    for (i, quote) in quotes_annotations.iter().enumerate() {
+        let title = source_books.get(&quote.book_id).map(|b| b.title.as_str());
+        run.progress(ImportPhase::Quotes, i + 1, quotes_annotations.len(), title)?;
+        let Some(book) = source_books.get(&quote.book_id) else {
+            log::info!("Quote missing book {}", quote.book_id);
+            run.report.error(
+                format!("annotation {}", quote.id),
+                format!("Book {} not found", quote.book_id),
+            );
+            continue;
+        };
+        if !run.imports_book(&book.key) {
             continue;
         }
 
-        let book_id = books_id_map.get(&quote.book_id).cloned();
-        let author_id = authors_id_map.get(&quote.book_id).unwrap_or(&None).clone();
-
-        if book_id.is_none() {
-            log::info!("Quote missing book {}", quote.book_id);
+        // Skip if quote already exists, unless it changed and is selected
+        let existing = queries::get_quote_by_original_id(quote.id.clone(), &mut *conn)
+            .await
+            .ok();
+        let preview = PreviewQuote {
+            key: quote.id.clone(),
+            content: Some(quote.content.clone()),
+            note: quote.annotation.clone(),
+            status: quote_status(existing.as_ref(), Some(&quote.content)),
+        };
+        if !run.add_quote(book, None, preview) {
             continue;
         }
+        if let Some(existing) = existing {
+            queries::update_quote_content(&existing.id, &quote.content, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to update quote".to_string()))?;
+            run.report.quotes.updated += 1;
+            continue;
+        }
+
+        let book_id = books_id_map.get(&quote.book_id).cloned();
+        let author_id = authors_id_map.get(&quote.book_id).unwrap_or(&None).clone();
 
         let created_at: NaiveDateTime =
             core_data_to_naive_datetime(quote.created_at).unwrap_or_else(|| Utc::now().naive_utc());
@@ -1034,13 +3021,19 @@ async fn import_ibooks() -> Result<String, ImportError> {
             imported_at: Some(Utc::now().naive_utc()),
             deleted_at: None,
             original_id: Some(quote.id.clone()),
+            color: quote.style.and_then(ibooks_color).map(str::to_string),
+            page_start: None,
+            page_end: None,
+            location_start: None,
+            location_end: None,
+            import_batch_id: run.batch_id.clone(),
         };
 
-        let db_quote = queries::insert_quote(&new_quote, &mut *tx)
+        let db_quote = queries::insert_quote(&new_quote, &mut *conn)
             .await
             .map_err(|e| ImportError::DbError(e, "Failed to insert quote".to_string()))?;
 
-        imported_quotes += 1;
+        run.report.quotes.created += 1;
 
         if let Some(annotation) = &quote.annotation {
             let note = models::Note {
@@ -1052,20 +3045,1177 @@ async fn import_ibooks() -> Result<String, ImportError> {
                 created_at,
                 updated_at,
                 deleted_at: None,
+                import_batch_id: run.batch_id.clone(),
             };
 
-            queries::insert_note(&note, &mut *tx)
+            queries::insert_note(&note, &mut *conn)
                 .await
                 .map_err(|e| ImportError::DbError(e, "Failed to insert note".to_string()))?;
+
+            run.report.notes.created += 1;
         }
     }
 
-    tx.commit()
-        .await
-        .map_err(|e| ImportError::DbError(e, "Failed to commit transaction".to_string()))?;
+    Ok(())
+}
 
-    Ok(format!(
-        "Imported successfully {} new books and {} new quotes",
-        imported_books, imported_quotes
-    ))
+//
+// Readwise CSV
+//
+//
+
+/// Readwise marks favorite highlights with this tag, imported as starred quotes.
+pub const READWISE_FAVORITE_TAG: &str = "favorite";
+
+/// A row of a Readwise CSV.
+///
+/// Accepts both the Readwise export columns ("Book Title", "Book Author", "Highlighted at")
+/// and the upload template ones ("Title", "Author", "Date").
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReadwiseHighlight {
+    #[serde(rename = "Highlight")]
+    pub highlight: String,
+    #[serde(rename = "Book Title", alias = "Title")]
+    pub title: String,
+    #[serde(rename = "Book Author", alias = "Author", default)]
+    pub author: Option<String>,
+    #[serde(rename = "Note", default)]
+    pub note: Option<String>,
+    #[serde(rename = "Location", default)]
+    pub location: Option<String>,
+    /// What `location` is, "location" or "page" for books, the other types are not kept
+    #[serde(rename = "Location Type", default)]
+    pub location_type: Option<String>,
+    #[serde(rename = "Highlighted at", alias = "Date", default)]
+    pub highlighted_at: Option<String>,
+    #[serde(rename = "Tags", default)]
+    pub tags: Option<String>,
+}
+
+/// Read the highlights of a Readwise CSV, rows without highlight text are left out.
+///
+/// Invalid rows are skipped and added to `report`.
+pub fn read_readwise_csv<R: io::Read>(reader: R, report: &mut ImportReport) -> Vec<ReadwiseHighlight> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::Headers)
+        .from_reader(reader);
+
+    let mut highlights = Vec::new();
+    for (i, row) in reader.deserialize::<ReadwiseHighlight>().enumerate() {
+        // Line 1 is the header
+        let highlight = match row {
+            Ok(highlight) => highlight,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or(i as u64 + 2);
+                report.error(format!("line {}", line), format!("Invalid Readwise row: {}", e));
+                continue;
+            }
+        };
+        if !highlight.highlight.trim().is_empty() && !highlight.title.trim().is_empty() {
+            highlights.push(highlight);
+        }
+    }
+
+    highlights
+}
+
+/// Split a Readwise note into its inline tags and the note text.
+///
+/// Readwise treats leading `.tag` words of a note as tags, e.g. ".stoic .favorite Some note".
+pub fn split_readwise_note(note: &str) -> (Vec<String>, Option<String>) {
+    let mut tags = Vec::new();
+    let mut rest = note.trim();
+    while let Some(tag) = rest.strip_prefix('.') {
+        if !tag.starts_with(char::is_alphanumeric) {
+            break;
+        }
+        let end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        tags.push(tag[..end].to_string());
+        rest = tag[end..].trim_start();
+    }
+
+    let text = Some(rest.to_string()).filter(|t| !t.is_empty());
+    (tags, text)
+}
+
+fn parse_readwise_datetime(s: &str) -> Option<NaiveDateTime> {
+    let s = s.trim();
+    chrono::DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%:z")
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(s))
+        .map(|dt| dt.naive_utc())
+        .ok()
+        .or_else(|| parse_datetime(s).ok())
+        .or_else(|| NaiveDateTime::parse_from_str(s, "%B %d, %Y %I:%M:%S %p").ok())
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// Stable id for a Readwise row, Readwise CSVs carry no highlight id.
+fn readwise_original_id(parts: &[&str]) -> String {
+    format!("readwise:{}", hash_parts(parts))
+}
+
+/// Import a Readwise CSV.
+///
+/// Books are matched by their Readwise id first, then by title and author, quotes by their
+/// Readwise id or their content, so importing the same file again adds nothing.
+pub async fn import_readwise(
+    path: &str,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    let path = Path::new(path);
+    if !path.exists() || !path.is_file() {
+        return Err(ImportError::IoError(io::Error::new(
+            io::ErrorKind::NotFound,
+            "File not found",
+        )));
+    }
+
+    let highlights = read_readwise_csv(File::open(path)?, &mut run.report);
+
+    // Map of (title, author) to (book id, author id).
+    let mut books_id_map: HashMap<(String, Option<String>), (String, Option<String>)> =
+        HashMap::new();
+    // Map of author name to author.
+    let mut authors_id_map = HashMap::new();
+    // Map of (title, author) to the book in the preview.
+    let mut source_books = HashMap::new();
+    // Map of lowercase tag name to tag id.
+    let mut tags_id_map: HashMap<String, String> = HashMap::new();
+
+    for (i, highlight) in highlights.iter().enumerate() {
+        run.progress(ImportPhase::Quotes, i + 1, highlights.len(), Some(highlight.title.trim()))?;
+        let title = highlight.title.trim().to_string();
+        let author_name = highlight
+            .author
+            .as_deref()
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(str::to_string);
+
+        let book_original_id =
+            readwise_original_id(&[&title, author_name.as_deref().unwrap_or_default()]);
+        if !run.imports_book(&book_original_id) {
+            continue;
+        }
+
+        let book_key = (title.clone(), author_name.clone());
+        let (book_id, author_id) = match books_id_map.get(&book_key) {
+            Some(ids) => ids.clone(),
+            None => {
+                let mut source_book = SourceBook {
+                    key: book_original_id.clone(),
+                    title: title.clone(),
+                    author: author_name.clone(),
+                    status: PreviewStatus::Duplicate,
+                };
+
+                // Author
+                let author_id = match &author_name {
+                    Some(name) => Some(
+                        get_or_insert_author(name, &mut authors_id_map, run, &mut *conn)
+                            .await?,
+                    ),
+                    None => None,
+                };
+
+                // Book
+                let book = match queries::get_book_by_original_id(book_original_id.clone(), &mut *conn)
+                    .await
+                {
+                    Ok(existing_book) => {
+                        run.report.books.skipped += 1;
+                        existing_book
+                    }
+                    Err(_) => match queries::get_book_by_title_and_author(
+                        &title,
+                        author_id.as_deref(),
+                        &mut *conn,
+                    )
+                    .await
+                    {
+                        Ok(existing_book) => {
+                            run.report.books.skipped += 1;
+                            existing_book
+                        }
+                        Err(_) => {
+                            let book = queries::insert_imported_book(
+                                title.clone(),
+                                author_id.clone(),
+                                Some(book_original_id.clone()),
+                                run.batch_id.as_deref(),
+                                &mut *conn,
+                            )
+                            .await
+                            .map_err(|e| {
+                                ImportError::DbError(e, "Failed to insert book".to_string())
+                            })?;
+                            run.report.books.created += 1;
+                            source_book.status = PreviewStatus::New;
+                            book
+                        }
+                    },
+                };
+
+                books_id_map.insert(book_key.clone(), (book.id.clone(), author_id.clone()));
+                source_books.insert(book_key.clone(), source_book);
+                (book.id, author_id)
+            }
+        };
+
+        // Tags come from the Tags column and the inline tags of the note
+        let (mut tags, note) = match &highlight.note {
+            Some(note) => split_readwise_note(note),
+            None => (Vec::new(), None),
+        };
+
+        // Skip if quote already exists
+        let content = highlight.highlight.trim().to_string();
+        let original_id = readwise_original_id(&[
+            &title,
+            author_name.as_deref().unwrap_or_default(),
+            &content,
+        ]);
+        let exists = queries::get_quote_by_original_id(original_id.clone(), &mut *conn)
+            .await
+            .is_ok()
+            || queries::get_quote_by_book_and_content(book_id.clone(), content.clone(), &mut *conn)
+                .await
+                .is_ok();
+        let preview = PreviewQuote {
+            key: original_id.clone(),
+            content: Some(content.clone()),
+            note: note.clone(),
+            status: if exists {
+                PreviewStatus::Duplicate
+            } else {
+                PreviewStatus::New
+            },
+        };
+        if !run.add_quote(&source_books[&book_key], None, preview) {
+            continue;
+        }
+
+        if let Some(column) = &highlight.tags {
+            tags.extend(column.split(',').map(|t| t.trim().to_string()));
+        }
+        let mut seen = HashSet::new();
+        tags.retain(|t| !t.is_empty() && seen.insert(t.to_lowercase()));
+        let starred = tags
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(READWISE_FAVORITE_TAG));
+        tags.retain(|t| !t.eq_ignore_ascii_case(READWISE_FAVORITE_TAG));
+
+        let now = Utc::now().naive_utc();
+        let created_at = highlight
+            .highlighted_at
+            .as_deref()
+            .and_then(parse_readwise_datetime)
+            .unwrap_or(now);
+
+        // The location keeps the reading order within the book, the upload template has no type
+        let position = highlight.location.as_deref().and_then(|l| l.trim().parse::<i64>().ok());
+        let (page, location) = match highlight.location_type.as_deref().map(str::trim) {
+            None | Some("") | Some("location") => (None, position),
+            Some("page") => (position, None),
+            Some(_) => (None, None),
+        };
+
+        // Quote
+        let quote = models::Quote {
+            id: Uuid::new_v4().to_string(),
+            book_id: Some(book_id.clone()),
+            author_id: author_id.clone(),
+            chapter_id: None,
+            chapter_progress: None,
+            content: Some(content),
+            starred: Some(starred as i64),
+            created_at,
+            updated_at: created_at,
+            imported_at: Some(now),
+            deleted_at: None,
+            original_id: Some(original_id),
+            color: None,
+            page_start: page,
+            page_end: page,
+            location_start: location,
+            location_end: location,
+            import_batch_id: run.batch_id.clone(),
+        };
+
+        let db_quote = queries::insert_quote(&quote, &mut *conn)
+            .await
+            .map_err(|e| ImportError::DbError(e, "Failed to insert quote".to_string()))?;
+
+        run.report.quotes.created += 1;
+
+        // Tags
+        for tag_name in tags {
+            let key = tag_name.to_lowercase();
+            let tag_id = match tags_id_map.get(&key) {
+                Some(tag_id) => tag_id.clone(),
+                None => {
+                    let tag = match queries::get_tag_by_name(&tag_name, &mut *conn).await {
+                        Ok(existing_tag) => existing_tag,
+                        Err(_) => {
+                            let tag = queries::insert_tag(
+                                &models::Tag {
+                                    id: Uuid::new_v4().to_string(),
+                                    name: tag_name.clone(),
+                                    color: None,
+                                    import_batch_id: run.batch_id.clone(),
+                                },
+                                &mut *conn,
+                            )
+                            .await
+                            .map_err(|e| {
+                                ImportError::DbError(e, "Failed to insert tag".to_string())
+                            })?;
+                            tag
+                        }
+                    };
+                    tags_id_map.insert(key, tag.id.clone());
+                    tag.id
+                }
+            };
+
+            queries::insert_quote_tag(&db_quote.id, &tag_id, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to tag quote".to_string()))?;
+        }
+
+        // Note
+        if let Some(note) = note {
+            let note = models::Note {
+                id: Uuid::new_v4().to_string(),
+                book_id: Some(book_id.clone()),
+                author_id: author_id.clone(),
+                quote_id: Some(db_quote.id.clone()),
+                content: Some(note),
+                created_at,
+                updated_at: created_at,
+                deleted_at: None,
+                import_batch_id: run.batch_id.clone(),
+            };
+
+            queries::insert_note(&note, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to insert note".to_string()))?;
+
+            run.report.notes.created += 1;
+        }
+    }
+
+    Ok(())
+}
+
+//
+// KOReader
+//
+//
+
+/// A highlight read from a KOReader `metadata.*.lua` sidecar.
+#[derive(Debug, Clone, Default)]
+pub struct KoreaderHighlight {
+    pub chapter: Option<String>,
+    pub text: String,
+    pub note: Option<String>,
+    pub datetime: Option<String>,
+    pub datetime_updated: Option<String>,
+    pub page: Option<f64>,
+    /// Position of the highlight in the document, xpointer or page number.
+    pub pos: String,
+}
+
+/// A document and its highlights read from a KOReader sidecar.
+#[derive(Debug, Clone, Default)]
+pub struct KoreaderDocument {
+    pub doc_path: String,
+    pub title: String,
+    pub author: Option<String>,
+    pub highlights: Vec<KoreaderHighlight>,
+}
+
+fn lua_text(table: &lua::LuaValue, key: &str) -> Option<String> {
+    table
+        .get(key)
+        .and_then(lua::LuaValue::to_text)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Position of a highlight, used to build its original id.
+///
+/// Paged documents (PDF, CBZ) locate a highlight with a `{page, x, y}` table rather than an
+/// xpointer, several highlights can share a page.
+fn koreader_pos(item: &lua::LuaValue) -> Option<String> {
+    let point = item.get("pos0").and_then(|pos| {
+        let (page, x, y) = (lua_text(pos, "page")?, lua_text(pos, "x")?, lua_text(pos, "y")?);
+        Some(format!("{}@{},{}", page, x, y))
+    });
+
+    point
+        .or_else(|| lua_text(item, "pos0"))
+        .or_else(|| lua_text(item, "page"))
+        .or_else(|| lua_text(item, "pageno"))
+        .or_else(|| lua_text(item, "datetime"))
+}
+
+/// Path of the document a sidecar belongs to, for sidecars without `doc_path`.
+///
+/// `Book.sdr/metadata.epub.lua` belongs to `Book.epub`.
+fn koreader_doc_path(sidecar: &Path) -> String {
+    let extension = sidecar
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.strip_prefix("metadata."))
+        .and_then(|n| n.strip_suffix(".lua"))
+        .unwrap_or_default();
+    let dir = sidecar.parent().unwrap_or(Path::new(""));
+    let dir = dir.to_string_lossy();
+    let stem = dir.strip_suffix(".sdr").unwrap_or(&dir);
+
+    if extension.is_empty() {
+        stem.to_string()
+    } else {
+        format!("{}.{}", stem, extension)
+    }
+}
+
+/// Parse a KOReader sidecar.
+///
+/// Reads the `annotations` table of current KOReader versions, or the `highlight` and
+/// `bookmarks` tables of older ones. Bookmarks without highlighted text are left out.
+pub fn parse_koreader_metadata(
+    source: &str,
+    sidecar: &Path,
+) -> Result<KoreaderDocument, ImportError> {
+    let table = lua::parse(source).map_err(|e| {
+        ImportError::InvalidFormat(format!(
+            "Invalid KOReader metadata {}: {}",
+            sidecar.display(),
+            e
+        ))
+    })?;
+
+    let doc_path = lua_text(&table, "doc_path").unwrap_or_else(|| koreader_doc_path(sidecar));
+    let props = table.get("doc_props");
+    let title = props
+        .and_then(|p| lua_text(p, "title"))
+        .unwrap_or_else(|| {
+            Path::new(&doc_path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| doc_path.clone())
+        });
+    // Multiple authors are separated by new lines
+    let author = props.and_then(|p| lua_text(p, "authors")).map(|a| {
+        a.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join(", ")
+    });
+
+    let mut highlights = Vec::new();
+    if let Some(annotations) = table.get("annotations") {
+        for item in annotations.values() {
+            let (Some(text), Some(pos)) = (lua_text(item, "text"), koreader_pos(item)) else {
+                continue;
+            };
+            highlights.push(KoreaderHighlight {
+                chapter: lua_text(item, "chapter"),
+                text,
+                note: lua_text(item, "note"),
+                datetime: lua_text(item, "datetime"),
+                datetime_updated: lua_text(item, "datetime_updated"),
+                page: item
+                    .get("pageno")
+                    .or_else(|| item.get("page"))
+                    .and_then(lua::LuaValue::as_f64),
+                pos,
+            });
+        }
+    } else if let Some(legacy) = table.get("highlight") {
+        // Notes live in the bookmarks, matched to their highlight by date. The bookmark
+        // text is generated ("Page 12 ... @ date") until the user edits it.
+        let re_generated = Regex::new(r"(?s)^Page \S+ .* @ \d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}$").unwrap();
+        let mut notes = HashMap::new();
+        for bookmark in table.get("bookmarks").map(|b| b.values()).unwrap_or_default() {
+            if let (Some(datetime), Some(text)) =
+                (lua_text(bookmark, "datetime"), lua_text(bookmark, "text"))
+            {
+                if !re_generated.is_match(&text) && Some(&text) != lua_text(bookmark, "notes").as_ref() {
+                    notes.insert(datetime, text);
+                }
+            }
+        }
+
+        for (page, items) in legacy.entries() {
+            for item in items.values() {
+                let (Some(text), Some(pos)) = (lua_text(item, "text"), koreader_pos(item)) else {
+                    continue;
+                };
+                let datetime = lua_text(item, "datetime");
+                highlights.push(KoreaderHighlight {
+                    chapter: lua_text(item, "chapter"),
+                    text,
+                    note: datetime.as_ref().and_then(|d| notes.get(d).cloned()),
+                    datetime,
+                    datetime_updated: None,
+                    page: page.as_f64(),
+                    pos,
+                });
+            }
+        }
+    }
+
+    Ok(KoreaderDocument {
+        doc_path,
+        title,
+        author,
+        highlights,
+    })
+}
+
+/// Find the KOReader sidecars under `path`, in `.sdr` folders or a docsettings folder.
+pub fn find_koreader_sidecars(path: &Path) -> Result<Vec<PathBuf>, ImportError> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        return Err(ImportError::IoError(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Folder not found",
+        )));
+    }
+
+    let pattern = format!(
+        "{}/**/metadata.*.lua",
+        glob::Pattern::escape(&path.to_string_lossy())
+    );
+    let mut sidecars: Vec<PathBuf> = glob(&pattern)
+        .map_err(|e| ImportError::InvalidFormat(format!("Invalid folder path: {}", e)))?
+        .filter_map(|entry| entry.ok())
+        .filter(|p| p.is_file())
+        .collect();
+    sidecars.sort();
+
+    Ok(sidecars)
+}
+
+/// Import the KOReader highlights found under `path`.
+///
+/// Books are identified by their document path, quotes by document path and position,
+/// so importing the same folder again only adds the new highlights. Sidecars that cannot
+/// be read are skipped.
+pub async fn import_koreader(
+    path: &str,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    let mut documents = Vec::new();
+    let sidecars = find_koreader_sidecars(Path::new(path))?;
+    for (i, sidecar) in sidecars.iter().enumerate() {
+        run.progress(ImportPhase::Reading, i + 1, sidecars.len(), None)?;
+        let document = std::fs::read_to_string(sidecar)
+            .map_err(ImportError::from)
+            .and_then(|source| parse_koreader_metadata(&source, sidecar));
+        match document {
+            Ok(document) if !document.highlights.is_empty() => documents.push(document),
+            Ok(_) => {}
+            Err(e) => run.report.error(sidecar.to_string_lossy(), e.to_string()),
+        }
+    }
+
+    // Map of author name to author.
+    let mut authors_id_map = HashMap::new();
+
+    for (i, document) in documents.iter().enumerate() {
+        run.progress(ImportPhase::Books, i + 1, documents.len(), Some(&document.title))?;
+        let book_original_id = format!("koreader:{}", document.doc_path);
+        if !run.imports_book(&book_original_id) {
+            continue;
+        }
+        let mut source_book = SourceBook {
+            key: book_original_id.clone(),
+            title: document.title.clone(),
+            author: document.author.clone(),
+            status: PreviewStatus::Duplicate,
+        };
+
+        // Author
+        let author_id = match &document.author {
+            Some(name) => Some(
+                get_or_insert_author(name, &mut authors_id_map, run, &mut *conn).await?,
+            ),
+            None => None,
+        };
+
+        // Book
+        let book = match queries::get_book_by_original_id(book_original_id.clone(), &mut *conn)
+            .await
+        {
+            Ok(existing_book) => {
+                run.report.books.skipped += 1;
+                existing_book
+            }
+            Err(_) => {
+                let book = queries::insert_imported_book(
+                    document.title.clone(),
+                    author_id.clone(),
+                    Some(book_original_id.clone()),
+                    run.batch_id.as_deref(),
+                    &mut *conn,
+                )
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to insert book".to_string()))?;
+                run.report.books.created += 1;
+                source_book.status = PreviewStatus::New;
+                book
+            }
+        };
+
+        // Chapters, in order of first highlight
+        let mut chapters_id_map = HashMap::new();
+        let now = Utc::now().naive_utc();
+        for title in document.highlights.iter().filter_map(|h| h.chapter.as_ref()) {
+            if chapters_id_map.contains_key(title) {
+                continue;
+            }
+
+            let original_id = format!("{}#{}", book_original_id, title);
+            if let Ok(existing_chapter) =
+                queries::get_chapter_by_original_id(original_id.clone(), &mut *conn).await
+            {
+                chapters_id_map.insert(title.clone(), existing_chapter.id.clone());
+                run.report.chapters.skipped += 1;
+                continue;
+            }
+
+            let new_chapter = models::Chapter {
+                id: Uuid::new_v4().to_string(),
+                book_id: Some(book.id.clone()),
+                title: title.clone(),
+                volume_index: chapters_id_map.len() as i64,
+                original_id: Some(original_id),
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+                import_batch_id: run.batch_id.clone(),
+            };
+
+            queries::insert_chapter(&new_chapter, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to insert chapter".to_string()))?;
+            chapters_id_map.insert(title.clone(), new_chapter.id.clone());
+            run.report.chapters.created += 1;
+        }
+
+        for highlight in document.highlights.iter() {
+            // Skip if quote already exists, unless it changed and is selected
+            let original_id = format!("{}#{}", book_original_id, highlight.pos);
+            let existing = queries::get_quote_by_original_id(original_id.clone(), &mut *conn)
+                .await
+                .ok();
+            let preview = PreviewQuote {
+                key: original_id.clone(),
+                content: Some(highlight.text.clone()),
+                note: highlight.note.clone(),
+                status: quote_status(existing.as_ref(), Some(&highlight.text)),
+            };
+            if !run.add_quote(&source_book, highlight.chapter.as_deref(), preview) {
+                continue;
+            }
+            if let Some(existing) = existing {
+                queries::update_quote_content(&existing.id, &highlight.text, &mut *conn)
+                    .await
+                    .map_err(|e| ImportError::DbError(e, "Failed to update quote".to_string()))?;
+                run.report.quotes.updated += 1;
+                continue;
+            }
+
+            let created_at = highlight
+                .datetime
+                .as_deref()
+                .and_then(|d| parse_datetime(d).ok())
+                .unwrap_or(now);
+            let updated_at = highlight
+                .datetime_updated
+                .as_deref()
+                .and_then(|d| parse_datetime(d).ok())
+                .unwrap_or(created_at);
+
+            // Quote
+            let quote = models::Quote {
+                id: Uuid::new_v4().to_string(),
+                book_id: Some(book.id.clone()),
+                author_id: author_id.clone(),
+                chapter_id: highlight
+                    .chapter
+                    .as_ref()
+                    .and_then(|c| chapters_id_map.get(c).cloned()),
+                chapter_progress: None,
+                content: Some(highlight.text.clone()),
+                starred: Some(0),
+                created_at,
+                updated_at,
+                imported_at: Some(now),
+                deleted_at: None,
+                original_id: Some(original_id),
+                color: None,
+                page_start: highlight.page.map(|p| p as i64),
+                page_end: highlight.page.map(|p| p as i64),
+                location_start: None,
+                location_end: None,
+                import_batch_id: run.batch_id.clone(),
+            };
+
+            let db_quote = queries::insert_quote(&quote, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to insert quote".to_string()))?;
+
+            run.report.quotes.created += 1;
+
+            // Note
+            if let Some(content) = &highlight.note {
+                let note = models::Note {
+                    id: Uuid::new_v4().to_string(),
+                    book_id: Some(book.id.clone()),
+                    author_id: author_id.clone(),
+                    quote_id: Some(db_quote.id.clone()),
+                    content: Some(content.clone()),
+                    created_at,
+                    updated_at,
+                    deleted_at: None,
+                    import_batch_id: run.batch_id.clone(),
+                };
+
+                queries::insert_note(&note, &mut *conn)
+                    .await
+                    .map_err(|e| ImportError::DbError(e, "Failed to insert note".to_string()))?;
+
+                run.report.notes.created += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//
+// Calibre
+//
+//
+
+const CALIBRE_DB_NAME: &str = "metadata.db";
+
+/// Colors of Calibre's built-in highlight styles.
+const CALIBRE_COLORS: [(&str, &str); 5] = [
+    ("yellow", "#ffeb6b"),
+    ("green", "#c1e7a4"),
+    ("blue", "#add8ff"),
+    ("red", "#ffb1b1"),
+    ("purple", "#e2c2f2"),
+];
+
+#[derive(Debug, FromRow)]
+struct CalibreBook {
+    id: i64,
+    uuid: Option<String>,
+    title: String,
+    author: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct CalibreAnnotationRow {
+    book: i64,
+    annot_id: String,
+    timestamp: f64,
+    annot_data: String,
+}
+
+/// Highlight stored as JSON in the `annot_data` column.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CalibreAnnotation {
+    pub highlighted_text: Option<String>,
+    pub notes: Option<String>,
+    /// Table of contents path of the highlight, the chapter is the last one.
+    pub toc_family_titles: Vec<String>,
+    pub spine_index: Option<i64>,
+    pub timestamp: Option<String>,
+    pub removed: bool,
+    pub style: Option<CalibreStyle>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CalibreStyle {
+    pub kind: Option<String>,
+    pub which: Option<String>,
+    #[serde(rename = "background-color")]
+    pub background_color: Option<String>,
+}
+
+impl CalibreStyle {
+    /// Name and hex value of the highlight color, underlines and strikeouts have none.
+    pub fn color(&self) -> Option<(String, String)> {
+        if self.kind.as_deref() != Some("color") {
+            return None;
+        }
+        if let Some(which) = &self.which {
+            return CALIBRE_COLORS
+                .iter()
+                .find(|(name, _)| name == which)
+                .map(|(name, hex)| (name.to_string(), hex.to_string()));
+        }
+        self.background_color
+            .as_ref()
+            .map(|hex| (hex.to_lowercase(), hex.to_lowercase()))
+    }
+}
+
+const QUERY_CALIBRE_BOOKS: &str = r#"
+    SELECT
+        b.id AS id,
+        b.uuid AS uuid,
+        b.title AS title,
+        (
+            SELECT group_concat(a.name, ', ')
+            FROM books_authors_link bal
+            INNER JOIN authors a ON a.id = bal.author
+            WHERE bal.book = b.id
+        ) AS author
+    FROM books b
+    WHERE b.id IN (SELECT book FROM annotations WHERE annot_type = 'highlight')
+    ORDER BY b.title;
+"#;
+
+const QUERY_CALIBRE_ANNOTATIONS: &str = r#"
+    SELECT
+        book,
+        annot_id,
+        timestamp,
+        annot_data
+    FROM annotations
+    WHERE annot_type = 'highlight'
+    ORDER BY book, timestamp;
+"#;
+
+/// Import the highlights of a Calibre library.
+///
+/// * `path` - The library folder, or its `metadata.db`.
+///
+/// Highlight colors are kept as tags named after the color.
+pub async fn import_calibre(
+    path: &str,
+    run: &mut ImportRun,
+    conn: &mut SqliteConnection,
+) -> Result<(), ImportError> {
+    let path = Path::new(path);
+    let db_path = if path.is_dir() {
+        path.join(CALIBRE_DB_NAME)
+    } else {
+        path.to_path_buf()
+    };
+    if !db_path.is_file() {
+        return Err(ImportError::IoError(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Calibre library database not found",
+        )));
+    }
+
+    let calibre_db = SqlitePool::connect_with(
+        sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(&db_path)
+            .read_only(true),
+    )
+    .await
+    .map_err(|e| ImportError::DbError(e, "Failed to open Calibre library".to_string()))?;
+
+    let books = sqlx::query_as::<_, CalibreBook>(QUERY_CALIBRE_BOOKS)
+        .fetch_all(&calibre_db)
+        .await
+        .map_err(|e| ImportError::DbError(e, "Failed to fetch books".to_string()))?;
+
+    let annotations = sqlx::query_as::<_, CalibreAnnotationRow>(QUERY_CALIBRE_ANNOTATIONS)
+        .fetch_all(&calibre_db)
+        .await
+        .map_err(|e| ImportError::DbError(e, "Failed to fetch annotations".to_string()))?;
+
+    calibre_db.close().await;
+
+    // Map of Calibre book id to (book id, author id, book original id).
+    let mut books_id_map = HashMap::new();
+    // Map of author name to author.
+    let mut authors_id_map = HashMap::new();
+    // Map of color name to tag id.
+    let mut tags_id_map: HashMap<String, String> = HashMap::new();
+
+    for (i, book) in books.iter().enumerate() {
+        run.progress(ImportPhase::Books, i + 1, books.len(), Some(&book.title))?;
+        let original_id = format!(
+            "calibre:{}",
+            book.uuid.clone().unwrap_or_else(|| book.id.to_string())
+        );
+        if !run.imports_book(&original_id) {
+            continue;
+        }
+        let author_name = book.author.clone().filter(|a| !a.trim().is_empty());
+        let mut source_book = SourceBook {
+            key: original_id.clone(),
+            title: book.title.clone(),
+            author: author_name.clone(),
+            status: PreviewStatus::Duplicate,
+        };
+
+        // Author
+        let author_id = match &author_name {
+            Some(book_author) => Some(
+                get_or_insert_author(book_author, &mut authors_id_map, run, &mut *conn)
+                    .await?,
+            ),
+            None => None,
+        };
+
+        // Book
+        let db_book = match queries::get_book_by_original_id(original_id.clone(), &mut *conn).await
+        {
+            Ok(existing_book) => {
+                run.report.books.skipped += 1;
+                existing_book
+            }
+            Err(_) => {
+                let db_book = queries::insert_imported_book(
+                    book.title.clone(),
+                    author_id.clone(),
+                    Some(original_id.clone()),
+                    run.batch_id.as_deref(),
+                    &mut *conn,
+                )
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to insert book".to_string()))?;
+                run.report.books.created += 1;
+                source_book.status = PreviewStatus::New;
+                db_book
+            }
+        };
+
+        books_id_map.insert(book.id, (db_book.id, author_id, source_book));
+    }
+
+    // Map of chapter original id to chapter id.
+    let mut chapters_id_map: HashMap<String, String> = HashMap::new();
+
+    for (i, row) in annotations.iter().enumerate() {
+        let title = books_id_map.get(&row.book).map(|(_, _, b)| b.title.as_str());
+        run.progress(ImportPhase::Quotes, i + 1, annotations.len(), title)?;
+        let Some((book_id, author_id, source_book)) = books_id_map.get(&row.book) else {
+            continue;
+        };
+
+        let annotation: CalibreAnnotation = match serde_json::from_str(&row.annot_data) {
+            Ok(annotation) => annotation,
+            Err(e) => {
+                run.report.error(
+                    format!("annotation {}", row.annot_id),
+                    format!("Invalid Calibre annotation: {}", e),
+                );
+                continue;
+            }
+        };
+        let Some(text) = annotation
+            .highlighted_text
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+        else {
+            continue;
+        };
+        if annotation.removed {
+            continue;
+        }
+
+        // Skip if quote already exists
+        let original_id = format!("calibre:{}", row.annot_id);
+        let existing = queries::get_quote_by_original_id(original_id.clone(), &mut *conn)
+            .await
+            .ok();
+        let notes = annotation.notes.as_deref().map(str::trim).filter(|n| !n.is_empty());
+        let preview = PreviewQuote {
+            key: original_id.clone(),
+            content: Some(text.to_string()),
+            note: notes.map(str::to_string),
+            status: quote_status(existing.as_ref(), Some(text)),
+        };
+        if !run.add_quote(
+            source_book,
+            annotation.toc_family_titles.last().map(String::as_str),
+            preview,
+        ) {
+            continue;
+        }
+        if let Some(existing) = existing {
+            queries::update_quote_content(&existing.id, text, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to update quote".to_string()))?;
+            run.report.quotes.updated += 1;
+            continue;
+        }
+
+        let now = Utc::now().naive_utc();
+
+        // Chapter
+        let chapter_id = match annotation.toc_family_titles.last() {
+            Some(title) => {
+                let chapter_original_id = format!("{}#{}", source_book.key, title);
+                match chapters_id_map.get(&chapter_original_id) {
+                    Some(chapter_id) => Some(chapter_id.clone()),
+                    None => {
+                        let chapter = match queries::get_chapter_by_original_id(
+                            chapter_original_id.clone(),
+                            &mut *conn,
+                        )
+                        .await
+                        {
+                            Ok(existing_chapter) => {
+                                run.report.chapters.skipped += 1;
+                                existing_chapter
+                            }
+                            Err(_) => {
+                                let new_chapter = models::Chapter {
+                                    id: Uuid::new_v4().to_string(),
+                                    book_id: Some(book_id.clone()),
+                                    title: title.clone(),
+                                    volume_index: annotation.spine_index.unwrap_or_default(),
+                                    original_id: Some(chapter_original_id.clone()),
+                                    created_at: now,
+                                    updated_at: now,
+                                    deleted_at: None,
+                                    import_batch_id: run.batch_id.clone(),
+                                };
+                                queries::insert_chapter(&new_chapter, &mut *conn)
+                                    .await
+                                    .map_err(|e| {
+                                        ImportError::DbError(
+                                            e,
+                                            "Failed to insert chapter".to_string(),
+                                        )
+                                    })?;
+                                run.report.chapters.created += 1;
+                                new_chapter
+                            }
+                        };
+                        chapters_id_map.insert(chapter_original_id, chapter.id.clone());
+                        Some(chapter.id)
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let created_at = annotation
+            .timestamp
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|dt| dt.naive_utc())
+            .or_else(|| {
+                Utc.timestamp_opt(row.timestamp.trunc() as i64, 0)
+                    .single()
+                    .map(|dt| dt.naive_utc())
+            })
+            .unwrap_or(now);
+        let color = annotation.style.as_ref().and_then(CalibreStyle::color);
+
+        // Quote
+        let quote = models::Quote {
+            id: Uuid::new_v4().to_string(),
+            book_id: Some(book_id.clone()),
+            author_id: author_id.clone(),
+            chapter_id,
+            chapter_progress: None,
+            content: Some(text.to_string()),
+            starred: Some(0),
+            created_at,
+            updated_at: created_at,
+            imported_at: Some(now),
+            deleted_at: None,
+            original_id: Some(original_id),
+            color: color.as_ref().map(|(name, _)| name.clone()),
+            page_start: None,
+            page_end: None,
+            location_start: None,
+            location_end: None,
+            import_batch_id: run.batch_id.clone(),
+        };
+
+        let db_quote = queries::insert_quote(&quote, &mut *conn)
+            .await
+            .map_err(|e| ImportError::DbError(e, "Failed to insert quote".to_string()))?;
+
+        run.report.quotes.created += 1;
+
+        // Color, also kept as a tag
+        if let Some((color_name, hex)) = color {
+            let tag_id = match tags_id_map.get(&color_name) {
+                Some(tag_id) => tag_id.clone(),
+                None => {
+                    let tag = match queries::get_tag_by_name(&color_name, &mut *conn).await {
+                        Ok(existing_tag) => existing_tag,
+                        Err(_) => {
+                            let tag = queries::insert_tag(
+                                &models::Tag {
+                                    id: Uuid::new_v4().to_string(),
+                                    name: color_name.clone(),
+                                    color: Some(hex),
+                                    import_batch_id: run.batch_id.clone(),
+                                },
+                                &mut *conn,
+                            )
+                            .await
+                            .map_err(|e| {
+                                ImportError::DbError(e, "Failed to insert tag".to_string())
+                            })?;
+                            tag
+                        }
+                    };
+                    tags_id_map.insert(color_name, tag.id.clone());
+                    tag.id
+                }
+            };
+
+            queries::insert_quote_tag(&db_quote.id, &tag_id, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to tag quote".to_string()))?;
+        }
+
+        // Note
+        if let Some(content) = notes {
+            let note = models::Note {
+                id: Uuid::new_v4().to_string(),
+                book_id: Some(book_id.clone()),
+                author_id: author_id.clone(),
+                quote_id: Some(db_quote.id.clone()),
+                content: Some(content.to_string()),
+                created_at,
+                updated_at: created_at,
+                deleted_at: None,
+                import_batch_id: run.batch_id.clone(),
+            };
+
+            queries::insert_note(&note, &mut *conn)
+                .await
+                .map_err(|e| ImportError::DbError(e, "Failed to insert note".to_string()))?;
+
+            run.report.notes.created += 1;
+        }
+    }
+
+    Ok(())
 }
@@ -1,5 +1,9 @@
+pub mod anki;
+pub mod backup;
 pub mod db;
+pub mod export;
 pub mod import;
+pub mod lua;
 pub mod menu;
 pub mod models;
 pub mod queries;
@@ -18,41 +22,253 @@ pub mod commands {
     use super::*;
     use crate::db::get_pool;
 
+    //
+    // Database
+    //
+
+    /// The error that prevented the database from starting, if any.
+    #[tauri::command]
+    pub async fn get_db_init_error() -> Option<&'static db::DbError> {
+        db::get_init_error()
+    }
+
     //
     // Import
     //
 
+    /// Import `path` from `source`, used for drag-and-drop and scripted imports.
+    /// Progress and the result are sent with the `importing` and `import-*` events,
+    /// tagged with the returned import id.
+    #[tauri::command]
+    pub async fn import_from_path(
+        app: AppHandle,
+        source: import::ImportSource,
+        path: String,
+    ) -> String {
+        import::spawn_import(app, source, Some(path.into()), None)
+    }
+
+    /// Import from iBooks, from the default container without a `path`.
+    /// Copies of the databases, e.g. from a backup, are imported with `annotation_path`
+    /// and `asset_path`.
+    #[tauri::command]
+    pub async fn import_from_ibooks(
+        app: AppHandle,
+        path: Option<String>,
+        annotation_path: Option<String>,
+        asset_path: Option<String>,
+    ) -> Result<String, String> {
+        let input = match (annotation_path, asset_path) {
+            (Some(annotation), Some(asset)) => {
+                Some(import::ImportInput::IBooksDatabases(import::IBooksDatabases {
+                    annotation: annotation.into(),
+                    asset: asset.into(),
+                }))
+            }
+            (None, None) => path.map(Into::into),
+            _ => return Err("Both iBooks databases are required".to_string()),
+        };
+
+        Ok(import::spawn_import(app, import::ImportSource::IBooks, input, None))
+    }
+
+    #[tauri::command]
+    pub async fn import_from_kobo(app: AppHandle, path: Option<String>) -> String {
+        import::spawn_import(app, import::ImportSource::Kobo, path.map(Into::into), None)
+    }
+
+    #[tauri::command]
+    pub async fn import_from_kindle(app: AppHandle, path: Option<String>) -> String {
+        import::spawn_import(app, import::ImportSource::Kindle, path.map(Into::into), None)
+    }
+
+    #[tauri::command]
+    pub async fn import_from_readwise(app: AppHandle, path: Option<String>) -> String {
+        import::spawn_import(app, import::ImportSource::Readwise, path.map(Into::into), None)
+    }
+
+    #[tauri::command]
+    pub async fn import_from_koreader(app: AppHandle, path: Option<String>) -> String {
+        import::spawn_import(app, import::ImportSource::KOReader, path.map(Into::into), None)
+    }
+
+    #[tauri::command]
+    pub async fn import_from_calibre(app: AppHandle, path: Option<String>) -> String {
+        import::spawn_import(app, import::ImportSource::Calibre, path.map(Into::into), None)
+    }
+
+    /// Preview importing from `source` without writing to the library.
+    /// Without an `input` the user picks a path.
+    /// The preview sends `import-progress` events and can be cancelled like an import.
+    #[tauri::command]
+    pub async fn preview_import(
+        app: AppHandle,
+        source: import::ImportSource,
+        input: Option<import::ImportInput>,
+    ) -> Result<import::ImportPreview, String> {
+        import::preview_from_source(&app, source, input).await
+    }
+
+    /// Import the books and quotes of `selection` from a previewed source.
+    #[tauri::command]
+    pub async fn import_selection(
+        app: AppHandle,
+        source: import::ImportSource,
+        input: import::ImportInput,
+        selection: import::ImportSelection,
+    ) -> String {
+        import::spawn_import(app, source, Some(input), Some(selection))
+    }
+
+    /// Stop the running import `id`, nothing it imported is kept.
+    #[tauri::command]
+    pub async fn cancel_import(id: &str) -> Result<(), String> {
+        if import::cancel_import(id) {
+            Ok(())
+        } else {
+            Err(format!("No running import {}", id))
+        }
+    }
+
+    /// The past imports with their report, most recent first.
+    #[tauri::command]
+    pub async fn get_import_batches() -> Result<Vec<ImportBatch>, String> {
+        queries::get_import_batches(get_pool())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub async fn get_import_batch(batch_id: &str) -> Result<ImportBatch, String> {
+        queries::get_import_batch(batch_id, get_pool())
+            .await
+            .map_err(|e| format!("Error fetching import batch {}: {}", batch_id, e))
+    }
+
+    /// Remove the rows created by an import, see [`queries::rollback_import_batch`].
     #[tauri::command]
-    pub async fn import_from_ibooks(app: AppHandle) -> bool {
-        let app_clone = app.clone();
+    pub async fn rollback_import_batch(batch_id: &str) -> Result<(), String> {
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
 
-        tokio::spawn(async move {
-            import::import_from_ibooks(&app_clone).await;
-        });
+        queries::rollback_import_batch(batch_id, &mut tx)
+            .await
+            .map_err(|e| format!("Error rolling back import batch {}: {}", batch_id, e))?;
 
-        true
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(())
     }
 
+    //
+    // Export
+    //
+
+    /// Export books to Markdown, one file per book. Exports the whole library
+    /// when `book_ids` is empty, asks for the folder when `dir` is missing.
     #[tauri::command]
-    pub async fn import_from_kobo(app: AppHandle) -> bool {
-        let app_clone = app.clone();
+    pub async fn export_markdown(
+        app: AppHandle,
+        book_ids: Option<Vec<String>>,
+        dir: Option<String>,
+    ) -> Result<Vec<String>, String> {
+        let dir = match dir {
+            Some(dir) => dir,
+            None => export::export_dialog(&app).await?,
+        };
 
-        tokio::spawn(async move {
-            import::import_from_kobo(&app_clone).await;
-        });
+        let mut conn = get_pool().acquire().await.map_err(|e| e.to_string())?;
+        let paths = export::export_markdown(book_ids.as_deref(), std::path::Path::new(&dir), &mut conn)
+            .await
+            .map_err(|e| format!("Error exporting to Markdown {}", e))?;
 
-        true
+        Ok(paths.iter().map(|p| p.to_string_lossy().to_string()).collect())
     }
 
+    /// Export the whole library as a Readwise CSV, asks for the file when `path` is missing.
+    /// Returns the number of highlights exported.
     #[tauri::command]
-    pub async fn import_from_kindle(app: AppHandle) -> bool {
-        let app_clone = app.clone();
+    pub async fn export_readwise(app: AppHandle, path: Option<String>) -> Result<usize, String> {
+        let path = match path {
+            Some(path) => path,
+            None => export::readwise_dialog(&app).await?,
+        };
+
+        let mut conn = get_pool().acquire().await.map_err(|e| e.to_string())?;
+        export::export_readwise_csv(std::path::Path::new(&path), &mut conn)
+            .await
+            .map_err(|e| format!("Error exporting to Readwise {}", e))
+    }
 
-        tokio::spawn(async move {
-            import::import_from_kindle(&app_clone).await;
-        });
+    /// Export the quotes matching `filter` as an Anki package, asks for the file
+    /// when `path` is missing. Returns the number of notes exported.
+    #[tauri::command]
+    pub async fn export_anki(
+        app: AppHandle,
+        filter: Option<QuoteFilter>,
+        note_type: Option<anki::AnkiNoteType>,
+        path: Option<String>,
+    ) -> Result<usize, String> {
+        let path = match path {
+            Some(path) => path,
+            None => anki::anki_dialog(&app).await?,
+        };
 
-        true
+        let mut conn = get_pool().acquire().await.map_err(|e| e.to_string())?;
+        anki::export_anki(
+            &filter.unwrap_or_default(),
+            note_type.unwrap_or_default(),
+            std::path::Path::new(&path),
+            &mut conn,
+        )
+        .await
+        .map_err(|e| format!("Error exporting to Anki {}", e))
+    }
+
+    //
+    // Backup
+    //
+
+    /// Save a JSON backup of the whole library, asks for the file when `path` is missing.
+    /// Returns the path of the backup.
+    #[tauri::command]
+    pub async fn backup_library(app: AppHandle, path: Option<String>) -> Result<String, String> {
+        let path = match path {
+            Some(path) => path,
+            None => backup::backup_dialog(&app).await?,
+        };
+
+        let mut conn = get_pool().acquire().await.map_err(|e| e.to_string())?;
+        backup::write_backup(std::path::Path::new(&path), &mut conn)
+            .await
+            .map_err(|e| format!("Error backing up library {}", e))?;
+
+        Ok(path)
+    }
+
+    /// Restore a JSON backup, asks for the file when `path` is missing.
+    #[tauri::command]
+    pub async fn restore_library(
+        app: AppHandle,
+        path: Option<String>,
+        mode: backup::RestoreMode,
+    ) -> Result<backup::RestoreSummary, String> {
+        let path = match path {
+            Some(path) => path,
+            None => backup::restore_dialog(&app).await?,
+        };
+
+        let library = backup::read_backup(std::path::Path::new(&path))
+            .map_err(|e| format!("Error reading backup {}", e))?;
+
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+        let summary = backup::restore_library(library, mode, &mut tx)
+            .await
+            .map_err(|e| format!("Error restoring library {}", e))?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(summary)
     }
 
     //
@@ -73,6 +289,31 @@ pub mod commands {
         Ok(Library { books, authors })
     }
 
+    /// Number of items per page when no limit is given.
+    const DEFAULT_PAGE_SIZE: i64 = 100;
+
+    #[tauri::command]
+    pub async fn get_books_page(
+        cursor: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<Page<Book>, String> {
+        let mut conn = get_pool().acquire().await.map_err(|e| e.to_string())?;
+        queries::get_books_page(cursor, limit.unwrap_or(DEFAULT_PAGE_SIZE), &mut conn)
+            .await
+            .map_err(|e| format!("Error fetching books {}", e))
+    }
+
+    #[tauri::command]
+    pub async fn get_authors_page(
+        cursor: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<Page<Author>, String> {
+        let mut conn = get_pool().acquire().await.map_err(|e| e.to_string())?;
+        queries::get_authors_page(cursor, limit.unwrap_or(DEFAULT_PAGE_SIZE), &mut conn)
+            .await
+            .map_err(|e| format!("Error fetching authors {}", e))
+    }
+
     #[tauri::command]
     pub async fn get_books_by_author(author_id: String) -> Result<Vec<Book>, String> {
         queries::get_all_books_by_author(author_id, get_pool())
@@ -91,6 +332,27 @@ pub mod commands {
             .map_err(|e| format!("Error fetching notes {}", e))
     }
 
+    #[tauri::command]
+    pub async fn get_book_quotes_page(
+        book_id: &str,
+        sort_by: Option<&str>,
+        sort_order: Option<&str>,
+        cursor: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<Page<QuoteWithTags>, String> {
+        let mut conn = get_pool().acquire().await.map_err(|e| e.to_string())?;
+        queries::get_all_quotes_by_book_id_page(
+            book_id,
+            sort_by,
+            sort_order,
+            cursor,
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+            &mut conn,
+        )
+        .await
+        .map_err(|e| format!("Error fetching notes {}", e))
+    }
+
     #[tauri::command]
     pub async fn get_book_chapters(book_id: &str) -> Result<Vec<Chapter>, String> {
         queries::get_chapters_by_book(book_id, get_pool())
@@ -105,11 +367,35 @@ pub mod commands {
             .map_err(|e| format!("Error fetching book notes {}", e))
     }
 
+    #[tauri::command]
+    pub async fn get_book_bookmarks(book_id: &str) -> Result<Vec<Bookmark>, String> {
+        queries::get_bookmarks_by_book(book_id, get_pool())
+            .await
+            .map_err(|e| format!("Error fetching book bookmarks {}", e))
+    }
+
+    #[tauri::command]
+    pub async fn delete_bookmark(bookmark_id: &str) -> Result<(), String> {
+        queries::delete_bookmark(bookmark_id, get_pool())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// The highlight colors in use, for the color filters.
+    #[tauri::command]
+    pub async fn get_quote_colors() -> Result<Vec<String>, String> {
+        queries::get_quote_colors(get_pool())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
     #[tauri::command]
     pub async fn search_quotes(search: &str) -> Result<Vec<QuoteFts>, String> {
-        queries::find_quotes(search, get_pool())
+        let quotes = queries::find_quotes(search, get_pool())
             .await
-            .map_err(|e| format!("Error fetching notes {}", e))
+            .map_err(|e| format!("Error fetching notes {}", e))?;
+        record_search(search).await;
+        Ok(quotes)
     }
 
     #[tauri::command]
@@ -117,9 +403,11 @@ pub mod commands {
         search: &str,
         book_title: &str,
     ) -> Result<Vec<QuoteFts>, String> {
-        queries::find_quotes_by_book_title(search, book_title, get_pool())
+        let quotes = queries::find_quotes_by_book_title(search, book_title, get_pool())
             .await
-            .map_err(|e| format!("Error fetching notes {}", e))
+            .map_err(|e| format!("Error fetching notes {}", e))?;
+        record_search(search).await;
+        Ok(quotes)
     }
 
     #[tauri::command]
@@ -127,23 +415,73 @@ pub mod commands {
         search: &str,
         author_name: &str,
     ) -> Result<Vec<QuoteFts>, String> {
-        queries::find_quotes_by_author_name(search, author_name, get_pool())
+        let quotes = queries::find_quotes_by_author_name(search, author_name, get_pool())
             .await
-            .map_err(|e| format!("Error fetching notes {}", e))
+            .map_err(|e| format!("Error fetching notes {}", e))?;
+        record_search(search).await;
+        Ok(quotes)
     }
 
     #[tauri::command]
     pub async fn search_books_by_title(search: &str) -> Result<Vec<Book>, String> {
-        queries::find_books_by_title(search, get_pool())
+        let books = queries::find_books_by_title(search, get_pool())
             .await
-            .map_err(|e| format!("Error searching books {}", e))
+            .map_err(|e| format!("Error searching books {}", e))?;
+        record_search(search).await;
+        Ok(books)
     }
 
     #[tauri::command]
     pub async fn search_authors_by_name(search: &str) -> Result<Vec<Author>, String> {
-        queries::find_authors_by_name(search, get_pool())
+        let authors = queries::find_authors_by_name(search, get_pool())
+            .await
+            .map_err(|e| format!("Error searching authors {}", e))?;
+        record_search(search).await;
+        Ok(authors)
+    }
+
+    /// Number of search history entries returned when no limit is given.
+    const SEARCH_HISTORY_DEFAULT_LIMIT: i64 = 10;
+
+    /// Add the query to the search history, failing to do so doesn't fail the search.
+    async fn record_search(search: &str) {
+        let result = async {
+            let mut conn = get_pool().acquire().await?;
+            queries::record_search(search, &mut conn).await
+        }
+        .await;
+
+        if let Err(e) = result {
+            log::error!("Error recording search {}: {}", search, e);
+        }
+    }
+
+    #[tauri::command]
+    pub async fn get_recent_searches(limit: Option<i64>) -> Result<Vec<SearchHistory>, String> {
+        queries::get_recent_searches(limit.unwrap_or(SEARCH_HISTORY_DEFAULT_LIMIT), get_pool())
+            .await
+            .map_err(|e| format!("Error fetching recent searches {}", e))
+    }
+
+    #[tauri::command]
+    pub async fn get_frequent_searches(limit: Option<i64>) -> Result<Vec<SearchHistory>, String> {
+        queries::get_frequent_searches(limit.unwrap_or(SEARCH_HISTORY_DEFAULT_LIMIT), get_pool())
             .await
-            .map_err(|e| format!("Error searching authors {}", e))
+            .map_err(|e| format!("Error fetching frequent searches {}", e))
+    }
+
+    #[tauri::command]
+    pub async fn delete_search(search_id: i64) -> Result<(), String> {
+        queries::delete_search(search_id, get_pool())
+            .await
+            .map_err(|e| format!("Error deleting search {}: {}", search_id, e))
+    }
+
+    #[tauri::command]
+    pub async fn clear_search_history() -> Result<(), String> {
+        queries::clear_search_history(get_pool())
+            .await
+            .map_err(|e| format!("Error clearing search history {}", e))
     }
 
     #[tauri::command]
@@ -243,6 +581,12 @@ pub mod commands {
             imported_at: None,
             deleted_at: None,
             original_id: None,
+            color: None,
+            page_start: None,
+            page_end: None,
+            location_start: None,
+            location_end: None,
+            import_batch_id: None,
         };
 
         let result = queries::insert_quote(&quote, &mut *tx)
@@ -338,12 +682,36 @@ pub mod commands {
         Ok(())
     }
 
+    //
+    // Trash
+    //
+
     #[tauri::command]
-    pub async fn update_author(author_id: &str, author_name: &str) -> Result<Author, String> {
+    pub async fn get_trash() -> Result<Trash, String> {
+        let pool = get_pool();
+        let quotes = queries::get_trashed_quotes(pool)
+            .await
+            .map_err(|e| format!("Error fetching trashed quotes {}", e))?;
+        let books = queries::get_trashed_books(pool)
+            .await
+            .map_err(|e| format!("Error fetching trashed books {}", e))?;
+        let authors = queries::get_trashed_authors(pool)
+            .await
+            .map_err(|e| format!("Error fetching trashed authors {}", e))?;
+
+        Ok(Trash {
+            quotes,
+            books,
+            authors,
+        })
+    }
+
+    #[tauri::command]
+    pub async fn restore_quote(quote_id: &str) -> Result<Quote, String> {
         let pool = get_pool();
         let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
 
-        let result = queries::update_author_name(author_id, author_name, &mut *tx)
+        let result = queries::restore_quote(quote_id, &mut *tx)
             .await
             .map_err(|e| e.to_string())?;
 
@@ -352,14 +720,123 @@ pub mod commands {
     }
 
     #[tauri::command]
-    pub async fn delete_author(author_id: String) -> Result<(), String> {
+    pub async fn restore_book(book_id: &str) -> Result<Book, String> {
         let pool = get_pool();
         let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
 
-        queries::delete_author_books(author_id.clone(), &mut *tx)
+        let result = queries::restore_book(book_id, &mut *tx)
             .await
             .map_err(|e| e.to_string())?;
 
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(result)
+    }
+
+    #[tauri::command]
+    pub async fn restore_author(author_id: &str) -> Result<Author, String> {
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+        let result = queries::restore_author(author_id, &mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(result)
+    }
+
+    #[tauri::command]
+    pub async fn purge_quote(quote_id: &str) -> Result<(), String> {
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+        queries::purge_quote(quote_id, &mut tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    #[tauri::command]
+    pub async fn purge_book(book_id: &str) -> Result<(), String> {
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+        queries::purge_book(book_id, &mut tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    #[tauri::command]
+    pub async fn purge_author(author_id: &str) -> Result<(), String> {
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+        queries::purge_author(author_id, &mut tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Permanently delete the trash content, optionally only items older than `older_than_days`.
+    #[tauri::command]
+    pub async fn empty_trash(older_than_days: Option<i64>) -> Result<u64, String> {
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+        let purged = queries::purge_trash(older_than_days, &mut tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(purged)
+    }
+
+    #[tauri::command]
+    pub async fn get_trash_retention_days() -> Result<Option<i64>, String> {
+        db::get_trash_retention_days(get_pool())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Set after how many days trashed items are purged on startup, `None` disables it.
+    #[tauri::command]
+    pub async fn set_trash_retention_days(days: Option<i64>) -> Result<(), String> {
+        if days.is_some_and(|d| d < 0) {
+            return Err("Invalid number of days".to_string());
+        }
+
+        let value = days.map(|d| d.to_string());
+        queries::set_setting(db::TRASH_RETENTION_DAYS_KEY, value.as_deref(), get_pool())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub async fn update_author(author_id: &str, author_name: &str) -> Result<Author, String> {
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+        let result = queries::update_author_name(author_id, author_name, &mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(result)
+    }
+
+    #[tauri::command]
+    pub async fn delete_author(author_id: String) -> Result<(), String> {
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+        // Books are hidden with the author by the author_after_soft_delete trigger
         queries::delete_author(author_id, &mut *tx)
             .await
             .map_err(|e| e.to_string())?;
@@ -380,6 +857,7 @@ pub mod commands {
             created_at: now.clone(),
             updated_at: now,
             deleted_at: None,
+            import_batch_id: None,
         };
         queries::insert_note(&new_note, get_pool())
             .await
@@ -410,6 +888,39 @@ pub mod commands {
             .map_err(|e| e.to_string())
     }
 
+    #[tauri::command]
+    pub async fn get_starred_quotes_page(
+        sort_by: Option<&str>,
+        sort_order: Option<&str>,
+        cursor: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<Page<QuoteWithTagsRedux>, String> {
+        let mut conn = get_pool().acquire().await.map_err(|e| e.to_string())?;
+        queries::get_starred_quotes_page(
+            sort_by,
+            sort_order,
+            cursor,
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+            &mut conn,
+        )
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    /// Get the quotes matching any combination of filters, with paging.
+    #[tauri::command]
+    pub async fn filter_quotes(
+        filter: QuoteFilter,
+        sort_by: Option<&str>,
+        sort_order: Option<&str>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<QuoteWithTagsRedux>, String> {
+        queries::query_quotes(&filter, sort_by, sort_order, limit, offset, get_pool())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
     #[tauri::command]
     pub async fn get_tags() -> Result<Vec<Tag>, String> {
         queries::get_tags(get_pool())
@@ -428,6 +939,27 @@ pub mod commands {
             .map_err(|e| e.to_string())
     }
 
+    #[tauri::command]
+    pub async fn get_quotes_by_tag_page(
+        tag_id: &str,
+        sort_by: Option<&str>,
+        sort_order: Option<&str>,
+        cursor: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<Page<QuoteWithTagsRedux>, String> {
+        let mut conn = get_pool().acquire().await.map_err(|e| e.to_string())?;
+        queries::get_quotes_by_tag_page(
+            tag_id,
+            sort_by,
+            sort_order,
+            cursor,
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+            &mut conn,
+        )
+        .await
+        .map_err(|e| e.to_string())
+    }
+
     static HEX_COLOR_REGEX: Lazy<Regex> =
         Lazy::new(|| regex::Regex::new(r"^#(?:[0-9a-fA-F]{6}|[0-9a-fA-F]{8})$").unwrap());
 
@@ -441,6 +973,7 @@ pub mod commands {
             id: Uuid::new_v4().to_string(),
             name: name.to_string(),
             color: Some(color.to_string()),
+            import_batch_id: None,
         };
         queries::insert_tag(&tag, get_pool())
             .await
@@ -481,6 +1014,95 @@ pub mod commands {
             .await
             .map_err(|e| e.to_string())
     }
+
+    //
+    // Smart collections
+    //
+
+    #[tauri::command]
+    pub async fn get_smart_collections() -> Result<Vec<SmartCollection>, String> {
+        queries::get_smart_collections(get_pool())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub async fn create_smart_collection(
+        name: &str,
+        filter: QuoteFilter,
+        sort_by: Option<&str>,
+        sort_order: Option<&str>,
+    ) -> Result<SmartCollection, String> {
+        if name.trim().is_empty() {
+            return Err("Smart collection name cannot be empty".to_string());
+        }
+
+        queries::insert_smart_collection(name.trim(), &filter, sort_by, sort_order, get_pool())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub async fn update_smart_collection(
+        collection_id: &str,
+        name: &str,
+        filter: QuoteFilter,
+        sort_by: Option<&str>,
+        sort_order: Option<&str>,
+    ) -> Result<SmartCollection, String> {
+        if name.trim().is_empty() {
+            return Err("Smart collection name cannot be empty".to_string());
+        }
+
+        let pool = get_pool();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+        let collection = queries::update_smart_collection(
+            collection_id,
+            name.trim(),
+            &filter,
+            sort_by,
+            sort_order,
+            &mut *tx,
+        )
+        .await
+        .map_err(|e| format!("Error updating smart collection {}: {}", collection_id, e))?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(collection)
+    }
+
+    #[tauri::command]
+    pub async fn delete_smart_collection(collection_id: &str) -> Result<(), String> {
+        queries::delete_smart_collection(collection_id, get_pool())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Run the smart collection filter, sorting with the collection options
+    /// unless overridden.
+    #[tauri::command]
+    pub async fn evaluate_smart_collection(
+        collection_id: &str,
+        sort_by: Option<&str>,
+        sort_order: Option<&str>,
+    ) -> Result<Vec<QuoteWithTagsRedux>, String> {
+        let pool = get_pool();
+        let collection = queries::get_smart_collection(collection_id, pool)
+            .await
+            .map_err(|e| format!("Error fetching smart collection {}: {}", collection_id, e))?;
+
+        queries::query_quotes(
+            &collection.filter,
+            sort_by.or(collection.sort_by.as_deref()),
+            sort_order.or(collection.sort_order.as_deref()),
+            None,
+            None,
+            pool,
+        )
+        .await
+        .map_err(|e| e.to_string())
+    }
 }
 
 // Re-export the commands for convenience
@@ -0,0 +1,454 @@
+// A parser for the Lua table literals KOReader writes in its `metadata.*.lua` files.
+//
+// Only data is understood: `return`, tables, strings, numbers, booleans and `nil`.
+// Nothing is ever executed, any other construct is a parse error.
+
+use std::fmt;
+
+/// Deepest nesting of tables accepted, deeper ones would overflow the stack of the parser.
+const MAX_DEPTH: usize = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LuaValue {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Table(Vec<(LuaValue, LuaValue)>),
+}
+
+impl LuaValue {
+    /// Value of the string key `key` of a table.
+    pub fn get(&self, key: &str) -> Option<&LuaValue> {
+        match self {
+            LuaValue::Table(entries) => entries
+                .iter()
+                .find(|(k, _)| matches!(k, LuaValue::String(s) if s == key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Entries of the table in key order, numeric keys first.
+    pub fn entries(&self) -> Vec<(&LuaValue, &LuaValue)> {
+        match self {
+            LuaValue::Table(entries) => {
+                let mut entries: Vec<(&LuaValue, &LuaValue)> =
+                    entries.iter().map(|(k, v)| (k, v)).collect();
+                entries.sort_by(|(a, _), (b, _)| match (a, b) {
+                    (LuaValue::Number(a), LuaValue::Number(b)) => a.total_cmp(b),
+                    (LuaValue::Number(_), _) => std::cmp::Ordering::Less,
+                    (_, LuaValue::Number(_)) => std::cmp::Ordering::Greater,
+                    _ => std::cmp::Ordering::Equal,
+                });
+                entries
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Values of the table in key order, numeric keys first.
+    pub fn values(&self) -> Vec<&LuaValue> {
+        self.entries().into_iter().map(|(_, v)| v).collect()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            LuaValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            LuaValue::Number(n) => Some(*n),
+            LuaValue::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            LuaValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// The value as text, numbers included.
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            LuaValue::String(s) => Some(s.clone()),
+            LuaValue::Number(n) if n.fract() == 0.0 => Some(format!("{}", *n as i64)),
+            LuaValue::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuaError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LuaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for LuaError {}
+
+/// Parse a Lua chunk made of an optional `return` followed by a single value.
+pub fn parse(source: &str) -> Result<LuaValue, LuaError> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+        line: 1,
+        depth: 0,
+    };
+
+    parser.skip_trivia()?;
+    if parser.peek_word() == Some("return") {
+        parser.pos += "return".len();
+        parser.skip_trivia()?;
+    }
+    let value = parser.value()?;
+    parser.skip_trivia()?;
+    if parser.peek() == Some(';') {
+        parser.bump();
+        parser.skip_trivia()?;
+    }
+    if parser.peek().is_some() {
+        return Err(parser.error("unexpected content after value"));
+    }
+
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    /// Number of tables being parsed
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> LuaError {
+        LuaError {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), LuaError> {
+        match self.bump() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected '{}'", expected))),
+        }
+    }
+
+    /// The identifier starting at the current position, if any.
+    fn peek_word(&self) -> Option<&'static str> {
+        ["return", "true", "false", "nil"].into_iter().find(|word| {
+            let end = self.pos + word.len();
+            end <= self.chars.len()
+                && self.chars[self.pos..end].iter().copied().eq(word.chars())
+                && !self
+                    .chars
+                    .get(end)
+                    .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+        })
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_trivia(&mut self) -> Result<(), LuaError> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('-') if self.peek_at(1) == Some('-') => {
+                    self.pos += 2;
+                    if let Some(level) = self.long_bracket_level() {
+                        self.long_string(level)?;
+                    } else {
+                        while self.peek().is_some_and(|c| c != '\n') {
+                            self.bump();
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<LuaValue, LuaError> {
+        match self.peek() {
+            Some('{') => self.table(),
+            Some('"') | Some('\'') => self.quoted_string().map(LuaValue::String),
+            Some('[') => match self.long_bracket_level() {
+                Some(level) => self.long_string(level).map(LuaValue::String),
+                None => Err(self.error("unexpected '['")),
+            },
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '.' => self.number(),
+            Some(_) => match self.peek_word() {
+                Some("true") => {
+                    self.pos += 4;
+                    Ok(LuaValue::Bool(true))
+                }
+                Some("false") => {
+                    self.pos += 5;
+                    Ok(LuaValue::Bool(false))
+                }
+                Some("nil") => {
+                    self.pos += 3;
+                    Ok(LuaValue::Nil)
+                }
+                _ => Err(self.error("unsupported expression")),
+            },
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn table(&mut self) -> Result<LuaValue, LuaError> {
+        self.expect('{')?;
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("tables nested too deeply"));
+        }
+        let mut entries = Vec::new();
+        let mut next_index = 1.0;
+
+        loop {
+            self.skip_trivia()?;
+            if self.peek() == Some('}') {
+                self.bump();
+                self.depth -= 1;
+                return Ok(LuaValue::Table(entries));
+            }
+
+            let key = if self.peek() == Some('[') && self.long_bracket_level().is_none() {
+                // [key] = value
+                self.bump();
+                self.skip_trivia()?;
+                let key = self.value()?;
+                self.skip_trivia()?;
+                self.expect(']')?;
+                self.skip_trivia()?;
+                self.expect('=')?;
+                Some(key)
+            } else if self.peek().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && self.peek_word().is_none()
+            {
+                // name = value
+                let mut name = String::new();
+                while let Some(c) = self.peek().filter(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                    self.bump();
+                }
+                self.skip_trivia()?;
+                self.expect('=')?;
+                Some(LuaValue::String(name))
+            } else {
+                None
+            };
+
+            self.skip_trivia()?;
+            let value = self.value()?;
+            let key = key.unwrap_or_else(|| {
+                let key = LuaValue::Number(next_index);
+                next_index += 1.0;
+                key
+            });
+            if value != LuaValue::Nil {
+                entries.push((key, value));
+            }
+
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(',') | Some(';') => {
+                    self.bump();
+                }
+                Some('}') => {}
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<LuaValue, LuaError> {
+        let mut text = String::new();
+        if self.peek() == Some('-') {
+            text.push('-');
+            self.bump();
+        }
+
+        if self.peek() == Some('0') && matches!(self.peek_at(1), Some('x') | Some('X')) {
+            self.pos += 2;
+            let mut hex = String::new();
+            while let Some(c) = self.peek().filter(|c| c.is_ascii_hexdigit()) {
+                hex.push(c);
+                self.bump();
+            }
+            let n = i64::from_str_radix(&hex, 16).map_err(|_| self.error("invalid number"))?;
+            return Ok(LuaValue::Number(if text.is_empty() { n } else { -n } as f64));
+        }
+
+        while let Some(c) = self.peek() {
+            let exponent_sign = matches!(c, '+' | '-') && text.ends_with(['e', 'E']);
+            if c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E') || exponent_sign {
+                text.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
+        text.parse::<f64>()
+            .map(LuaValue::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn quoted_string(&mut self) -> Result<String, LuaError> {
+        let Some(quote) = self.bump() else {
+            return Err(self.error("unexpected end of input"));
+        };
+        let mut s = String::new();
+        // Escapes produce bytes, collected separately so \ddd sequences can form UTF-8
+        let mut bytes = Vec::new();
+
+        loop {
+            let c = self.bump().ok_or_else(|| self.error("unfinished string"))?;
+            if c == quote {
+                break;
+            }
+            if c == '\n' {
+                return Err(self.error("unfinished string"));
+            }
+            if c != '\\' {
+                flush_bytes(&mut bytes, &mut s);
+                s.push(c);
+                continue;
+            }
+
+            let escaped = self.bump().ok_or_else(|| self.error("unfinished string"))?;
+            match escaped {
+                'n' => bytes.push(b'\n'),
+                't' => bytes.push(b'\t'),
+                'r' => bytes.push(b'\r'),
+                'a' => bytes.push(0x07),
+                'b' => bytes.push(0x08),
+                'f' => bytes.push(0x0c),
+                'v' => bytes.push(0x0b),
+                '\\' => bytes.push(b'\\'),
+                '"' => bytes.push(b'"'),
+                '\'' => bytes.push(b'\''),
+                '\n' => bytes.push(b'\n'),
+                'x' => {
+                    let hex: String = (0..2).filter_map(|_| self.bump()).collect();
+                    let byte =
+                        u8::from_str_radix(&hex, 16).map_err(|_| self.error("invalid escape"))?;
+                    bytes.push(byte);
+                }
+                'z' => {
+                    while self.peek().is_some_and(char::is_whitespace) {
+                        self.bump();
+                    }
+                }
+                'u' => {
+                    self.expect('{')?;
+                    let mut hex = String::new();
+                    while let Some(c) = self.peek().filter(|c| *c != '}') {
+                        hex.push(c);
+                        self.bump();
+                    }
+                    self.expect('}')?;
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| self.error("invalid escape"))?;
+                    let mut buf = [0u8; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                c if c.is_ascii_digit() => {
+                    let mut digits = c.to_string();
+                    while digits.len() < 3 && self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                        digits.extend(self.bump());
+                    }
+                    let byte = digits
+                        .parse::<u8>()
+                        .map_err(|_| self.error("invalid escape"))?;
+                    bytes.push(byte);
+                }
+                _ => return Err(self.error("invalid escape")),
+            }
+        }
+
+        flush_bytes(&mut bytes, &mut s);
+        Ok(s)
+    }
+
+    /// Level of the long bracket `[==[` starting at the current position.
+    fn long_bracket_level(&self) -> Option<usize> {
+        if self.peek() != Some('[') {
+            return None;
+        }
+        let mut level = 0;
+        while self.peek_at(1 + level) == Some('=') {
+            level += 1;
+        }
+        (self.peek_at(1 + level) == Some('[')).then_some(level)
+    }
+
+    fn long_string(&mut self, level: usize) -> Result<String, LuaError> {
+        for _ in 0..level + 2 {
+            self.bump();
+        }
+        // A newline right after the opening bracket is skipped
+        if self.peek() == Some('\n') {
+            self.bump();
+        }
+
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some(']')
+                    if (0..level).all(|i| self.peek_at(i) == Some('='))
+                        && self.peek_at(level) == Some(']') =>
+                {
+                    for _ in 0..level + 1 {
+                        self.bump();
+                    }
+                    return Ok(s);
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error("unfinished long string")),
+            }
+        }
+    }
+}
+
+fn flush_bytes(bytes: &mut Vec<u8>, s: &mut String) {
+    if !bytes.is_empty() {
+        s.push_str(&String::from_utf8_lossy(bytes));
+        bytes.clear();
+    }
+}
@@ -3,7 +3,7 @@
 
 use liture_notes_lib::menu;
 use std::env;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 #[cfg(target_os = "macos")]
 use tauri::{Runtime, WindowEvent};
@@ -136,6 +136,15 @@ async fn main() {
                     }
                     Err(e) => {
                         log::error!("Failed to initialize database: {}", e);
+                        liture_notes_lib::db::set_init_error(e);
+
+                        // Show the window anyway so the error can be displayed
+                        if let Some(window) = app_handle.get_window("main") {
+                            let _ = window.show();
+                        }
+                        if let Some(error) = liture_notes_lib::db::get_init_error() {
+                            let _ = app_handle.emit("db-error", error);
+                        }
                     }
                 }
             });
@@ -169,14 +178,34 @@ async fn main() {
             _ => {}
         })
         .invoke_handler(tauri::generate_handler![
+            // Database
+            liture_notes_lib::commands::get_db_init_error,
             // Import
+            liture_notes_lib::commands::import_from_path,
             liture_notes_lib::commands::import_from_ibooks,
             liture_notes_lib::commands::import_from_kobo,
             liture_notes_lib::commands::import_from_kindle,
+            liture_notes_lib::commands::import_from_readwise,
+            liture_notes_lib::commands::import_from_koreader,
+            liture_notes_lib::commands::import_from_calibre,
+            liture_notes_lib::commands::preview_import,
+            liture_notes_lib::commands::import_selection,
+            liture_notes_lib::commands::cancel_import,
+            liture_notes_lib::commands::get_import_batches,
+            liture_notes_lib::commands::get_import_batch,
+            liture_notes_lib::commands::rollback_import_batch,
+            // Export
+            liture_notes_lib::commands::export_markdown,
+            liture_notes_lib::commands::export_anki,
+            liture_notes_lib::commands::export_readwise,
+            // Backup
+            liture_notes_lib::commands::backup_library,
+            liture_notes_lib::commands::restore_library,
             // Fetch
             // Tags
             liture_notes_lib::commands::get_tags,
             liture_notes_lib::commands::get_quotes_by_tag,
+            liture_notes_lib::commands::get_quotes_by_tag_page,
             liture_notes_lib::commands::create_tag,
             liture_notes_lib::commands::delete_tag,
             liture_notes_lib::commands::add_quote_tag,
@@ -187,15 +216,29 @@ async fn main() {
             liture_notes_lib::commands::create_author,
             liture_notes_lib::commands::update_author,
             liture_notes_lib::commands::delete_author,
+            // Trash
+            liture_notes_lib::commands::get_trash,
+            liture_notes_lib::commands::restore_quote,
+            liture_notes_lib::commands::restore_book,
+            liture_notes_lib::commands::restore_author,
+            liture_notes_lib::commands::purge_quote,
+            liture_notes_lib::commands::purge_book,
+            liture_notes_lib::commands::purge_author,
+            liture_notes_lib::commands::empty_trash,
+            liture_notes_lib::commands::get_trash_retention_days,
+            liture_notes_lib::commands::set_trash_retention_days,
             // Books
             liture_notes_lib::commands::get_books_by_author,
             liture_notes_lib::commands::get_books_with_authors,
+            liture_notes_lib::commands::get_books_page,
+            liture_notes_lib::commands::get_authors_page,
             liture_notes_lib::commands::create_book,
             liture_notes_lib::commands::update_book,
             liture_notes_lib::commands::delete_book,
             liture_notes_lib::commands::create_book_with_author,
             // Quotes
             liture_notes_lib::commands::get_book_quotes,
+            liture_notes_lib::commands::get_book_quotes_page,
             liture_notes_lib::commands::create_quote,
             liture_notes_lib::commands::update_quote,
             liture_notes_lib::commands::delete_quote,
@@ -205,10 +248,16 @@ async fn main() {
             liture_notes_lib::commands::toggle_quote_starred,
             liture_notes_lib::commands::set_quote_starred,
             liture_notes_lib::commands::get_starred_quotes,
+            liture_notes_lib::commands::get_starred_quotes_page,
+            // Filter
+            liture_notes_lib::commands::filter_quotes,
             // Random
             liture_notes_lib::commands::get_random_quote,
             // Notes
             liture_notes_lib::commands::get_book_notes,
+            liture_notes_lib::commands::get_book_bookmarks,
+            liture_notes_lib::commands::delete_bookmark,
+            liture_notes_lib::commands::get_quote_colors,
             liture_notes_lib::commands::create_note,
             liture_notes_lib::commands::update_note,
             // Search
@@ -217,6 +266,17 @@ async fn main() {
             liture_notes_lib::commands::search_authors_by_name,
             liture_notes_lib::commands::search_quotes_by_book_title,
             liture_notes_lib::commands::search_quotes_by_author_name,
+            // Search history
+            liture_notes_lib::commands::get_recent_searches,
+            liture_notes_lib::commands::get_frequent_searches,
+            liture_notes_lib::commands::delete_search,
+            liture_notes_lib::commands::clear_search_history,
+            // Smart collections
+            liture_notes_lib::commands::get_smart_collections,
+            liture_notes_lib::commands::create_smart_collection,
+            liture_notes_lib::commands::update_smart_collection,
+            liture_notes_lib::commands::delete_smart_collection,
+            liture_notes_lib::commands::evaluate_smart_collection,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,4 +1,5 @@
-use crate::import;
+use crate::export;
+use crate::import::{self, ImportHandle, ImportSource};
 use std::str::FromStr;
 use tauri::{menu::Menu, AppHandle, Wry};
 
@@ -7,6 +8,11 @@ pub enum MenuEvent {
     ImportFromKobo,
     ImportFromKindle,
     ImportFromiBooks,
+    ImportFromReadwise,
+    ImportFromKOReader,
+    ImportFromCalibre,
+    ExportToMarkdown,
+    ExportToReadwise,
 }
 
 impl ToString for MenuEvent {
@@ -15,6 +21,11 @@ impl ToString for MenuEvent {
             MenuEvent::ImportFromKobo => "import_from_kobo".to_string(),
             MenuEvent::ImportFromKindle => "import_from_kindle".to_string(),
             MenuEvent::ImportFromiBooks => "import_from_ibooks".to_string(),
+            MenuEvent::ImportFromReadwise => "import_from_readwise".to_string(),
+            MenuEvent::ImportFromKOReader => "import_from_koreader".to_string(),
+            MenuEvent::ImportFromCalibre => "import_from_calibre".to_string(),
+            MenuEvent::ExportToMarkdown => "export_to_markdown".to_string(),
+            MenuEvent::ExportToReadwise => "export_to_readwise".to_string(),
         }
     }
 }
@@ -28,6 +39,11 @@ impl FromStr for MenuEvent {
             "import_from_kobo" => Ok(MenuEvent::ImportFromKobo),
             "import_from_kindle" => Ok(MenuEvent::ImportFromKindle),
             "import_from_ibooks" => Ok(MenuEvent::ImportFromiBooks),
+            "import_from_readwise" => Ok(MenuEvent::ImportFromReadwise),
+            "import_from_koreader" => Ok(MenuEvent::ImportFromKOReader),
+            "import_from_calibre" => Ok(MenuEvent::ImportFromCalibre),
+            "export_to_markdown" => Ok(MenuEvent::ExportToMarkdown),
+            "export_to_readwise" => Ok(MenuEvent::ExportToReadwise),
             _ => Err(ParseError::InvalidMenuEvent),
         }
     }
@@ -60,7 +76,22 @@ fn setup_file_submenu(app: &mut tauri::App) -> tauri::Result<tauri::menu::Submen
                 .build(app)?,
         )
         .item(
-            &MenuItemBuilder::with_id(MenuEvent::ImportFromKindle, "From Kindle Clippings file")
+            &MenuItemBuilder::with_id(
+                MenuEvent::ImportFromKindle,
+                "From Kindle Clippings or Notebook file",
+            )
+            .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::with_id(MenuEvent::ImportFromReadwise, "From Readwise CSV file")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::with_id(MenuEvent::ImportFromKOReader, "From KOReader folder")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::with_id(MenuEvent::ImportFromCalibre, "From Calibre library")
                 .build(app)?,
         );
 
@@ -72,9 +103,16 @@ fn setup_file_submenu(app: &mut tauri::App) -> tauri::Result<tauri::menu::Submen
         );
     }
 
+    let export_submenu = SubmenuBuilder::new(app, "Export")
+        .item(&MenuItemBuilder::with_id(MenuEvent::ExportToMarkdown, "To Markdown").build(app)?)
+        .item(
+            &MenuItemBuilder::with_id(MenuEvent::ExportToReadwise, "To Readwise CSV").build(app)?,
+        );
+
     // Build the File submenu with the conditionally populated Import submenu
     SubmenuBuilder::new(app, "File")
         .item(&import_submenu.build()?)
+        .item(&export_submenu.build()?)
         .build()
 }
 
@@ -180,16 +218,36 @@ pub fn setup_menu(app: &mut tauri::App) -> tauri::Result<Menu<Wry>> {
     }
 }
 
+/// Import from `source`, the user picks the path.
+async fn import_from(app: &AppHandle, source: ImportSource) {
+    import::import_from_source(app, ImportHandle::register(), source, None, None).await;
+}
+
 async fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
     match event {
         MenuEvent::ImportFromKobo => {
-            import::import_from_kobo(app).await;
+            import_from(app, ImportSource::Kobo).await;
         }
         MenuEvent::ImportFromKindle => {
-            import::import_from_kindle(app).await;
+            import_from(app, ImportSource::Kindle).await;
         }
         MenuEvent::ImportFromiBooks => {
-            import::import_from_ibooks(app).await;
+            import_from(app, ImportSource::IBooks).await;
+        }
+        MenuEvent::ImportFromReadwise => {
+            import_from(app, ImportSource::Readwise).await;
+        }
+        MenuEvent::ImportFromKOReader => {
+            import_from(app, ImportSource::KOReader).await;
+        }
+        MenuEvent::ImportFromCalibre => {
+            import_from(app, ImportSource::Calibre).await;
+        }
+        MenuEvent::ExportToMarkdown => {
+            export::export_library_to_markdown(app).await;
+        }
+        MenuEvent::ExportToReadwise => {
+            export::export_library_to_readwise(app).await;
         }
     }
 }
@@ -9,6 +9,10 @@ pub struct Author {
     pub updated_at: NaiveDateTime,
     pub deleted_at: Option<NaiveDateTime>,
     pub original_id: Option<String>,
+    /// The import that created the row, see [`ImportBatch`]
+    #[sqlx(default)]
+    #[serde(default)]
+    pub import_batch_id: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
@@ -21,6 +25,10 @@ pub struct Book {
     pub updated_at: NaiveDateTime,
     pub deleted_at: Option<NaiveDateTime>,
     pub original_id: Option<String>,
+    /// The import that created the row, see [`ImportBatch`]
+    #[sqlx(default)]
+    #[serde(default)]
+    pub import_batch_id: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
@@ -37,6 +45,18 @@ pub struct Quote {
     pub imported_at: Option<NaiveDateTime>,
     pub deleted_at: Option<NaiveDateTime>,
     pub original_id: Option<String>,
+    /// Highlight color, a name such as `yellow` or a hex code
+    pub color: Option<String>,
+    /// Page range in the printed edition, from Kindle clippings
+    pub page_start: Option<i64>,
+    pub page_end: Option<i64>,
+    /// Kindle location range
+    pub location_start: Option<i64>,
+    pub location_end: Option<i64>,
+    /// The import that created the row, see [`ImportBatch`]
+    #[sqlx(default)]
+    #[serde(default)]
+    pub import_batch_id: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
@@ -49,6 +69,10 @@ pub struct Chapter {
     pub deleted_at: Option<NaiveDateTime>,
     pub original_id: Option<String>,
     pub volume_index: i64,
+    /// The import that created the row, see [`ImportBatch`]
+    #[sqlx(default)]
+    #[serde(default)]
+    pub import_batch_id: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
@@ -61,6 +85,28 @@ pub struct Note {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub deleted_at: Option<NaiveDateTime>,
+    /// The import that created the row, see [`ImportBatch`]
+    #[sqlx(default)]
+    #[serde(default)]
+    pub import_batch_id: Option<String>,
+}
+
+/// A position saved in a book, without any highlighted text.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
+pub struct Bookmark {
+    pub id: String,
+    pub book_id: Option<String>,
+    pub chapter_id: Option<String>,
+    pub chapter_progress: Option<f64>,
+    /// `bookmark`, or `dogear` for Kobo dog-ears
+    pub kind: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub original_id: Option<String>,
+    /// The import that created the row, see [`ImportBatch`]
+    #[sqlx(default)]
+    #[serde(default)]
+    pub import_batch_id: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
@@ -68,6 +114,10 @@ pub struct Tag {
     pub id: String,
     pub name: String,
     pub color: Option<String>,
+    /// The import that created the row, see [`ImportBatch`]
+    #[sqlx(default)]
+    #[serde(default)]
+    pub import_batch_id: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
@@ -76,6 +126,135 @@ pub struct QuoteTag {
     pub tag_id: String,
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
+pub struct SearchHistory {
+    pub id: i64,
+    pub query: String,
+    /// When the query was last searched
+    pub timestamp: NaiveDateTime,
+    /// How many times the query was searched
+    pub frequency: i64,
+}
+
+/// A key/value application setting, e.g. how long the trash is kept
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
+pub struct Setting {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct SmartCollection {
+    pub id: String,
+    pub name: String,
+    pub filter: QuoteFilter,
+    pub sort_by: Option<String>,
+    pub sort_order: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Number of records of one kind created, updated and skipped as duplicates by an import.
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ImportCounts {
+    pub created: u64,
+    pub updated: u64,
+    pub skipped: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportIssueLevel {
+    /// The record was imported, possibly with missing data
+    Warning,
+    /// The record was skipped
+    Error,
+    /// The record was skipped on purpose, e.g. superseded by a newer version
+    Info,
+}
+
+/// A problem with a single record of an import.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ImportIssue {
+    pub level: ImportIssueLevel,
+    /// Where the record is in the source, e.g. `line 42` or `bookmark <id>`
+    pub location: String,
+    pub message: String,
+}
+
+/// What an import did, per kind of record.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ImportReport {
+    pub books: ImportCounts,
+    pub authors: ImportCounts,
+    pub chapters: ImportCounts,
+    pub quotes: ImportCounts,
+    pub notes: ImportCounts,
+    #[serde(default)]
+    pub bookmarks: ImportCounts,
+    pub issues: Vec<ImportIssue>,
+}
+
+impl ImportReport {
+    pub fn warning(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ImportIssue {
+            level: ImportIssueLevel::Warning,
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn info(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ImportIssue {
+            level: ImportIssueLevel::Info,
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn error(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ImportIssue {
+            level: ImportIssueLevel::Error,
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Number of records skipped because of an error.
+    pub fn errors(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|i| i.level == ImportIssueLevel::Error)
+            .count()
+    }
+}
+
+impl std::fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Imported successfully {} new books and {} new quotes",
+            self.books.created, self.quotes.created
+        )?;
+        match self.errors() {
+            0 => Ok(()),
+            1 => write!(f, ", 1 invalid record was skipped"),
+            n => write!(f, ", {} invalid records were skipped", n),
+        }
+    }
+}
+
+/// A past import, kept for review and rollback.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct ImportBatch {
+    pub id: String,
+    pub source: String,
+    pub path: String,
+    pub file_hash: Option<String>,
+    pub report: ImportReport,
+    pub created_at: NaiveDateTime,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
 pub struct QuoteFts {
     pub id: String,
@@ -92,6 +271,12 @@ pub struct QuoteFts {
     pub author_id: Option<String>,
     pub book_title: Option<String>,
     pub author_name: Option<String>,
+    /// `bm25()` score, lower is a better match
+    pub rank: f64,
+    /// Excerpt of the content around the matched terms, as HTML like `highlight`
+    pub snippet: Option<String>,
+    /// Full content as HTML, escaped, with the matched terms wrapped in `<mark>` tags
+    pub highlight: Option<String>,
     pub tags: Vec<Tag>,
 }
 
@@ -103,6 +288,90 @@ pub struct Library {
     pub authors: Vec<Author>,
 }
 
+/// A page of the results of a paginated query
+#[derive(Debug, serde::Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor to get the next page, `None` on the last page
+    pub next_cursor: Option<String>,
+    /// Number of results across all the pages
+    pub total: i64,
+}
+
+impl<T> Page<T> {
+    pub fn empty() -> Self {
+        Page {
+            items: Vec::new(),
+            next_cursor: None,
+            total: 0,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Trash {
+    pub quotes: Vec<TrashedQuote>,
+    pub books: Vec<Book>,
+    pub authors: Vec<Author>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
+pub struct TrashedQuote {
+    pub id: String,
+    pub book_id: Option<String>,
+    pub book_title: Option<String>,
+    pub author_id: Option<String>,
+    pub author_name: Option<String>,
+    pub content: Option<String>,
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+/// Composable filter on quotes, every field set narrows down the quotes matched.
+/// Also stored as JSON by smart collections.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(default)]
+pub struct QuoteFilter {
+    /// Full text search on the quote content, same syntax as the search bar
+    pub text: Option<String>,
+    pub starred: Option<bool>,
+    pub tag_ids: Vec<String>,
+    /// Whether quotes need any or all of `tag_ids`
+    pub tag_match: TagMatch,
+    pub book_id: Option<String>,
+    pub author_id: Option<String>,
+    pub chapter_id: Option<String>,
+    /// Highlight colors, quotes of any of them match
+    pub colors: Vec<String>,
+    /// Books added on or after this date
+    pub book_created_from: Option<NaiveDateTime>,
+    /// Books added before this date
+    pub book_created_to: Option<NaiveDateTime>,
+    pub created_from: Option<NaiveDateTime>,
+    pub created_to: Option<NaiveDateTime>,
+    pub imported_from: Option<NaiveDateTime>,
+    pub imported_to: Option<NaiveDateTime>,
+    pub deleted: DeletedState,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletedState {
+    /// Quotes not deleted, in books and authors not deleted
+    #[default]
+    Active,
+    /// Quotes in the trash, deleted on their own or with their book
+    Deleted,
+    All,
+}
+
 #[derive(Debug, serde::Serialize, Clone, sqlx::FromRow)]
 pub struct StarredQuote {
     pub id: String,
@@ -139,9 +408,26 @@ pub struct QuoteWithTags {
     pub imported_at: Option<NaiveDateTime>,
     pub deleted_at: Option<NaiveDateTime>,
     pub original_id: Option<String>,
+    pub color: Option<String>,
+    pub page_start: Option<i64>,
+    pub page_end: Option<i64>,
+    pub location_start: Option<i64>,
+    pub location_end: Option<i64>,
     pub tags: Vec<Tag>,
 }
 
+impl QuoteWithTags {
+    /// Position of the quote for reading order: its Kindle location or page when known,
+    /// else its progress in the chapter. Quotes without a position come last.
+    pub fn position(&self) -> f64 {
+        self.location_start
+            .or(self.page_start)
+            .map(|p| p as f64)
+            .or(self.chapter_progress)
+            .unwrap_or(f64::MAX)
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, sqlx::FromRow)]
 pub struct QuoteWithTagsRedux {
     pub id: String,
@@ -151,6 +437,7 @@ pub struct QuoteWithTagsRedux {
     pub author_name: Option<String>,
     pub content: Option<String>,
     pub starred: Option<i64>,
+    pub color: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub tags: Vec<Tag>,
@@ -1,5 +1,6 @@
 use crate::models::*;
-use sqlx::{Executor, Row, Sqlite};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Executor, FromRow, QueryBuilder, Row, Sqlite, SqliteConnection};
 use uuid::Uuid;
 
 /// Format the order and sort by clauses for db queries with sorting.
@@ -23,6 +24,139 @@ fn extract_order_clauses(sort_by: Option<&str>, order: Option<&str>) -> (String,
     (order_clause.to_string(), sort_by_clause.to_string())
 }
 
+/// Reading position of a quote: its Kindle location or page when known, else its chapter progress.
+const QUOTE_POSITION: &str = "COALESCE(q.location_start, q.page_start, q.chapter_progress)";
+
+/// Column of the quotes `q` to sort by, from [`extract_order_clauses`]
+fn quote_order_column(column: &str) -> String {
+    match column {
+        "chapter_progress" => QUOTE_POSITION.to_string(),
+        column => format!("q.{}", column),
+    }
+}
+
+/// Position after the last row of a page, handed to the UI as an opaque string.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Cursor {
+    key: CursorKey,
+    id: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum CursorKey {
+    Number(f64),
+    Text(String),
+}
+
+fn decode_cursor(cursor: Option<&str>) -> Result<Option<Cursor>, sqlx::Error> {
+    cursor
+        .map(|c| {
+            serde_json::from_str(c)
+                .map_err(|_| sqlx::Error::Protocol(format!("Invalid page cursor {}", c)))
+        })
+        .transpose()
+}
+
+/// Sort key of a paginated query. Rows are sorted by the key then by id,
+/// so the key of the last row and its id are enough to find the next page.
+struct SortKey {
+    /// SQL expression of the key, never NULL so it can be compared
+    expr: String,
+    /// Numeric keys are compared as numbers, the others as text
+    numeric: bool,
+    id: &'static str,
+    order: String,
+}
+
+impl SortKey {
+    /// Sort key of the quotes, from the usual sort options
+    fn quote(sort_by: Option<&str>, sort_order: Option<&str>) -> Self {
+        let (order, column) = extract_order_clauses(sort_by, sort_order);
+        let (expr, numeric) = match column.as_str() {
+            "chapter_progress" => (format!("CAST(COALESCE({}, -1) AS REAL)", QUOTE_POSITION), true),
+            "starred" => ("CAST(COALESCE(q.starred, 0) AS REAL)".to_string(), true),
+            column => (format!("COALESCE(q.{}, '')", column), false),
+        };
+
+        SortKey { expr, numeric, id: "q.id", order }
+    }
+
+    /// Sort key on a text column, ignoring case
+    fn text(column: &str, id: &'static str) -> Self {
+        SortKey {
+            expr: format!("{} COLLATE NOCASE", column),
+            numeric: false,
+            id,
+            order: "ASC".to_string(),
+        }
+    }
+
+    /// Restrict a query already in its WHERE clause to the rows after the cursor
+    fn push_after(&self, query: &mut QueryBuilder<'_, Sqlite>, cursor: Option<Cursor>) {
+        let Some(cursor) = cursor else {
+            return;
+        };
+        let op = if self.order == "DESC" { "<" } else { ">" };
+
+        query.push(format!(" AND ({} {} ", self.expr, op));
+        Self::push_key(query, &cursor.key);
+        query.push(format!(" OR ({} = ", self.expr));
+        Self::push_key(query, &cursor.key);
+        query
+            .push(format!(" AND {} {} ", self.id, op))
+            .push_bind(cursor.id)
+            .push("))");
+    }
+
+    fn push_key(query: &mut QueryBuilder<'_, Sqlite>, key: &CursorKey) {
+        match key {
+            CursorKey::Number(n) => query.push_bind(*n),
+            CursorKey::Text(s) => query.push_bind(s.clone()),
+        };
+    }
+
+    /// Sort the query and fetch one more row than needed, to know if there's a next page
+    fn push_order(&self, query: &mut QueryBuilder<'_, Sqlite>, limit: i64) {
+        query
+            .push(format!(
+                " ORDER BY {} {}, {} {} LIMIT ",
+                self.expr, self.order, self.id, self.order
+            ))
+            .push_bind(limit.max(1) + 1);
+    }
+
+    /// Build the page from rows fetched with `push_order`, they need `id` and `sort_key` columns
+    fn to_page<T>(
+        &self,
+        mut rows: Vec<SqliteRow>,
+        limit: i64,
+        total: i64,
+        map: impl Fn(&SqliteRow) -> Result<T, sqlx::Error>,
+    ) -> Result<Page<T>, sqlx::Error> {
+        let limit = limit.max(1) as usize;
+        let next_cursor = if rows.len() > limit {
+            rows.truncate(limit);
+            let last = &rows[limit - 1];
+            let key = if self.numeric {
+                CursorKey::Number(last.try_get("sort_key")?)
+            } else {
+                CursorKey::Text(last.try_get("sort_key")?)
+            };
+            let cursor = Cursor { key, id: last.try_get("id")? };
+            Some(serde_json::to_string(&cursor).map_err(|e| sqlx::Error::Protocol(e.to_string()))?)
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items: rows.iter().map(map).collect::<Result<_, _>>()?,
+            next_cursor,
+            total,
+        })
+    }
+}
+
 // Insert a new quote with some default values
 pub async fn insert_quote_lite<'e, E>(
     content: String,
@@ -49,6 +183,12 @@ where
             imported_at: Some(now),
             deleted_at: None,
             original_id: None,
+            color: None,
+            page_start: None,
+            page_end: None,
+            location_start: None,
+            location_end: None,
+            import_batch_id: None,
         },
         executor,
     )
@@ -79,6 +219,7 @@ where
             created_at: now,
             updated_at: now,
             deleted_at: None,
+            import_batch_id: None,
         },
         executor,
     )
@@ -101,6 +242,17 @@ where
         .await
 }
 
+/// Get author by ID
+pub async fn get_author_by_id<'e, E>(id: &str, executor: E) -> Result<Author, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Author>("SELECT * FROM author WHERE id = ?")
+        .bind(id)
+        .fetch_one(executor)
+        .await
+}
+
 /// Get book by ID
 pub async fn get_book_by_id<'e, E>(id: String, executor: E) -> Result<Book, sqlx::Error>
 where
@@ -112,18 +264,41 @@ where
         .await
 }
 
+/// The row of an `INSERT` or `UPDATE` with `RETURNING`, fetched with `fetch_all`.
+///
+/// `fetch_one` returns as soon as SQLite hands back the row, before the statement has run to
+/// its end and committed, so another connection writing right after it can get
+/// "database is locked". Fetching all the rows waits for the statement to finish.
+fn returned_row<T>(rows: Vec<T>) -> Result<T, sqlx::Error> {
+    rows.into_iter().next().ok_or(sqlx::Error::RowNotFound)
+}
+
 /// Insert a new author
 pub async fn insert_author<'e, E>(author_name: String, executor: E) -> Result<Author, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    insert_imported_author(author_name, None, executor).await
+}
+
+/// Insert a new author created by the import `import_batch_id`
+pub async fn insert_imported_author<'e, E>(
+    author_name: String,
+    import_batch_id: Option<&str>,
+    executor: E,
+) -> Result<Author, sqlx::Error>
 where
     E: Executor<'e, Database = Sqlite>,
 {
     let author = sqlx::query_as::<_, Author>(
-        "INSERT INTO author (id, name) VALUES (?, ?) RETURNING *",
+        "INSERT INTO author (id, name, import_batch_id) VALUES (?, ?, ?) RETURNING *",
     )
     .bind(Uuid::new_v4().to_string())
     .bind(author_name.clone())
-    .fetch_one(executor)
-    .await?;
+    .bind(import_batch_id)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)?;
 
     Ok(author)
 }
@@ -135,18 +310,36 @@ pub async fn insert_book_with_defaults<'e, E>(
     original_id: Option<String>,
     executor: E,
 ) -> Result<Book, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    insert_imported_book(book_title, author_id, original_id, None, executor).await
+}
+
+/// Insert a new book created by the import `import_batch_id`
+pub async fn insert_imported_book<'e, E>(
+    book_title: String,
+    author_id: Option<String>,
+    original_id: Option<String>,
+    import_batch_id: Option<&str>,
+    executor: E,
+) -> Result<Book, sqlx::Error>
 where
     E: Executor<'e, Database = Sqlite>,
 {
     sqlx::query_as::<_, Book>(
-        "INSERT INTO book (id, title, author_id, original_id) VALUES (?, ?, ?, ?) RETURNING *",
+        "INSERT INTO book (id, title, author_id, original_id, import_batch_id)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING *",
     )
     .bind(Uuid::new_v4().to_string())
     .bind(book_title)
     .bind(author_id)
     .bind(original_id)
-    .fetch_one(executor)
+    .bind(import_batch_id)
+    .fetch_all(executor)
     .await
+    .and_then(returned_row)
 }
 
 pub async fn insert_quote<'e, E>(quote: &Quote, executor: E) -> Result<Quote, sqlx::Error>
@@ -167,9 +360,15 @@ where
                 updated_at,
                 imported_at,
                 deleted_at,
-                original_id
+                original_id,
+                color,
+                page_start,
+                page_end,
+                location_start,
+                location_end,
+                import_batch_id
             ) 
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             RETURNING *",
     )
     .bind(quote.id.clone())
@@ -184,8 +383,15 @@ where
     .bind(quote.imported_at)
     .bind(quote.deleted_at)
     .bind(quote.original_id.clone())
-    .fetch_one(executor)
+    .bind(quote.color.clone())
+    .bind(quote.page_start)
+    .bind(quote.page_end)
+    .bind(quote.location_start)
+    .bind(quote.location_end)
+    .bind(&quote.import_batch_id)
+    .fetch_all(executor)
     .await
+    .and_then(returned_row)
 }
 
 pub async fn insert_chapter<'e, E>(chapter: &Chapter, executor: E) -> Result<Chapter, sqlx::Error>
@@ -194,8 +400,8 @@ where
 {
     sqlx::query_as::<_, Chapter>(
         "INSERT OR IGNORE INTO chapter
-        (id, book_id, title, volume_index, original_id) 
-        VALUES (?, ?, ?, ?, ?) 
+        (id, book_id, title, volume_index, original_id, import_batch_id) 
+        VALUES (?, ?, ?, ?, ?, ?) 
         RETURNING *",
     )
     .bind(chapter.id.clone())
@@ -203,8 +409,10 @@ where
     .bind(chapter.title.clone())
     .bind(chapter.volume_index)
     .bind(chapter.original_id.clone())
-    .fetch_one(executor)
+    .bind(&chapter.import_batch_id)
+    .fetch_all(executor)
     .await
+    .and_then(returned_row)
 }
 
 pub async fn get_chapters_by_book<'e, E>(
@@ -230,14 +438,123 @@ where
         .await
 }
 
+/// Get the bookmarks of a book, in reading order
+pub async fn get_bookmarks_by_book<'e, E>(
+    book_id: &str,
+    executor: E,
+) -> Result<Vec<Bookmark>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Bookmark>(
+        "SELECT bm.* FROM bookmark bm
+        LEFT JOIN chapter c ON bm.chapter_id = c.id
+        WHERE bm.book_id = ?
+        ORDER BY c.volume_index, bm.chapter_progress, bm.created_at",
+    )
+    .bind(book_id)
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn get_bookmark_by_original_id<'e, E>(
+    original_id: &str,
+    executor: E,
+) -> Result<Bookmark, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Bookmark>("SELECT * FROM bookmark WHERE original_id = ?")
+        .bind(original_id)
+        .fetch_one(executor)
+        .await
+}
+
+pub async fn insert_bookmark<'e, E>(
+    bookmark: &Bookmark,
+    executor: E,
+) -> Result<Bookmark, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Bookmark>(
+        "INSERT INTO bookmark
+            (id, book_id, chapter_id, chapter_progress, kind, created_at, updated_at, original_id,
+            import_batch_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING *",
+    )
+    .bind(&bookmark.id)
+    .bind(&bookmark.book_id)
+    .bind(&bookmark.chapter_id)
+    .bind(bookmark.chapter_progress)
+    .bind(&bookmark.kind)
+    .bind(bookmark.created_at)
+    .bind(bookmark.updated_at)
+    .bind(&bookmark.original_id)
+    .bind(&bookmark.import_batch_id)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)
+}
+
+pub async fn delete_bookmark<'e, E>(bookmark_id: &str, executor: E) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("DELETE FROM bookmark WHERE id = ?")
+        .bind(bookmark_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Get the highlight colors of the quotes not deleted, for the color filters
+pub async fn get_quote_colors<'e, E>(executor: E) -> Result<Vec<String>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_scalar(
+        "SELECT DISTINCT LOWER(color) AS color FROM quote
+        WHERE color IS NOT NULL AND deleted_at IS NULL
+        ORDER BY color",
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the notes attached to any of the quotes, oldest first.
+pub async fn get_notes_by_quote_ids<'e, E>(
+    quote_ids: &[String],
+    executor: E,
+) -> Result<Vec<Note>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    if quote_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM note WHERE quote_id IN (");
+    let mut ids = query.separated(", ");
+    for quote_id in quote_ids {
+        ids.push_bind(quote_id);
+    }
+    query.push(") AND deleted_at IS NULL ORDER BY created_at ASC");
+
+    query.build_query_as::<Note>().fetch_all(executor).await
+}
+
 pub async fn insert_note<'e, E>(note: &Note, executor: E) -> Result<Note, sqlx::Error>
 where
     E: Executor<'e, Database = Sqlite>,
 {
     sqlx::query_as::<_, Note>(
         "INSERT INTO note 
-            (id, content, quote_id, book_id, author_id, created_at, updated_at, deleted_at) 
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            (id, content, quote_id, book_id, author_id, created_at, updated_at, deleted_at,
+            import_batch_id) 
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
             RETURNING *",
     )
     .bind(note.id.clone())
@@ -248,8 +565,10 @@ where
     .bind(note.created_at)
     .bind(note.updated_at)
     .bind(note.deleted_at)
-    .fetch_one(executor)
+    .bind(&note.import_batch_id)
+    .fetch_all(executor)
     .await
+    .and_then(returned_row)
 }
 
 pub async fn update_note<'e, E>(
@@ -268,8 +587,9 @@ where
     )
     .bind(content)
     .bind(note_id)
-    .fetch_one(executor)
+    .fetch_all(executor)
     .await
+    .and_then(returned_row)
 }
 
 pub async fn update_author_name<'e, E>(
@@ -283,8 +603,9 @@ where
     sqlx::query_as::<_, Author>("UPDATE author SET name = ?1 WHERE id = ?2 RETURNING *")
         .bind(author_name)
         .bind(author_id)
-        .fetch_one(executor)
+        .fetch_all(executor)
         .await
+        .and_then(returned_row)
 }
 
 /// Get all books
@@ -317,6 +638,52 @@ where
     .await
 }
 
+/// Get a page of the books, sorted by title
+pub async fn get_books_page(
+    cursor: Option<&str>,
+    limit: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Page<Book>, sqlx::Error> {
+    let cursor = decode_cursor(cursor)?;
+    let key = SortKey::text("b.title", "b.id");
+
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM book WHERE deleted_at IS NULL")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT b.*, b.title AS sort_key FROM book b WHERE b.deleted_at IS NULL",
+    );
+    key.push_after(&mut query, cursor);
+    key.push_order(&mut query, limit);
+    let rows = query.build().fetch_all(&mut *conn).await?;
+
+    key.to_page(rows, limit, total, |row| Book::from_row(row))
+}
+
+/// Get a page of the authors, sorted by name
+pub async fn get_authors_page(
+    cursor: Option<&str>,
+    limit: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Page<Author>, sqlx::Error> {
+    let cursor = decode_cursor(cursor)?;
+    let key = SortKey::text("a.name", "a.id");
+
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM author WHERE deleted_at IS NULL")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT a.*, a.name AS sort_key FROM author a WHERE a.deleted_at IS NULL",
+    );
+    key.push_after(&mut query, cursor);
+    key.push_order(&mut query, limit);
+    let rows = query.build().fetch_all(&mut *conn).await?;
+
+    key.to_page(rows, limit, total, |row| Author::from_row(row))
+}
+
 /// Get all books by author
 pub async fn get_all_books_by_author<'e, E>(
     author_id: String,
@@ -336,6 +703,25 @@ where
     .await
 }
 
+const QUOTE_WITH_TAGS_SELECT: &str = "SELECT
+        q.*,
+        json_group_array(
+            json_object(
+                'id', t.id,
+                'name', t.name,
+                'color', t.color
+            )
+        ) AS tags_json";
+
+/// Same as `QUOTE_WITH_TAGS_REDUX_FROM`, keeping the quotes without a book or author.
+const QUOTE_WITH_TAGS_FROM: &str = "
+    FROM quote q
+    LEFT JOIN book b ON q.book_id = b.id
+    LEFT JOIN author a ON b.author_id = a.id
+    LEFT JOIN quote_tag qt ON q.id = qt.quote_id
+    LEFT JOIN tag t ON qt.tag_id = t.id
+    WHERE 1 = 1";
+
 /// Get all quotes by book ID
 pub async fn get_all_quotes_by_book_id<'e, E>(
     book_id: &str,
@@ -348,70 +734,276 @@ where
 {
     let order = sort_order.unwrap_or("ASC");
     let (order_clause, sort_by_clause) = extract_order_clauses(sort_by, Some(order));
+    let filter = QuoteFilter {
+        book_id: Some(book_id.to_string()),
+        ..Default::default()
+    };
 
-    let sql = format!(
-        "WITH QuoteResults AS (
-            SELECT * 
-            FROM quote 
-            WHERE book_id = ? AND deleted_at IS NULL
-        )
-        SELECT 
-            q.*,
-            json_group_array(
-                json_object(
-                    'id', t.id,
-                    'name', t.name,
-                    'color', t.color
-                )
-            ) as tags_json
-        FROM QuoteResults q
-        LEFT JOIN quote_tag qt ON q.id = qt.quote_id
-        LEFT JOIN tag t ON qt.tag_id = t.id
-        GROUP BY q.id
-        ORDER BY q.{} {}
-        ",
-        sort_by_clause, order_clause
+    let mut query = QueryBuilder::<Sqlite>::new(QUOTE_WITH_TAGS_SELECT);
+    query.push(QUOTE_WITH_TAGS_FROM);
+    push_quote_filter(&mut query, &filter);
+    query.push(format!(
+        " GROUP BY q.id ORDER BY {} {}, q.id {}",
+        quote_order_column(&sort_by_clause),
+        order_clause,
+        order_clause
+    ));
+
+    let rows = query.build().fetch_all(executor).await?;
+
+    Ok(rows.iter().map(quote_with_tags_from_row).collect())
+}
+
+fn quote_with_tags_from_row(row: &SqliteRow) -> QuoteWithTags {
+    let tags_json: String = row.get("tags_json");
+    let tags: Vec<Tag> = serde_json::from_str(&tags_json).unwrap_or_default();
+
+    QuoteWithTags {
+        id: row.get("id"),
+        book_id: row.get("book_id"),
+        author_id: row.get("author_id"),
+        chapter_id: row.get("chapter_id"),
+        chapter_progress: row.get("chapter_progress"),
+        content: row.get("content"),
+        starred: row.get("starred"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        imported_at: row.get("imported_at"),
+        deleted_at: row.get("deleted_at"),
+        original_id: row.get("original_id"),
+        color: row.get("color"),
+        page_start: row.get("page_start"),
+        page_end: row.get("page_end"),
+        location_start: row.get("location_start"),
+        location_end: row.get("location_end"),
+        tags,
+    }
+}
+
+/// Get a page of the quotes of a book, starting after `cursor`.
+pub async fn get_all_quotes_by_book_id_page(
+    book_id: &str,
+    sort_by: Option<&str>,
+    sort_order: Option<&str>,
+    cursor: Option<&str>,
+    limit: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Page<QuoteWithTags>, sqlx::Error> {
+    let cursor = decode_cursor(cursor)?;
+    let key = SortKey::quote(sort_by, Some(sort_order.unwrap_or("ASC")));
+    let filter = QuoteFilter {
+        book_id: Some(book_id.to_string()),
+        ..Default::default()
+    };
+
+    let mut count = QueryBuilder::<Sqlite>::new(
+        "SELECT COUNT(*)
+        FROM quote q
+        LEFT JOIN book b ON q.book_id = b.id
+        LEFT JOIN author a ON b.author_id = a.id
+        WHERE 1 = 1",
     );
+    push_quote_filter(&mut count, &filter);
+    let total: i64 = count.build_query_scalar().fetch_one(&mut *conn).await?;
 
-    let rows = sqlx::query(&sql)
-        .bind(book_id)
-        .fetch_all(executor)
-        .await?;
+    let mut query = QueryBuilder::<Sqlite>::new(QUOTE_WITH_TAGS_SELECT);
+    query.push(format!(", {} AS sort_key", key.expr));
+    query.push(QUOTE_WITH_TAGS_FROM);
+    push_quote_filter(&mut query, &filter);
+    key.push_after(&mut query, cursor);
+    query.push(" GROUP BY q.id");
+    key.push_order(&mut query, limit);
 
-    let quotes_with_tags = rows
-        .iter()
-        .map(|row| {
-            let tags_json: String = row.get("tags_json");
-            let tags: Vec<Tag> = serde_json::from_str(&tags_json).unwrap_or_default();
+    let rows = query.build().fetch_all(&mut *conn).await?;
 
-            QuoteWithTags {
-                id: row.get("id"),
-                book_id: row.get("book_id"),
-                author_id: row.get("author_id"),
-                chapter_id: row.get("chapter_id"),
-                chapter_progress: row.get("chapter_progress"),
-                content: row.get("content"),
-                starred: row.get("starred"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                imported_at: row.get("imported_at"),
-                deleted_at: row.get("deleted_at"),
-                original_id: row.get("original_id"),
-                tags: tags,
+    key.to_page(rows, limit, total, |row| Ok(quote_with_tags_from_row(row)))
+}
+
+/// Max number of tokens returned by `snippet()`.
+const FTS_SNIPPET_TOKENS: i64 = 16;
+
+/// Snippet/highlight markers wrapped around every FTS hit, from the Unicode private use area
+/// so they can't be mistaken for the quote text.
+const FTS_HIT_OPEN: &str = "\u{E000}";
+const FTS_HIT_CLOSE: &str = "\u{E001}";
+
+/// HTML of a `snippet()` or `highlight()` result: the quote text is escaped,
+/// and the hits are wrapped in `<mark>` tags.
+fn fts_markup(text: Option<String>) -> Option<String> {
+    text.map(|text| {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&#39;")
+            .replace(FTS_HIT_OPEN, "<mark>")
+            .replace(FTS_HIT_CLOSE, "</mark>")
+    })
+}
+
+/// Turn raw user input into a safe FTS5 query restricted to the `content` column.
+///
+/// Supports `"phrase queries"`, `prefix*` terms and the `AND`/`OR`/`NOT` operators.
+/// Every term is quoted so any other FTS5 syntax (columns, parentheses, `^`, `-`, ...)
+/// is treated as plain text instead of producing an SQL error.
+/// Returns `None` if the input contains nothing searchable.
+fn to_fts_query(search: &str) -> Option<String> {
+    enum Token {
+        Term(String, bool), // (text, is_prefix)
+        Operator(&'static str),
+    }
+
+    let mut tokens = Vec::new();
+    let mut chars = search.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() || c == '(' || c == ')' {
+            continue;
+        }
+
+        let text = if c == '"' {
+            // Phrase, unterminated phrases run to the end of the input
+            let mut phrase = String::new();
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                phrase.push(c);
             }
-        })
-        .collect();
+            phrase
+        } else {
+            let mut word = String::from(c);
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' || c == '(' || c == ')' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            let operator = match word.as_str() {
+                "AND" => Some("AND"),
+                "OR" => Some("OR"),
+                "NOT" => Some("NOT"),
+                _ => None,
+            };
+            if let Some(operator) = operator {
+                tokens.push(Token::Operator(operator));
+                continue;
+            }
+            word
+        };
+
+        let mut prefix = false;
+        while chars.peek() == Some(&'*') {
+            prefix = true;
+            chars.next();
+        }
+        let text = if text.ends_with('*') {
+            prefix = true;
+            text.trim_end_matches('*').to_string()
+        } else {
+            text
+        };
+
+        // Skip terms the tokenizer would drop anyway, e.g. "-" or "..."
+        if text.chars().any(|c| c.is_alphanumeric()) {
+            tokens.push(Token::Term(text, prefix));
+        }
+    }
+
+    let mut query = String::new();
+    let mut pending_operator = None;
+
+    for token in tokens {
+        match token {
+            // Operators are only valid between two terms
+            Token::Operator(op) => {
+                if !query.is_empty() {
+                    pending_operator = Some(op);
+                }
+            }
+            Token::Term(text, prefix) => {
+                if !query.is_empty() {
+                    query.push(' ');
+                    if let Some(op) = pending_operator.take() {
+                        query.push_str(op);
+                        query.push(' ');
+                    }
+                }
+                query.push('"');
+                query.push_str(&text.replace('"', "\"\""));
+                query.push('"');
+                if prefix {
+                    query.push('*');
+                }
+            }
+        }
+    }
 
-    Ok(quotes_with_tags)
+    if query.is_empty() {
+        None
+    } else {
+        Some(format!("{{content}} : ({})", query))
+    }
 }
 
-/// Find notes using full-text search
-pub async fn find_quotes<'e, E>(search: &str, executor: E) -> Result<Vec<QuoteFts>, sqlx::Error>
+/// Restricts a full-text search to an exact book title or author name,
+/// which a [`QuoteFilter`] only matches by id.
+enum FtsFilter<'a> {
+    BookTitle(&'a str),
+    AuthorName(&'a str),
+}
+
+/// Full-text search on the quotes matching `filter`, ranked by `bm25()`.
+///
+/// The text of the filter is matched here rather than by [`push_quote_filter`],
+/// which has no rank or snippets.
+async fn fts_search_quotes<'e, E>(
+    filter: &QuoteFilter,
+    name: Option<FtsFilter<'_>>,
+    executor: E,
+) -> Result<Vec<QuoteFts>, sqlx::Error>
 where
     E: Executor<'e, Database = Sqlite>,
 {
-    let rows = sqlx::query(
-        r#"
+    let Some(fts_query) = filter.text.as_deref().and_then(to_fts_query) else {
+        return Ok(Vec::new());
+    };
+    let filter = QuoteFilter {
+        text: None,
+        ..filter.clone()
+    };
+
+    // The matches are materialized first since the FTS auxiliary functions
+    // are not available once the rows are grouped for the tags aggregation.
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "WITH matches AS MATERIALIZED (
+            SELECT
+                fts.id,
+                fts.book_title,
+                fts.author_name,
+                bm25(quote_fts) AS rank,
+                snippet(quote_fts, 1, ",
+    );
+    query
+        .push_bind(FTS_HIT_OPEN)
+        .push(", ")
+        .push_bind(FTS_HIT_CLOSE)
+        .push(", '…', ")
+        .push_bind(FTS_SNIPPET_TOKENS)
+        .push(") AS snippet, highlight(quote_fts, 1, ")
+        .push_bind(FTS_HIT_OPEN)
+        .push(", ")
+        .push_bind(FTS_HIT_CLOSE)
+        .push(
+            ") AS highlight
+            FROM quote_fts fts
+            WHERE quote_fts MATCH ",
+        )
+        .push_bind(fts_query);
+    query.push(
+        "
+        )
         SELECT 
             q.id, 
             q.content,
@@ -425,8 +1017,11 @@ where
             q.original_id,
             q.book_id,
             q.author_id,
-            fts.book_title,
-            fts.author_name,
+            m.book_title,
+            m.author_name,
+            m.rank,
+            m.snippet,
+            m.highlight,
             json_group_array(
                 json_object(
                     'id', t.id,
@@ -434,17 +1029,27 @@ where
                     'color', t.color
                 )
             ) AS tags_json
-        FROM quote_fts fts
-        JOIN quote q ON q.id = fts.id
+        FROM matches m
+        JOIN quote q ON q.id = m.id
+        LEFT JOIN book b ON q.book_id = b.id
+        LEFT JOIN author a ON b.author_id = a.id
         LEFT JOIN quote_tag qt ON q.id = qt.quote_id
         LEFT JOIN tag t ON qt.tag_id = t.id
-        WHERE fts.content LIKE ?
-            AND q.deleted_at IS NULL
-        GROUP BY q.id;"#,
-    )
-    .bind(format!("%{}%", search))
-    .fetch_all(executor)
-    .await?;
+        WHERE 1 = 1",
+    );
+    push_quote_filter(&mut query, &filter);
+    match name {
+        Some(FtsFilter::BookTitle(title)) => {
+            query.push(" AND m.book_title = ").push_bind(title);
+        }
+        Some(FtsFilter::AuthorName(name)) => {
+            query.push(" AND m.author_name = ").push_bind(name);
+        }
+        None => {}
+    }
+    query.push(" GROUP BY q.id ORDER BY m.rank ASC");
+
+    let rows = query.build().fetch_all(executor).await?;
 
     let quotes = rows
         .iter()
@@ -467,6 +1072,9 @@ where
                 author_id: row.get("author_id"),
                 book_title: row.get("book_title"),
                 author_name: row.get("author_name"),
+                rank: row.get("rank"),
+                snippet: fts_markup(row.get("snippet")),
+                highlight: fts_markup(row.get("highlight")),
                 tags,
             }
         })
@@ -475,6 +1083,19 @@ where
     Ok(quotes)
 }
 
+/// Find quotes using full-text search
+pub async fn find_quotes<'e, E>(search: &str, executor: E) -> Result<Vec<QuoteFts>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let filter = QuoteFilter {
+        text: Some(search.to_string()),
+        ..Default::default()
+    };
+    fts_search_quotes(&filter, None, executor).await
+}
+
+/// Find quotes using full-text search, limited to a book
 pub async fn find_quotes_by_book_title<'e, E>(
     search: &str,
     book_title: &str,
@@ -483,162 +1104,45 @@ pub async fn find_quotes_by_book_title<'e, E>(
 where
     E: Executor<'e, Database = Sqlite>,
 {
-    let rows = sqlx::query(
-        r#"
-        SELECT 
-            q.id, 
-            q.content,
-            q.chapter_id,
-            q.chapter_progress,
-            q.starred,
-            q.created_at,
-            q.updated_at,
-            q.deleted_at,
-            q.imported_at,
-            q.original_id,
-            q.book_id,
-            q.author_id,
-            fts.book_title,
-            fts.author_name,
-            json_group_array(
-                json_object(
-                    'id', t.id,
-                    'name', t.name,
-                    'color', t.color
-                )
-            ) AS tags_json
-        FROM quote_fts fts
-        JOIN quote q ON q.id = fts.id
-        LEFT JOIN quote_tag qt ON q.id = qt.quote_id
-        LEFT JOIN tag t ON qt.tag_id = t.id
-        WHERE fts.content MATCH ? AND fts.book_title = ?
-            AND q.deleted_at IS NULL
-        GROUP BY q.id;"#,
+    let filter = QuoteFilter {
+        text: Some(search.to_string()),
+        ..Default::default()
+    };
+    fts_search_quotes(&filter, Some(FtsFilter::BookTitle(book_title)), executor).await
+}
+
+/// Find quotes using full-text search, limited to an author
+pub async fn find_quotes_by_author_name<'e, E>(
+    search: &str,
+    author_name: &str,
+    executor: E,
+) -> Result<Vec<QuoteFts>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let filter = QuoteFilter {
+        text: Some(search.to_string()),
+        ..Default::default()
+    };
+    fts_search_quotes(&filter, Some(FtsFilter::AuthorName(author_name)), executor).await
+}
+
+/// Find books by title
+pub async fn find_books_by_title<'e, E>(search: &str, executor: E) -> Result<Vec<Book>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let search_pattern = format!("%{}%", search);
+    sqlx::query_as::<_, Book>(
+        "SELECT *
+         FROM book
+         WHERE title LIKE ? 
+         ORDER BY title",
     )
-    .bind(search)
-    .bind(book_title)
+    .bind(search_pattern)
     .fetch_all(executor)
-    .await?;
-
-    let quotes = rows
-        .iter()
-        .map(|row| {
-            let tags_json: String = row.get("tags_json");
-            let tags: Vec<Tag> = serde_json::from_str(&tags_json).unwrap_or_default();
-
-            QuoteFts {
-                id: row.get("id"),
-                content: row.get("content"),
-                chapter_id: row.get("chapter_id"),
-                chapter_progress: row.get("chapter_progress"),
-                starred: row.get("starred"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                deleted_at: row.get("deleted_at"),
-                imported_at: row.get("imported_at"),
-                original_id: row.get("original_id"),
-                book_id: row.get("book_id"),
-                author_id: row.get("author_id"),
-                book_title: row.get("book_title"),
-                author_name: row.get("author_name"),
-                tags,
-            }
-        })
-        .collect();
-
-    Ok(quotes)
-}
-
-pub async fn find_quotes_by_author_name<'e, E>(
-    search: &str,
-    author_name: &str,
-    executor: E,
-) -> Result<Vec<QuoteFts>, sqlx::Error>
-where
-    E: Executor<'e, Database = Sqlite>,
-{
-    let rows = sqlx::query(
-        r#"
-        SELECT 
-            q.id, 
-            q.content,
-            q.chapter_id,
-            q.chapter_progress,
-            q.starred,
-            q.created_at,
-            q.updated_at,
-            q.deleted_at,
-            q.imported_at,
-            q.original_id,
-            q.book_id,
-            q.author_id,
-            fts.book_title, 
-            fts.author_name,
-            json_group_array(
-                json_object(
-                    'id', t.id,
-                    'name', t.name,
-                    'color', t.color
-                )
-            ) AS tags_json
-        FROM quote_fts fts
-        JOIN quote q ON q.id = fts.id
-        LEFT JOIN quote_tag qt ON q.id = qt.quote_id
-        LEFT JOIN tag t ON qt.tag_id = t.id
-        WHERE fts.content MATCH ? AND fts.author_name = ?
-            AND q.deleted_at IS NULL
-        GROUP BY q.id;"#,
-    )
-    .bind(search)
-    .bind(author_name)
-    .fetch_all(executor)
-    .await?;
-
-    let quotes = rows
-        .iter()
-        .map(|row| {
-            let tags_json: String = row.get("tags_json");
-            let tags: Vec<Tag> = serde_json::from_str(&tags_json).unwrap_or_default();
-
-            QuoteFts {
-                id: row.get("id"),
-                content: row.get("content"),
-                chapter_id: row.get("chapter_id"),
-                chapter_progress: row.get("chapter_progress"),
-                starred: row.get("starred"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                deleted_at: row.get("deleted_at"),
-                imported_at: row.get("imported_at"),
-                original_id: row.get("original_id"),
-                book_id: row.get("book_id"),
-                author_id: row.get("author_id"),
-                book_title: row.get("book_title"),
-                author_name: row.get("author_name"),
-                tags,
-            }
-        })
-        .collect();
-
-    Ok(quotes)
-}
-
-/// Find books by title
-pub async fn find_books_by_title<'e, E>(search: &str, executor: E) -> Result<Vec<Book>, sqlx::Error>
-where
-    E: Executor<'e, Database = Sqlite>,
-{
-    let search_pattern = format!("%{}%", search);
-    sqlx::query_as::<_, Book>(
-        "SELECT *
-         FROM book
-         WHERE title LIKE ? 
-         ORDER BY title",
-    )
-    .bind(search_pattern)
-    .fetch_all(executor)
-    .await
-}
+    .await
+}
 
 /// Find authors by name
 pub async fn find_authors_by_name<'e, E>(
@@ -676,34 +1180,46 @@ pub async fn delete_quote<'e, E>(quote_id: &str, executor: E) -> Result<(), sqlx
 where
     E: Executor<'e, Database = Sqlite>,
 {
-    sqlx::query("UPDATE quote SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?")
-        .bind(quote_id)
-        .execute(executor)
-        .await?;
+    sqlx::query(
+        "UPDATE quote
+        SET deleted_at = strftime('%Y-%m-%d %H:%M:%f', 'now')
+        WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(quote_id)
+    .execute(executor)
+    .await?;
     Ok(())
 }
 
-/// Set book as deleted
+/// Set book as deleted, its quotes and notes are hidden with it
 pub async fn delete_book<'e, E>(book_id: &str, executor: E) -> Result<(), sqlx::Error>
 where
     E: Executor<'e, Database = Sqlite>,
 {
-    sqlx::query("UPDATE book SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?")
-        .bind(book_id)
-        .execute(executor)
-        .await?;
+    sqlx::query(
+        "UPDATE book
+        SET deleted_at = strftime('%Y-%m-%d %H:%M:%f', 'now')
+        WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(book_id)
+    .execute(executor)
+    .await?;
     Ok(())
 }
 
-/// Set author as deleted
+/// Set author as deleted, their books are hidden with them
 pub async fn delete_author<'e, E>(author_id: String, executor: E) -> Result<(), sqlx::Error>
 where
     E: Executor<'e, Database = Sqlite>,
 {
-    sqlx::query("UPDATE author SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?")
-        .bind(author_id.clone())
-        .execute(executor)
-        .await?;
+    sqlx::query(
+        "UPDATE author
+        SET deleted_at = strftime('%Y-%m-%d %H:%M:%f', 'now')
+        WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(author_id.clone())
+    .execute(executor)
+    .await?;
 
     Ok(())
 }
@@ -712,10 +1228,262 @@ pub async fn delete_author_books<'e, E>(author_id: String, executor: E) -> Resul
 where
     E: Executor<'e, Database = Sqlite>,
 {
-    sqlx::query("UPDATE book SET deleted_at = CURRENT_TIMESTAMP WHERE author_id = ?")
+    sqlx::query(
+        "UPDATE book
+        SET deleted_at = strftime('%Y-%m-%d %H:%M:%f', 'now')
+        WHERE author_id = ? AND deleted_at IS NULL",
+    )
+    .bind(author_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Get quotes moved to the trash on their own, not together with their book
+pub async fn get_trashed_quotes<'e, E>(executor: E) -> Result<Vec<TrashedQuote>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, TrashedQuote>(
+        "SELECT
+            q.id,
+            q.book_id,
+            b.title AS book_title,
+            q.author_id,
+            a.name AS author_name,
+            q.content,
+            q.deleted_at
+        FROM quote q
+        LEFT JOIN book b ON q.book_id = b.id
+        LEFT JOIN author a ON q.author_id = a.id
+        WHERE q.deleted_at IS NOT NULL
+          AND (b.deleted_at IS NULL OR b.deleted_at != q.deleted_at)
+        ORDER BY q.deleted_at DESC",
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Get books moved to the trash on their own, not together with their author
+pub async fn get_trashed_books<'e, E>(executor: E) -> Result<Vec<Book>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Book>(
+        "SELECT b.*
+        FROM book b
+        LEFT JOIN author a ON b.author_id = a.id
+        WHERE b.deleted_at IS NOT NULL
+          AND (a.deleted_at IS NULL OR a.deleted_at != b.deleted_at)
+        ORDER BY b.deleted_at DESC",
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Get authors moved to the trash
+pub async fn get_trashed_authors<'e, E>(executor: E) -> Result<Vec<Author>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Author>(
+        "SELECT * FROM author WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Restore a quote from the trash
+pub async fn restore_quote<'e, E>(quote_id: &str, executor: E) -> Result<Quote, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Quote>(
+        "UPDATE quote SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL RETURNING *",
+    )
+    .bind(quote_id)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)
+}
+
+/// Restore a book from the trash, with the quotes and notes hidden with it
+pub async fn restore_book<'e, E>(book_id: &str, executor: E) -> Result<Book, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Book>(
+        "UPDATE book SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL RETURNING *",
+    )
+    .bind(book_id)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)
+}
+
+/// Restore an author from the trash, with the books hidden with them
+pub async fn restore_author<'e, E>(author_id: &str, executor: E) -> Result<Author, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Author>(
+        "UPDATE author SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL RETURNING *",
+    )
+    .bind(author_id)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)
+}
+
+/// Permanently delete a trashed quote with its tags and notes.
+/// The `quote_fts` entry is removed by the `quote_fts_before_delete` trigger.
+pub async fn purge_quote(quote_id: &str, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT id FROM quote WHERE id = ? AND deleted_at IS NOT NULL")
+        .bind(quote_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM quote_tag WHERE quote_id = ?")
+        .bind(quote_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM note WHERE quote_id = ?")
+        .bind(quote_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM quote WHERE id = ?")
+        .bind(quote_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Permanently delete a trashed book with its chapters, quotes, tags and notes
+pub async fn purge_book(book_id: &str, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT id FROM book WHERE id = ? AND deleted_at IS NOT NULL")
+        .bind(book_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    for sql in [
+        "DELETE FROM quote_tag WHERE quote_id IN (SELECT id FROM quote WHERE book_id = ?1)",
+        "DELETE FROM note WHERE book_id = ?1 OR quote_id IN (SELECT id FROM quote WHERE book_id = ?1)",
+        "DELETE FROM quote WHERE book_id = ?1",
+        "DELETE FROM bookmark WHERE book_id = ?1",
+        "DELETE FROM chapter WHERE book_id = ?1",
+        "DELETE FROM book WHERE id = ?1",
+    ] {
+        sqlx::query(sql).bind(book_id).execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Permanently delete a trashed author with all their books
+pub async fn purge_author(author_id: &str, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT id FROM author WHERE id = ? AND deleted_at IS NOT NULL")
         .bind(author_id)
-        .execute(executor)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    for sql in [
+        "DELETE FROM quote_tag WHERE quote_id IN (
+            SELECT id FROM quote
+            WHERE author_id = ?1 OR book_id IN (SELECT id FROM book WHERE author_id = ?1)
+        )",
+        "DELETE FROM note WHERE author_id = ?1
+            OR book_id IN (SELECT id FROM book WHERE author_id = ?1)
+            OR quote_id IN (
+                SELECT id FROM quote
+                WHERE author_id = ?1 OR book_id IN (SELECT id FROM book WHERE author_id = ?1)
+            )",
+        "DELETE FROM quote WHERE author_id = ?1 OR book_id IN (SELECT id FROM book WHERE author_id = ?1)",
+        "DELETE FROM bookmark WHERE book_id IN (SELECT id FROM book WHERE author_id = ?1)",
+        "DELETE FROM chapter WHERE book_id IN (SELECT id FROM book WHERE author_id = ?1)",
+        "DELETE FROM book WHERE author_id = ?1",
+        "DELETE FROM author WHERE id = ?1",
+    ] {
+        sqlx::query(sql).bind(author_id).execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Permanently delete everything in the trash.
+/// If `older_than_days` is set, only items trashed before that many days ago are removed.
+/// Returns the number of purged authors, books and quotes.
+pub async fn purge_trash(
+    older_than_days: Option<i64>,
+    conn: &mut SqliteConnection,
+) -> Result<u64, sqlx::Error> {
+    let cutoff = format!("-{} days", older_than_days.unwrap_or(0).max(0));
+    let trashed_sql = |table: &str| {
+        format!(
+            "SELECT id FROM {}
+            WHERE deleted_at IS NOT NULL
+              AND deleted_at <= strftime('%Y-%m-%d %H:%M:%f', 'now', ?)",
+            table
+        )
+    };
+
+    // Parents first, the books and quotes hidden with them are purged along
+    let author_ids: Vec<String> = sqlx::query_scalar(&trashed_sql("author"))
+        .bind(&cutoff)
+        .fetch_all(&mut *conn)
+        .await?;
+    for id in author_ids.iter() {
+        purge_author(id, &mut *conn).await?;
+    }
+
+    let book_ids: Vec<String> = sqlx::query_scalar(&trashed_sql("book"))
+        .bind(&cutoff)
+        .fetch_all(&mut *conn)
+        .await?;
+    for id in book_ids.iter() {
+        purge_book(id, &mut *conn).await?;
+    }
+
+    let quote_ids: Vec<String> = sqlx::query_scalar(&trashed_sql("quote"))
+        .bind(&cutoff)
+        .fetch_all(&mut *conn)
         .await?;
+    for id in quote_ids.iter() {
+        purge_quote(id, &mut *conn).await?;
+    }
+
+    Ok((author_ids.len() + book_ids.len() + quote_ids.len()) as u64)
+}
+
+/// Get an application setting
+pub async fn get_setting<'e, E>(key: &str, executor: E) -> Result<Option<String>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_scalar("SELECT value FROM setting WHERE key = ?")
+        .bind(key)
+        .fetch_optional(executor)
+        .await
+        .map(Option::flatten)
+}
+
+/// Set an application setting, `None` removes it
+pub async fn set_setting<'e, E>(key: &str, value: Option<&str>, executor: E) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    match value {
+        Some(value) => sqlx::query(
+            "INSERT INTO setting (key, value) VALUES (?, ?)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(key)
+        .bind(value),
+        None => sqlx::query("DELETE FROM setting WHERE key = ?").bind(key),
+    }
+    .execute(executor)
+    .await?;
 
     Ok(())
 }
@@ -744,8 +1512,9 @@ where
     sqlx::query_as::<_, Quote>("UPDATE quote SET starred = ? WHERE id = ? RETURNING *")
         .bind(starred)
         .bind(quote_id)
-        .fetch_one(executor)
+        .fetch_all(executor)
         .await
+        .and_then(returned_row)
 }
 
 /// Toggle quote starred status
@@ -764,8 +1533,58 @@ where
             RETURNING *",
     )
     .bind(quote_id)
-    .fetch_one(executor)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)
+}
+
+/// Replace a quote by a newer version from its source, with its content and position
+pub async fn update_quote_version<'e, E>(quote: &Quote, executor: E) -> Result<Quote, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Quote>(
+        "UPDATE quote
+        SET content = ?, page_start = ?, page_end = ?, location_start = ?, location_end = ?,
+            updated_at = ?
+        WHERE id = ?
+        RETURNING *",
+    )
+    .bind(&quote.content)
+    .bind(quote.page_start)
+    .bind(quote.page_end)
+    .bind(quote.location_start)
+    .bind(quote.location_end)
+    .bind(quote.updated_at)
+    .bind(&quote.id)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)
+}
+
+/// Update the chapter, color and position of a quote, e.g. from a richer export of its source
+pub async fn update_quote_details<'e, E>(quote: &Quote, executor: E) -> Result<Quote, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Quote>(
+        "UPDATE quote
+        SET chapter_id = ?, color = ?, page_start = ?, page_end = ?, location_start = ?,
+            location_end = ?, updated_at = ?
+        WHERE id = ?
+        RETURNING *",
+    )
+    .bind(&quote.chapter_id)
+    .bind(&quote.color)
+    .bind(quote.page_start)
+    .bind(quote.page_end)
+    .bind(quote.location_start)
+    .bind(quote.location_end)
+    .bind(quote.updated_at)
+    .bind(&quote.id)
+    .fetch_all(executor)
     .await
+    .and_then(returned_row)
 }
 
 /// Update quote
@@ -780,8 +1599,9 @@ where
     sqlx::query_as::<_, Quote>("UPDATE quote SET content = ? WHERE id = ? RETURNING *")
         .bind(content)
         .bind(id)
-        .fetch_one(executor)
+        .fetch_all(executor)
         .await
+        .and_then(returned_row)
 }
 
 /// Get random quote
@@ -811,74 +1631,236 @@ where
     .await
 }
 
-pub async fn get_starred_quotes<'e, E>(
+/// Push the conditions of a quote filter to a query already in its WHERE clause.
+/// Returns false if the filter can't match any quote, e.g. an invalid full text search.
+fn push_quote_filter<'a>(query: &mut QueryBuilder<'a, Sqlite>, filter: &'a QuoteFilter) -> bool {
+    match filter.deleted {
+        DeletedState::Active => {
+            query.push(
+                " AND q.deleted_at IS NULL AND b.deleted_at IS NULL AND a.deleted_at IS NULL",
+            );
+        }
+        DeletedState::Deleted => {
+            query.push(" AND q.deleted_at IS NOT NULL");
+        }
+        DeletedState::All => {}
+    }
+    if let Some(text) = filter.text.as_deref().filter(|t| !t.trim().is_empty()) {
+        match to_fts_query(text) {
+            Some(fts_query) => {
+                query
+                    .push(" AND q.id IN (SELECT id FROM quote_fts WHERE quote_fts MATCH ")
+                    .push_bind(fts_query)
+                    .push(")");
+            }
+            None => return false,
+        }
+    }
+    if let Some(starred) = filter.starred {
+        query.push(" AND COALESCE(q.starred, 0) = ").push_bind(starred as i64);
+    }
+    let mut tag_ids: Vec<&String> = filter.tag_ids.iter().collect();
+    tag_ids.sort();
+    tag_ids.dedup();
+    if !tag_ids.is_empty() {
+        query.push(" AND q.id IN (SELECT quote_id FROM quote_tag WHERE tag_id IN (");
+        let mut tags = query.separated(", ");
+        for tag_id in &tag_ids {
+            tags.push_bind(*tag_id);
+        }
+        query.push(")");
+        if filter.tag_match == TagMatch::All {
+            query
+                .push(" GROUP BY quote_id HAVING COUNT(DISTINCT tag_id) = ")
+                .push_bind(tag_ids.len() as i64);
+        }
+        query.push(")");
+    }
+    if let Some(book_id) = &filter.book_id {
+        query.push(" AND q.book_id = ").push_bind(book_id);
+    }
+    if let Some(author_id) = &filter.author_id {
+        query.push(" AND b.author_id = ").push_bind(author_id);
+    }
+    if let Some(chapter_id) = &filter.chapter_id {
+        query.push(" AND q.chapter_id = ").push_bind(chapter_id);
+    }
+    if !filter.colors.is_empty() {
+        query.push(" AND LOWER(q.color) IN (");
+        let mut colors = query.separated(", ");
+        for color in &filter.colors {
+            colors.push_bind(color.to_lowercase());
+        }
+        query.push(")");
+    }
+
+    let ranges = [
+        ("b.created_at", filter.book_created_from, filter.book_created_to),
+        ("q.created_at", filter.created_from, filter.created_to),
+        ("q.imported_at", filter.imported_from, filter.imported_to),
+    ];
+    for (column, from, to) in ranges {
+        if let Some(from) = from {
+            query.push(format!(" AND {} >= ", column)).push_bind(from);
+        }
+        if let Some(to) = to {
+            query.push(format!(" AND {} < ", column)).push_bind(to);
+        }
+    }
+
+    true
+}
+
+const QUOTE_WITH_TAGS_REDUX_SELECT: &str = "SELECT
+        q.id,
+        q.content,
+        q.book_id,
+        b.title AS book_title,
+        b.author_id,
+        a.name AS author_name,
+        q.starred,
+        q.color,
+        q.created_at,
+        q.updated_at,
+        json_group_array(
+            json_object(
+                'id', t.id,
+                'name', t.name,
+                'color', t.color
+            )
+        ) AS tags_json";
+
+const QUOTE_WITH_TAGS_REDUX_FROM: &str = "
+    FROM quote q
+    JOIN book b ON q.book_id = b.id
+    JOIN author a ON b.author_id = a.id
+    LEFT JOIN quote_tag qt ON q.id = qt.quote_id
+    LEFT JOIN tag t ON qt.tag_id = t.id
+    WHERE 1 = 1";
+
+fn quote_with_tags_redux_from_row(row: &SqliteRow) -> QuoteWithTagsRedux {
+    let tags_json: String = row.get("tags_json");
+    let tags: Vec<Tag> = serde_json::from_str(&tags_json).unwrap_or_default();
+
+    QuoteWithTagsRedux {
+        id: row.get("id"),
+        content: row.get("content"),
+        book_id: row.get("book_id"),
+        book_title: row.get("book_title"),
+        author_id: row.get("author_id"),
+        author_name: row.get("author_name"),
+        starred: row.get("starred"),
+        color: row.get("color"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        tags,
+    }
+}
+
+/// Get the quotes matching a filter, sorted with the usual sort options.
+/// An invalid full text search matches no quotes.
+pub async fn query_quotes<'e, E>(
+    filter: &QuoteFilter,
     sort_by: Option<&str>,
     sort_order: Option<&str>,
+    limit: Option<i64>,
+    offset: Option<i64>,
     executor: E,
 ) -> Result<Vec<QuoteWithTagsRedux>, sqlx::Error>
 where
     E: Executor<'e, Database = Sqlite>,
 {
-    let (order_clause, sort_by_clause) = extract_order_clauses(sort_by, sort_order);
-
-    let sql = format!(
-        "SELECT 
-            q.id,
-            q.content,
-            q.book_id,
-            b.title AS book_title,
-            b.author_id,
-            a.name AS author_name,
-            q.starred,
-            q.created_at,
-            q.updated_at,
-            json_group_array(
-                json_object(
-                    'id', t.id,
-                    'name', t.name,
-                    'color', t.color
-                )
-            ) AS tags_json
-        FROM quote q
-        JOIN book b ON q.book_id = b.id
-        JOIN author a ON b.author_id = a.id
-        LEFT JOIN quote_tag qt ON q.id = qt.quote_id
-        LEFT JOIN tag t ON qt.tag_id = t.id
-        WHERE q.starred = 1
-          AND q.deleted_at IS NULL
-          AND b.deleted_at IS NULL
-          AND a.deleted_at IS NULL
-        GROUP BY q.id
-        ORDER BY q.{} {}",
-        sort_by_clause, order_clause
-    );
-
-    let rows = sqlx::query(&sql)
-        .fetch_all(executor)
-        .await?;
-
-    let quotes = rows
-        .iter()
-        .map(|row| {
-            let tags_json: String = row.get("tags_json");
-            let tags: Vec<Tag> = serde_json::from_str(&tags_json).unwrap_or_default();
-
-            QuoteWithTagsRedux {
-                id: row.get("id"),
-                content: row.get("content"),
-                book_id: row.get("book_id"),
-                book_title: row.get("book_title"),
-                author_id: row.get("author_id"),
-                author_name: row.get("author_name"),
-                starred: row.get("starred"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                tags,
-            }
-        })
-        .collect();
+    let (order_clause, sort_by_clause) = extract_order_clauses(sort_by, sort_order);
 
-    Ok(quotes)
+    let mut query = QueryBuilder::<Sqlite>::new(QUOTE_WITH_TAGS_REDUX_SELECT);
+    query.push(QUOTE_WITH_TAGS_REDUX_FROM);
+    if !push_quote_filter(&mut query, filter) {
+        return Ok(Vec::new());
+    }
+
+    query.push(format!(
+        " GROUP BY q.id ORDER BY {} {}, q.id {}",
+        quote_order_column(&sort_by_clause),
+        order_clause,
+        order_clause
+    ));
+    if limit.is_some() || offset.is_some() {
+        query
+            .push(" LIMIT ")
+            .push_bind(limit.unwrap_or(-1))
+            .push(" OFFSET ")
+            .push_bind(offset.unwrap_or(0));
+    }
+
+    let rows = query.build().fetch_all(executor).await?;
+
+    Ok(rows.iter().map(quote_with_tags_redux_from_row).collect())
+}
+
+/// Get a page of the quotes matching a filter, starting after `cursor`.
+pub async fn query_quotes_page(
+    filter: &QuoteFilter,
+    sort_by: Option<&str>,
+    sort_order: Option<&str>,
+    cursor: Option<&str>,
+    limit: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Page<QuoteWithTagsRedux>, sqlx::Error> {
+    let cursor = decode_cursor(cursor)?;
+    let key = SortKey::quote(sort_by, sort_order);
+
+    let mut count = QueryBuilder::<Sqlite>::new(
+        "SELECT COUNT(*)
+        FROM quote q
+        JOIN book b ON q.book_id = b.id
+        JOIN author a ON b.author_id = a.id
+        WHERE 1 = 1",
+    );
+    if !push_quote_filter(&mut count, filter) {
+        return Ok(Page::empty());
+    }
+    let total: i64 = count.build_query_scalar().fetch_one(&mut *conn).await?;
+
+    let mut query = QueryBuilder::<Sqlite>::new(QUOTE_WITH_TAGS_REDUX_SELECT);
+    query.push(format!(", {} AS sort_key", key.expr));
+    query.push(QUOTE_WITH_TAGS_REDUX_FROM);
+    push_quote_filter(&mut query, filter);
+    key.push_after(&mut query, cursor);
+    query.push(" GROUP BY q.id");
+    key.push_order(&mut query, limit);
+
+    let rows = query.build().fetch_all(&mut *conn).await?;
+
+    key.to_page(rows, limit, total, |row| Ok(quote_with_tags_redux_from_row(row)))
+}
+
+pub async fn get_starred_quotes<'e, E>(
+    sort_by: Option<&str>,
+    sort_order: Option<&str>,
+    executor: E,
+) -> Result<Vec<QuoteWithTagsRedux>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let filter = QuoteFilter {
+        starred: Some(true),
+        ..Default::default()
+    };
+    query_quotes(&filter, sort_by, sort_order, None, None, executor).await
+}
+
+pub async fn get_starred_quotes_page(
+    sort_by: Option<&str>,
+    sort_order: Option<&str>,
+    cursor: Option<&str>,
+    limit: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Page<QuoteWithTagsRedux>, sqlx::Error> {
+    let filter = QuoteFilter {
+        starred: Some(true),
+        ..Default::default()
+    };
+    query_quotes_page(&filter, sort_by, sort_order, cursor, limit, conn).await
 }
 
 /// Get book by original ID
@@ -895,6 +1877,26 @@ where
         .await
 }
 
+/// Get a book by its title and author, ignoring case.
+pub async fn get_book_by_title_and_author<'e, E>(
+    title: &str,
+    author_id: Option<&str>,
+    executor: E,
+) -> Result<Book, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Book>(
+        "SELECT * FROM book
+        WHERE title = ? COLLATE NOCASE AND author_id IS ? AND deleted_at IS NULL
+        LIMIT 1",
+    )
+    .bind(title)
+    .bind(author_id)
+    .fetch_one(executor)
+    .await
+}
+
 /// Get chapter by original ID
 pub async fn get_chapter_by_original_id<'e, E>(
     original_id: String,
@@ -953,8 +1955,9 @@ where
     .bind(book.publication_year.clone())
     .bind(book.created_at)
     .bind(book.id.clone())
-    .fetch_one(executor)
+    .fetch_all(executor)
     .await
+    .and_then(returned_row)
 }
 
 pub async fn get_tags<'e, E>(executor: E) -> Result<Vec<Tag>, sqlx::Error>
@@ -980,18 +1983,31 @@ where
     .await
 }
 
+/// Get a tag by its name, ignoring case.
+pub async fn get_tag_by_name<'e, E>(name: &str, executor: E) -> Result<Tag, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, Tag>("SELECT * FROM tag WHERE name = ? COLLATE NOCASE LIMIT 1")
+        .bind(name)
+        .fetch_one(executor)
+        .await
+}
+
 pub async fn insert_tag<'e, E>(tag: &Tag, executor: E) -> Result<Tag, sqlx::Error>
 where
     E: Executor<'e, Database = Sqlite>,
 {
     sqlx::query_as::<_, Tag>(
-        "INSERT INTO tag (id, name, color) VALUES (?, ?, ?) RETURNING *",
+        "INSERT INTO tag (id, name, color, import_batch_id) VALUES (?, ?, ?, ?) RETURNING *",
     )
     .bind(tag.id.clone())
     .bind(tag.name.clone())
     .bind(tag.color.clone())
-    .fetch_one(executor)
+    .bind(&tag.import_batch_id)
+    .fetch_all(executor)
     .await
+    .and_then(returned_row)
 }
 
 pub async fn insert_quote_tag<'e, E>(
@@ -1051,74 +2067,26 @@ pub async fn get_quotes_by_tag<'e, E>(
 where
     E: Executor<'e, Database = Sqlite>,
 {
-    let (order_clause, sort_by_clause) = extract_order_clauses(sort_by, sort_order);
-    
-    let sql = format!(
-        "
-        SELECT 
-            q.id,
-            q.content,
-            q.book_id,
-            b.title AS book_title,
-            b.author_id,
-            a.name AS author_name,
-            q.starred,
-            q.created_at,
-            q.updated_at,
-            json_group_array(
-                json_object(
-                    'id', t.id,
-                    'name', t.name,
-                    'color', t.color
-                )
-            ) AS tags_json
-        FROM quote q
-        JOIN book b ON q.book_id = b.id
-        JOIN author a ON b.author_id = a.id
-        LEFT JOIN quote_tag qt ON q.id = qt.quote_id
-        LEFT JOIN tag t ON qt.tag_id = t.id
-        WHERE q.id IN (
-            SELECT q2.id
-            FROM quote q2
-            JOIN quote_tag qt2 ON q2.id = qt2.quote_id
-            WHERE qt2.tag_id = ?
-        )
-        AND q.deleted_at IS NULL
-        AND b.deleted_at IS NULL
-        AND a.deleted_at IS NULL
-        GROUP BY q.id
-        ORDER BY q.{} {}
-        ",
-        sort_by_clause, order_clause
-    );
-
-    let rows = sqlx::query(&sql)
-        .bind(tag_id)
-        .fetch_all(executor)
-        .await?;
-
-    let quotes = rows
-        .iter()
-        .map(|row| {
-            let tags_json: String = row.get("tags_json");
-            let tags: Vec<Tag> = serde_json::from_str(&tags_json).unwrap_or_default();
-
-            QuoteWithTagsRedux {
-                id: row.get("id"),
-                content: row.get("content"),
-                book_id: row.get("book_id"),
-                book_title: row.get("book_title"),
-                author_id: row.get("author_id"),
-                author_name: row.get("author_name"),
-                starred: row.get("starred"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                tags,
-            }
-        })
-        .collect();
+    let filter = QuoteFilter {
+        tag_ids: vec![tag_id.to_string()],
+        ..Default::default()
+    };
+    query_quotes(&filter, sort_by, sort_order, None, None, executor).await
+}
 
-    Ok(quotes)
+pub async fn get_quotes_by_tag_page(
+    tag_id: &str,
+    sort_by: Option<&str>,
+    sort_order: Option<&str>,
+    cursor: Option<&str>,
+    limit: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Page<QuoteWithTagsRedux>, sqlx::Error> {
+    let filter = QuoteFilter {
+        tag_ids: vec![tag_id.to_string()],
+        ..Default::default()
+    };
+    query_quotes_page(&filter, sort_by, sort_order, cursor, limit, conn).await
 }
 
 pub async fn get_tags_by_book_id<'e, E>(book_id: &str, executor: E) -> Result<Vec<Tag>, sqlx::Error>
@@ -1136,3 +2104,361 @@ where
     .fetch_all(executor)
     .await
 }
+
+/// Max number of distinct queries kept in the search history.
+pub const SEARCH_HISTORY_LIMIT: i64 = 200;
+/// Searching the same query again within this window doesn't count as a new search,
+/// a single search from the UI runs several search commands.
+const SEARCH_HISTORY_DEDUP_SECONDS: i64 = 10;
+
+/// Record a search query, bumping its frequency if it was already searched.
+///
+/// Whitespace is collapsed before storing, empty queries are ignored. Once the
+/// history grows over `SEARCH_HISTORY_LIMIT` the oldest entries are dropped.
+pub async fn record_search(
+    query: &str,
+    conn: &mut SqliteConnection,
+) -> Result<Option<SearchHistory>, sqlx::Error> {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    if query.is_empty() {
+        return Ok(None);
+    }
+
+    let entry = sqlx::query_as::<_, SearchHistory>(
+        "INSERT INTO search_history (query, timestamp, frequency)
+        VALUES (?, strftime('%Y-%m-%d %H:%M:%f', 'now'), 1)
+        ON CONFLICT(query) DO UPDATE SET
+            frequency = frequency + (timestamp < strftime('%Y-%m-%d %H:%M:%f', 'now', ?)),
+            timestamp = excluded.timestamp
+        RETURNING *",
+    )
+    .bind(&query)
+    .bind(format!("-{} seconds", SEARCH_HISTORY_DEDUP_SECONDS))
+    .fetch_all(&mut *conn)
+    .await
+    .and_then(returned_row)?;
+
+    sqlx::query(
+        "DELETE FROM search_history
+        WHERE id NOT IN (
+            SELECT id FROM search_history ORDER BY timestamp DESC, id DESC LIMIT ?
+        )",
+    )
+    .bind(SEARCH_HISTORY_LIMIT)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Some(entry))
+}
+
+/// Get the most recent searches, newest first
+pub async fn get_recent_searches<'e, E>(
+    limit: i64,
+    executor: E,
+) -> Result<Vec<SearchHistory>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, SearchHistory>(
+        "SELECT * FROM search_history ORDER BY timestamp DESC, id DESC LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the most frequent searches, ties broken by the most recent
+pub async fn get_frequent_searches<'e, E>(
+    limit: i64,
+    executor: E,
+) -> Result<Vec<SearchHistory>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, SearchHistory>(
+        "SELECT * FROM search_history
+        ORDER BY frequency DESC, timestamp DESC, id DESC
+        LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn delete_search<'e, E>(search_id: i64, executor: E) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("DELETE FROM search_history WHERE id = ?")
+        .bind(search_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn clear_search_history<'e, E>(executor: E) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("DELETE FROM search_history")
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+fn smart_collection_from_row(row: &SqliteRow) -> Result<SmartCollection, sqlx::Error> {
+    let filter_json: String = row.get("filter");
+    let filter = serde_json::from_str(&filter_json).map_err(|e| sqlx::Error::ColumnDecode {
+        index: "filter".to_string(),
+        source: Box::new(e),
+    })?;
+
+    Ok(SmartCollection {
+        id: row.get("id"),
+        name: row.get("name"),
+        filter,
+        sort_by: row.get("sort_by"),
+        sort_order: row.get("sort_order"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    })
+}
+
+fn serialize_filter(filter: &QuoteFilter) -> Result<String, sqlx::Error> {
+    serde_json::to_string(filter).map_err(|e| sqlx::Error::Protocol(e.to_string()))
+}
+
+/// Get all the smart collections, sorted by name
+pub async fn get_smart_collections<'e, E>(executor: E) -> Result<Vec<SmartCollection>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("SELECT * FROM smart_collection ORDER BY name COLLATE NOCASE")
+        .fetch_all(executor)
+        .await?
+        .iter()
+        .map(smart_collection_from_row)
+        .collect()
+}
+
+pub async fn get_smart_collection<'e, E>(
+    collection_id: &str,
+    executor: E,
+) -> Result<SmartCollection, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let row = sqlx::query("SELECT * FROM smart_collection WHERE id = ?")
+        .bind(collection_id)
+        .fetch_one(executor)
+        .await?;
+
+    smart_collection_from_row(&row)
+}
+
+pub async fn insert_smart_collection<'e, E>(
+    name: &str,
+    filter: &QuoteFilter,
+    sort_by: Option<&str>,
+    sort_order: Option<&str>,
+    executor: E,
+) -> Result<SmartCollection, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let row = sqlx::query(
+        "INSERT INTO smart_collection (id, name, filter, sort_by, sort_order)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING *",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(serialize_filter(filter)?)
+    .bind(sort_by)
+    .bind(sort_order)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)?;
+
+    smart_collection_from_row(&row)
+}
+
+pub async fn update_smart_collection<'e, E>(
+    collection_id: &str,
+    name: &str,
+    filter: &QuoteFilter,
+    sort_by: Option<&str>,
+    sort_order: Option<&str>,
+    executor: E,
+) -> Result<SmartCollection, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let row = sqlx::query(
+        "UPDATE smart_collection
+        SET name = ?, filter = ?, sort_by = ?, sort_order = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        RETURNING *",
+    )
+    .bind(name)
+    .bind(serialize_filter(filter)?)
+    .bind(sort_by)
+    .bind(sort_order)
+    .bind(collection_id)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)?;
+
+    smart_collection_from_row(&row)
+}
+
+pub async fn delete_smart_collection<'e, E>(collection_id: &str, executor: E) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("DELETE FROM smart_collection WHERE id = ?")
+        .bind(collection_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+fn import_batch_from_row(row: &SqliteRow) -> Result<ImportBatch, sqlx::Error> {
+    let report_json: String = row.get("report");
+    let report = serde_json::from_str(&report_json).map_err(|e| sqlx::Error::ColumnDecode {
+        index: "report".to_string(),
+        source: Box::new(e),
+    })?;
+
+    Ok(ImportBatch {
+        id: row.get("id"),
+        source: row.get("source"),
+        path: row.get("path"),
+        file_hash: row.get("file_hash"),
+        report,
+        created_at: row.get("created_at"),
+    })
+}
+
+/// Get the past imports, most recent first
+pub async fn get_import_batches<'e, E>(executor: E) -> Result<Vec<ImportBatch>, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("SELECT * FROM import_batch ORDER BY created_at DESC, rowid DESC")
+        .fetch_all(executor)
+        .await?
+        .iter()
+        .map(import_batch_from_row)
+        .collect()
+}
+
+pub async fn get_import_batch<'e, E>(
+    batch_id: &str,
+    executor: E,
+) -> Result<ImportBatch, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let row = sqlx::query("SELECT * FROM import_batch WHERE id = ?")
+        .bind(batch_id)
+        .fetch_one(executor)
+        .await?;
+
+    import_batch_from_row(&row)
+}
+
+pub async fn insert_import_batch<'e, E>(
+    source: &str,
+    path: &str,
+    file_hash: Option<&str>,
+    report: &ImportReport,
+    executor: E,
+) -> Result<ImportBatch, sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let report_json =
+        serde_json::to_string(report).map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+
+    let row = sqlx::query(
+        "INSERT INTO import_batch (id, source, path, file_hash, report)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING *",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(source)
+    .bind(path)
+    .bind(file_hash)
+    .bind(report_json)
+    .fetch_all(executor)
+    .await
+    .and_then(returned_row)?;
+
+    import_batch_from_row(&row)
+}
+
+/// Save the report of the import `batch_id` once it is done
+pub async fn update_import_batch_report<'e, E>(
+    batch_id: &str,
+    report: &ImportReport,
+    executor: E,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let report_json =
+        serde_json::to_string(report).map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+
+    sqlx::query("UPDATE import_batch SET report = ? WHERE id = ?")
+        .bind(report_json)
+        .bind(batch_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Permanently delete the rows created by the import `batch_id`, and the batch itself.
+///
+/// Authors, books, chapters and tags of the batch are kept while other rows still use them,
+/// rows the import only updated are left as they are.
+pub async fn rollback_import_batch(
+    batch_id: &str,
+    conn: &mut SqliteConnection,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT id FROM import_batch WHERE id = ?")
+        .bind(batch_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    for sql in [
+        "DELETE FROM quote_tag WHERE quote_id IN (SELECT id FROM quote WHERE import_batch_id = ?1)",
+        "DELETE FROM note WHERE import_batch_id = ?1
+            OR quote_id IN (SELECT id FROM quote WHERE import_batch_id = ?1)",
+        "DELETE FROM quote WHERE import_batch_id = ?1",
+        "DELETE FROM bookmark WHERE import_batch_id = ?1",
+        "DELETE FROM tag WHERE import_batch_id = ?1
+            AND NOT EXISTS (SELECT 1 FROM quote_tag WHERE tag_id = tag.id)",
+        "DELETE FROM chapter WHERE import_batch_id = ?1
+            AND NOT EXISTS (SELECT 1 FROM quote WHERE chapter_id = chapter.id)
+            AND NOT EXISTS (SELECT 1 FROM bookmark WHERE chapter_id = chapter.id)",
+        "DELETE FROM book WHERE import_batch_id = ?1
+            AND NOT EXISTS (SELECT 1 FROM chapter WHERE book_id = book.id)
+            AND NOT EXISTS (SELECT 1 FROM quote WHERE book_id = book.id)
+            AND NOT EXISTS (SELECT 1 FROM note WHERE book_id = book.id)
+            AND NOT EXISTS (SELECT 1 FROM bookmark WHERE book_id = book.id)",
+        "DELETE FROM author WHERE import_batch_id = ?1
+            AND NOT EXISTS (SELECT 1 FROM book WHERE author_id = author.id)
+            AND NOT EXISTS (SELECT 1 FROM quote WHERE author_id = author.id)
+            AND NOT EXISTS (SELECT 1 FROM note WHERE author_id = author.id)",
+        "DELETE FROM import_batch WHERE id = ?1",
+    ] {
+        sqlx::query(sql).bind(batch_id).execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
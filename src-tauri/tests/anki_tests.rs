@@ -0,0 +1,143 @@
+use liture_notes_lib::anki::{self, AnkiNoteType};
+use liture_notes_lib::models::{Note, QuoteFilter, Tag};
+use liture_notes_lib::queries;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Row, SqlitePool};
+use std::fs;
+use std::io::Read;
+use uuid::Uuid;
+
+#[test]
+fn test_anki_guid_is_stable() {
+    let guid = anki::anki_guid("quote-1");
+    assert_eq!(guid, anki::anki_guid("quote-1"));
+    assert_ne!(guid, anki::anki_guid("quote-2"));
+    assert!(!guid.is_empty() && guid.len() <= 10);
+}
+
+#[test]
+fn test_cloze_text() {
+    assert_eq!(
+        anki::cloze_text("All we have to decide is what to do with the time"),
+        "All we have to {{c1::decide}} is what to do with the time"
+    );
+    assert_eq!(
+        anki::cloze_text("\"Fear\" cuts more than steel & <knives>"),
+        "&quot;Fear&quot; cuts more than steel &amp; &lt;{{c1::knives}}&gt;"
+    );
+    assert_eq!(anki::cloze_text("..."), "{{c1::...}}");
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_export_anki(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+
+    let author = queries::insert_author("Frank Herbert".to_string(), &mut *conn).await.unwrap();
+    let book = queries::insert_book_with_defaults(
+        "Dune".to_string(),
+        Some(author.id.clone()),
+        None,
+        &mut *conn,
+    )
+    .await
+    .unwrap();
+    let quote = queries::insert_quote_lite(
+        "Fear is the mind-killer.".to_string(),
+        Some(book.id.clone()),
+        Some(author.id.clone()),
+        Some(0),
+        &mut *conn,
+    )
+    .await
+    .unwrap();
+    queries::insert_quote_lite("".to_string(), Some(book.id.clone()), Some(author.id.clone()), Some(0), &mut *conn)
+        .await
+        .unwrap();
+
+    let tag = queries::insert_tag(
+        &Tag {
+            id: Uuid::new_v4().to_string(),
+            name: "litany against fear".to_string(),
+            color: None,
+            import_batch_id: None,
+        },
+        &mut *conn,
+    )
+    .await
+    .unwrap();
+    queries::insert_quote_tag(&quote.id, &tag.id, &mut *conn).await.unwrap();
+    queries::insert_note(
+        &Note {
+            id: Uuid::new_v4().to_string(),
+            content: Some("Bene Gesserit".to_string()),
+            quote_id: Some(quote.id.clone()),
+            book_id: Some(book.id.clone()),
+            author_id: Some(author.id.clone()),
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: chrono::Utc::now().naive_utc(),
+            deleted_at: None,
+            import_batch_id: None,
+        },
+        &mut *conn,
+    )
+    .await
+    .unwrap();
+
+    let dir = std::env::temp_dir().join(format!("liture-anki-test-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let package = dir.join("export.apkg");
+    let exported = anki::export_anki(&QuoteFilter::default(), AnkiNoteType::Quote, &package, &mut conn)
+        .await
+        .unwrap();
+    assert_eq!(exported, 1);
+
+    // Unpack the collection and read it back
+    let mut archive = zip::ZipArchive::new(fs::File::open(&package).unwrap()).unwrap();
+    let mut media = String::new();
+    archive.by_name("media").unwrap().read_to_string(&mut media).unwrap();
+    assert_eq!(media, "{}");
+    let mut collection = Vec::new();
+    archive.by_name("collection.anki2").unwrap().read_to_end(&mut collection).unwrap();
+    let collection_path = dir.join("collection.anki2");
+    fs::write(&collection_path, collection).unwrap();
+
+    let mut anki_conn = SqliteConnectOptions::new()
+        .filename(&collection_path)
+        .connect()
+        .await
+        .unwrap();
+
+    let note = sqlx::query("SELECT guid, tags, flds, sfld FROM notes")
+        .fetch_one(&mut anki_conn)
+        .await
+        .unwrap();
+    assert_eq!(note.get::<String, _>("guid"), anki::anki_guid(&quote.id));
+    assert_eq!(note.get::<String, _>("tags"), " litany_against_fear ");
+    assert_eq!(
+        note.get::<String, _>("flds"),
+        "Fear is the mind-killer.\x1fDune\x1fFrank Herbert\x1fBene Gesserit"
+    );
+    assert_eq!(note.get::<String, _>("sfld"), "Fear is the mind-killer.");
+
+    let decks: String = sqlx::query_scalar("SELECT decks FROM col")
+        .fetch_one(&mut anki_conn)
+        .await
+        .unwrap();
+    let decks: serde_json::Value = serde_json::from_str(&decks).unwrap();
+    let mut names: Vec<&str> = decks
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|d| d["name"].as_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["Default", "Frank Herbert", "Frank Herbert::Dune"]);
+
+    let card_deck: i64 = sqlx::query_scalar("SELECT did FROM cards")
+        .fetch_one(&mut anki_conn)
+        .await
+        .unwrap();
+    assert_eq!(decks[card_deck.to_string()]["name"], "Frank Herbert::Dune");
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,275 @@
+use liture_notes_lib::backup::{self, LibraryBackup, RestoreMode, BACKUP_VERSION};
+use liture_notes_lib::db;
+use liture_notes_lib::import;
+use liture_notes_lib::models::{Quote, QuoteFilter, Tag};
+use liture_notes_lib::queries;
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+/// Library with a book, two quotes one of them tagged and noted, a trashed quote and a search
+async fn init_db(conn: &mut SqliteConnection) -> Quote {
+    let author = queries::insert_author("Ursula K. Le Guin".to_string(), &mut *conn).await.unwrap();
+    let book = queries::insert_book_with_defaults(
+        "The Dispossessed".to_string(),
+        Some(author.id.clone()),
+        Some("book-1".to_string()),
+        &mut *conn,
+    )
+    .await
+    .unwrap();
+
+    let quote = queries::insert_quote_lite(
+        "You cannot buy the revolution.".to_string(),
+        Some(book.id.clone()),
+        Some(author.id.clone()),
+        Some(1),
+        &mut *conn,
+    )
+    .await
+    .unwrap();
+    sqlx::query("UPDATE quote SET original_id = 'quote-1' WHERE id = ?")
+        .bind(&quote.id)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    queries::insert_note_lite(
+        "Odo".to_string(),
+        Some(quote.id.clone()),
+        Some(book.id.clone()),
+        Some(author.id.clone()),
+        &mut *conn,
+    )
+    .await
+    .unwrap();
+    let tag = queries::insert_tag(
+        &Tag {
+            id: Uuid::new_v4().to_string(),
+            name: "Anarres".to_string(),
+            color: None,
+            import_batch_id: None,
+        },
+        &mut *conn,
+    )
+    .await
+    .unwrap();
+    queries::insert_quote_tag(&quote.id, &tag.id, &mut *conn).await.unwrap();
+
+    let trashed = queries::insert_quote_lite(
+        "To be whole is to be part.".to_string(),
+        Some(book.id),
+        Some(author.id),
+        Some(0),
+        &mut *conn,
+    )
+    .await
+    .unwrap();
+    queries::delete_quote(&trashed.id, &mut *conn).await.unwrap();
+
+    queries::record_search("revolution", &mut *conn).await.unwrap();
+
+    queries::get_quote_by_id(&quote.id, &mut *conn).await.unwrap()
+}
+
+async fn count(table: &str, conn: &mut SqliteConnection) -> i64 {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+        .fetch_one(conn)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_dump_library(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+    init_db(&mut conn).await;
+
+    let backup = backup::dump_library(&mut conn).await.unwrap();
+    assert_eq!(backup.version, BACKUP_VERSION);
+    assert_eq!(backup.authors.len(), 1);
+    assert_eq!(backup.books[0].original_id.as_deref(), Some("book-1"));
+    assert_eq!(backup.quotes.len(), 2);
+    assert_eq!(backup.quotes.iter().filter(|q| q.deleted_at.is_some()).count(), 1);
+    assert_eq!(backup.notes.len(), 1);
+    assert_eq!(backup.tags.len(), 1);
+    assert_eq!(backup.quote_tags.len(), 1);
+    assert_eq!(backup.search_history.len(), 1);
+
+    // Survives a round trip through JSON
+    let json = serde_json::to_string(&backup).unwrap();
+    let restored: LibraryBackup = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.quotes[0].content, backup.quotes[0].content);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_restore_library_replace(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+    let quote = init_db(&mut conn).await;
+    let backup = backup::dump_library(&mut conn).await.unwrap();
+
+    queries::update_quote_content(&quote.id, "Changed", &mut *conn).await.unwrap();
+    queries::insert_author("Someone else".to_string(), &mut *conn).await.unwrap();
+
+    let summary = backup::restore_library(backup.clone(), RestoreMode::Replace, &mut conn)
+        .await
+        .unwrap();
+    assert_eq!(summary.updated, 0);
+
+    let restored = backup::dump_library(&mut conn).await.unwrap();
+    assert_eq!(restored.authors.len(), 1);
+    assert_eq!(restored.quotes.len(), 2);
+    assert_eq!(restored.quote_tags.len(), 1);
+    let restored_quote = restored.quotes.iter().find(|q| q.id == quote.id).unwrap();
+    assert_eq!(restored_quote.content, quote.content);
+    assert_eq!(restored_quote.original_id.as_deref(), Some("quote-1"));
+    assert_eq!(restored.quotes.iter().filter(|q| q.deleted_at.is_some()).count(), 1);
+
+    // The search index follows the restored content
+    assert_eq!(queries::find_quotes("revolution", &mut *conn).await.unwrap().len(), 1);
+    assert!(queries::find_quotes("Changed", &mut *conn).await.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_restore_library_merge(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+    let quote = init_db(&mut conn).await;
+    let mut backup = backup::dump_library(&mut conn).await.unwrap();
+
+    // The same quote imported on another machine, with another id and newer content
+    let other_id = Uuid::new_v4().to_string();
+    let backup_quote = backup.quotes.iter_mut().find(|q| q.id == quote.id).unwrap();
+    backup_quote.id = other_id.clone();
+    backup_quote.content = Some("You cannot make the revolution.".to_string());
+    backup_quote.updated_at += chrono::Duration::hours(1);
+    for note in backup.notes.iter_mut() {
+        note.quote_id = Some(other_id.clone());
+    }
+    for quote_tag in backup.quote_tags.iter_mut() {
+        quote_tag.quote_id = other_id.clone();
+    }
+    // A tag with the same name and another id
+    backup.tags[0].id = Uuid::new_v4().to_string();
+    // An older version of the book is skipped
+    backup.books[0].title = "Old title".to_string();
+    backup.books[0].updated_at -= chrono::Duration::hours(1);
+
+    let summary = backup::restore_library(backup, RestoreMode::Merge, &mut conn)
+        .await
+        .unwrap();
+    assert_eq!(summary.updated, 1);
+    assert_eq!(summary.created, 0);
+
+    assert_eq!(count("quote", &mut conn).await, 2);
+    assert_eq!(count("tag", &mut conn).await, 1);
+    assert_eq!(count("quote_tag", &mut conn).await, 1);
+    assert_eq!(count("search_history", &mut conn).await, 1);
+
+    let merged = queries::get_quote_by_id(&quote.id, &mut *conn).await.unwrap();
+    assert_eq!(merged.content.as_deref(), Some("You cannot make the revolution."));
+    let book = queries::get_book_by_id(merged.book_id.unwrap(), &mut *conn).await.unwrap();
+    assert_eq!(book.title, "The Dispossessed");
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_restore_library_keeps_import_batches(pool: SqlitePool) {
+    let path = std::env::temp_dir().join(format!("liture-backup-{}.csv", Uuid::new_v4()));
+    std::fs::write(&path, "Highlight,Title,Author\nThe map is not the territory.,Science and Sanity,Korzybski\n")
+        .unwrap();
+    let report = import::import_into_library(import::ImportSource::Readwise, path.to_str().unwrap().into(), None, &pool)
+        .await
+        .unwrap();
+    assert_eq!(report.quotes.created, 1);
+    std::fs::remove_file(path).unwrap();
+
+    let mut conn = pool.acquire().await.unwrap();
+    let backup = backup::dump_library(&mut conn).await.unwrap();
+    assert_eq!(backup.import_batches.len(), 1);
+    let batch_id = backup.import_batches[0].id.clone();
+    assert_eq!(backup.quotes[0].import_batch_id.as_deref(), Some(batch_id.as_str()));
+    assert_eq!(backup.books[0].import_batch_id.as_deref(), Some(batch_id.as_str()));
+
+    // A version 1 backup has no import batches
+    let json = serde_json::to_value(&backup).unwrap();
+    let mut legacy = json.clone();
+    legacy.as_object_mut().unwrap().remove("import_batches");
+    legacy["version"] = 1.into();
+    let legacy: LibraryBackup = serde_json::from_value(legacy).unwrap();
+    assert!(legacy.import_batches.is_empty());
+
+    let json = serde_json::to_string(&backup).unwrap();
+    let restored: LibraryBackup = serde_json::from_str(&json).unwrap();
+    backup::restore_library(restored, RestoreMode::Replace, &mut conn).await.unwrap();
+    let batches = queries::get_import_batches(&mut *conn).await.unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].id, batch_id);
+    assert_eq!(batches[0].report, report);
+
+    // The restored batch can still be rolled back
+    queries::rollback_import_batch(&batch_id, &mut conn).await.unwrap();
+    assert_eq!(count("quote", &mut conn).await, 0);
+    assert_eq!(count("book", &mut conn).await, 0);
+    assert_eq!(count("import_batch", &mut conn).await, 0);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_restore_library_keeps_collections_and_settings(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+    init_db(&mut conn).await;
+    let filter = QuoteFilter {
+        starred: Some(true),
+        ..Default::default()
+    };
+    let collection = queries::insert_smart_collection("Starred", &filter, Some("date_created"), None, &mut *conn)
+        .await
+        .unwrap();
+    queries::set_setting(db::TRASH_RETENTION_DAYS_KEY, Some("7"), &mut *conn).await.unwrap();
+
+    let backup = backup::dump_library(&mut conn).await.unwrap();
+    assert_eq!(backup.smart_collections.len(), 1);
+    assert_eq!(backup.settings.len(), 1);
+
+    queries::delete_smart_collection(&collection.id, &mut *conn).await.unwrap();
+    queries::set_setting(db::TRASH_RETENTION_DAYS_KEY, Some("30"), &mut *conn).await.unwrap();
+
+    let json = serde_json::to_string(&backup).unwrap();
+    let restored: LibraryBackup = serde_json::from_str(&json).unwrap();
+    backup::restore_library(restored, RestoreMode::Replace, &mut conn).await.unwrap();
+    let collections = queries::get_smart_collections(&mut *conn).await.unwrap();
+    assert_eq!(collections.len(), 1);
+    assert_eq!(collections[0].id, collection.id);
+    assert_eq!(collections[0].filter.starred, Some(true));
+    assert_eq!(collections[0].sort_by.as_deref(), Some("date_created"));
+    let retention = queries::get_setting(db::TRASH_RETENTION_DAYS_KEY, &mut *conn).await.unwrap();
+    assert_eq!(retention.as_deref(), Some("7"));
+
+    // A merge keeps the settings of the library
+    queries::set_setting(db::TRASH_RETENTION_DAYS_KEY, Some("30"), &mut *conn).await.unwrap();
+    let summary = backup::restore_library(backup.clone(), RestoreMode::Merge, &mut conn).await.unwrap();
+    assert_eq!(summary.created, 0);
+    assert_eq!(count("smart_collection", &mut conn).await, 1);
+    let retention = queries::get_setting(db::TRASH_RETENTION_DAYS_KEY, &mut *conn).await.unwrap();
+    assert_eq!(retention.as_deref(), Some("30"));
+
+    // A version 2 backup has neither, replacing the library keeps them
+    let mut legacy = serde_json::to_value(&backup).unwrap();
+    legacy.as_object_mut().unwrap().remove("smart_collections");
+    legacy.as_object_mut().unwrap().remove("settings");
+    legacy["version"] = 2.into();
+    let legacy: LibraryBackup = serde_json::from_value(legacy).unwrap();
+    backup::restore_library(legacy, RestoreMode::Replace, &mut conn).await.unwrap();
+    assert_eq!(count("smart_collection", &mut conn).await, 1);
+    assert_eq!(count("setting", &mut conn).await, 1);
+}
+
+#[test]
+fn test_read_backup_newer_version() {
+    let path = std::env::temp_dir().join(format!("liture-backup-{}.json", Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        format!(r#"{{"version": {}, "created_at": "2026-01-01T00:00:00"}}"#, BACKUP_VERSION + 1),
+    )
+    .unwrap();
+
+    let result = backup::read_backup(&path);
+    assert!(matches!(result, Err(backup::BackupError::UnsupportedVersion(_))));
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,139 @@
+use liture_notes_lib::db::{self, DbError};
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const MIGRATIONS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../migrations");
+const INIT_MIGRATION: &str = "20250319132742_init";
+
+/// Create an empty temporary directory for a test
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("liture-db-test-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+async fn connect(db_path: &Path) -> SqlitePool {
+    SqlitePool::connect_with(
+        SqliteConnectOptions::new()
+            .filename(db_path)
+            .create_if_missing(true),
+    )
+    .await
+    .unwrap()
+}
+
+/// Build a migrator with the init migration and optionally an extra one
+async fn init_migrator(dir: &Path, extra: Option<(&str, &str)>) -> Migrator {
+    let migrations = dir.join("migrations");
+    fs::create_dir_all(&migrations).unwrap();
+    for suffix in ["up", "down"] {
+        let name = format!("{}.{}.sql", INIT_MIGRATION, suffix);
+        fs::copy(Path::new(MIGRATIONS_DIR).join(&name), migrations.join(&name)).unwrap();
+    }
+    if let Some((name, sql)) = extra {
+        fs::write(migrations.join(format!("{}.up.sql", name)), sql).unwrap();
+        fs::write(migrations.join(format!("{}.down.sql", name)), "").unwrap();
+    }
+    Migrator::new(migrations.as_path()).await.unwrap()
+}
+
+async fn table_exists(pool: &SqlitePool, table: &str) -> bool {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)")
+        .bind(table)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+fn backups(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries.map(|e| e.unwrap().path()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn test_run_migrations_new_database() {
+    let dir = temp_dir();
+    let db_path = dir.join("main.db");
+    let backup_dir = dir.join("backups");
+
+    let pool = db::run_migrations(connect(&db_path).await, &db::MIGRATOR, &db_path, None)
+        .await
+        .unwrap();
+    assert!(table_exists(&pool, "quote").await);
+    assert!(table_exists(&pool, "search_history").await);
+
+    // Nothing pending, so no backup is taken
+    let pool = db::run_migrations(pool, &db::MIGRATOR, &db_path, Some(&backup_dir))
+        .await
+        .unwrap();
+    assert!(backups(&backup_dir).is_empty());
+
+    pool.close().await;
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_run_migrations_existing_database() {
+    let dir = temp_dir();
+    let db_path = dir.join("main.db");
+    let backup_dir = dir.join("backups");
+
+    // Database created before the search history migration existed
+    let pool = connect(&db_path).await;
+    init_migrator(&dir, None).await.run(&pool).await.unwrap();
+    assert!(!table_exists(&pool, "search_history").await);
+
+    let pool = db::run_migrations(pool, &db::MIGRATOR, &db_path, Some(&backup_dir))
+        .await
+        .unwrap();
+    assert!(table_exists(&pool, "search_history").await);
+    assert_eq!(backups(&backup_dir).len(), 1);
+
+    pool.close().await;
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_run_migrations_failure_restores_backup() {
+    let dir = temp_dir();
+    let db_path = dir.join("main.db");
+    let backup_dir = dir.join("backups");
+
+    let pool = connect(&db_path).await;
+    init_migrator(&dir, None).await.run(&pool).await.unwrap();
+    // The initial schema has no import_batch_id column yet, so no insert_author
+    sqlx::query("INSERT INTO author (id, name) VALUES (?, ?)")
+        .bind(Uuid::new_v4().to_string())
+        .bind("George R.R. Martin")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let broken_dir = temp_dir();
+    let broken = init_migrator(
+        &broken_dir,
+        Some((
+            "20990101000000_broken",
+            "CREATE TABLE partial (id TEXT); INSERT INTO missing_table VALUES (1);",
+        )),
+    )
+    .await;
+
+    let result = db::run_migrations(pool, &broken, &db_path, Some(&backup_dir)).await;
+    assert!(matches!(result, Err(DbError::MigrationRestored { .. })));
+    assert_eq!(result.unwrap_err().kind(), "migration_restored");
+
+    let pool = connect(&db_path).await;
+    assert!(!table_exists(&pool, "partial").await);
+    let authors = liture_notes_lib::queries::get_authors(&pool).await.unwrap();
+    assert_eq!(authors.len(), 1);
+
+    pool.close().await;
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_dir_all(broken_dir).unwrap();
+}
@@ -0,0 +1,188 @@
+use chrono::{NaiveDateTime, Utc};
+use liture_notes_lib::export::{self, BookExport};
+use liture_notes_lib::models::{Author, Book, Chapter, Note, QuoteWithTags, Tag};
+use liture_notes_lib::queries;
+use sqlx::SqlitePool;
+use std::fs;
+use uuid::Uuid;
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+fn chapter(id: &str, title: &str, volume_index: i64) -> Chapter {
+    Chapter {
+        id: id.to_string(),
+        book_id: Some("book".to_string()),
+        title: title.to_string(),
+        created_at: now(),
+        updated_at: now(),
+        deleted_at: None,
+        original_id: None,
+        volume_index,
+        import_batch_id: None,
+    }
+}
+
+fn quote(id: &str, content: &str, chapter_id: Option<&str>, progress: Option<f64>, starred: bool) -> QuoteWithTags {
+    QuoteWithTags {
+        id: id.to_string(),
+        book_id: Some("book".to_string()),
+        author_id: Some("author".to_string()),
+        chapter_id: chapter_id.map(|c| c.to_string()),
+        chapter_progress: progress,
+        content: Some(content.to_string()),
+        starred: Some(starred as i64),
+        created_at: now(),
+        updated_at: now(),
+        imported_at: None,
+        deleted_at: None,
+        original_id: None,
+        color: None,
+        page_start: None,
+        page_end: None,
+        location_start: None,
+        location_end: None,
+        tags: Vec::new(),
+    }
+}
+
+fn sample_book() -> BookExport {
+    let mut tagged = quote("q3", "Winter is coming.", Some("c1"), Some(0.5), false);
+    tagged.tags = vec![Tag {
+        id: "t1".to_string(),
+        name: "Stark".to_string(),
+        color: None,
+        import_batch_id: None,
+    }];
+
+    BookExport {
+        book: Book {
+            id: "book".to_string(),
+            author_id: Some("author".to_string()),
+            title: "A Game of \"Thrones\"".to_string(),
+            publication_year: Some("1996".to_string()),
+            created_at: now(),
+            updated_at: now(),
+            deleted_at: None,
+            original_id: None,
+            import_batch_id: None,
+        },
+        author: Some(Author {
+            id: "author".to_string(),
+            name: "George R.R. Martin".to_string(),
+            created_at: now(),
+            updated_at: now(),
+            deleted_at: None,
+            original_id: None,
+            import_batch_id: None,
+        }),
+        // Out of order on purpose
+        chapters: vec![chapter("c2", "Chapter Two", 2), chapter("c1", "Chapter One", 1)],
+        quotes: vec![
+            quote("q1", "When you play the game of thrones,\nyou win or you die.", Some("c2"), Some(0.1), true),
+            tagged,
+            quote("q2", "The night is dark.", Some("c1"), Some(0.2), false),
+            quote("q4", "Prologue quote.", None, None, false),
+        ],
+        notes: vec![note("n1", Some("q1"), "Cersei to Ned"), note("n2", None, "Reread the prologue")],
+    }
+}
+
+fn note(id: &str, quote_id: Option<&str>, content: &str) -> Note {
+    Note {
+        id: id.to_string(),
+        book_id: Some("book".to_string()),
+        author_id: Some("author".to_string()),
+        quote_id: quote_id.map(|q| q.to_string()),
+        content: Some(content.to_string()),
+        created_at: now(),
+        updated_at: now(),
+        deleted_at: None,
+        import_batch_id: None,
+    }
+}
+
+#[test]
+fn test_book_to_markdown() {
+    let md = export::book_to_markdown(&sample_book());
+
+    assert!(md.starts_with(
+        "---\n\
+        title: \"A Game of \\\"Thrones\\\"\"\n\
+        author: \"George R.R. Martin\"\n\
+        publication_year: \"1996\"\n\
+        tags:\n  - \"Stark\"\n\
+        quotes: 4\n\
+        starred: 1\n\
+        notes: 2\n\
+        ---\n"
+    ));
+
+    // Quotes without chapter first, then chapters by volume index and progress
+    let positions: Vec<usize> = [
+        "> Prologue quote.",
+        "## Chapter One",
+        "> The night is dark.",
+        "> Winter is coming.",
+        "## Chapter Two",
+        "> ⭐ When you play the game of thrones,\n> you win or you die.\n>\n> > Cersei to Ned",
+        "## Notes\n\n> Reread the prologue",
+    ]
+    .iter()
+    .map(|s| md.find(s).unwrap_or_else(|| panic!("{:?} not found in\n{}", s, md)))
+    .collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn test_markdown_file_name() {
+    let export = sample_book();
+    assert_eq!(
+        export::markdown_file_name(&export.book, export.author.as_ref()),
+        "George R.R. Martin - A Game of _Thrones_.md"
+    );
+
+    let mut book = export.book.clone();
+    book.title = "../".to_string();
+    assert_eq!(export::markdown_file_name(&book, None), "_.md");
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_export_markdown(pool: SqlitePool) {
+    let author = queries::insert_author("George R.R. Martin".to_string(), &pool).await.unwrap();
+    for _ in 0..2 {
+        let book = queries::insert_book_with_defaults(
+            "A Dance with Dragons".to_string(),
+            Some(author.id.clone()),
+            None,
+            &pool,
+        )
+        .await
+        .unwrap();
+        queries::insert_quote_lite("A reader lives a thousand lives.".to_string(), Some(book.id), Some(author.id.clone()), Some(1), &pool)
+            .await
+            .unwrap();
+    }
+
+    let dir = std::env::temp_dir().join(format!("liture-export-test-{}", Uuid::new_v4()));
+    let mut conn = pool.acquire().await.unwrap();
+    let paths = export::export_markdown(None, &dir, &mut conn).await.unwrap();
+
+    let mut names: Vec<_> = paths
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "George R.R. Martin - A Dance with Dragons (2).md",
+            "George R.R. Martin - A Dance with Dragons.md",
+        ]
+    );
+    let md = fs::read_to_string(&paths[0]).unwrap();
+    assert!(md.contains("> ⭐ A reader lives a thousand lives."));
+
+    fs::remove_dir_all(dir).unwrap();
+}
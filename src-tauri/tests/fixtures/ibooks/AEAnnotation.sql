@@ -0,0 +1,23 @@
+-- Annotations of Apple Books, dates are seconds since 2001-01-01 (Core Data).
+CREATE TABLE ZAEANNOTATION (
+    Z_PK INTEGER PRIMARY KEY,
+    ZANNOTATIONUUID VARCHAR,
+    ZANNOTATIONASSETID VARCHAR,
+    ZANNOTATIONSELECTEDTEXT VARCHAR,
+    ZFUTUREPROOFING5 VARCHAR,
+    ZANNOTATIONCREATIONDATE TIMESTAMP,
+    ZANNOTATIONMODIFICATIONDATE TIMESTAMP,
+    ZANNOTATIONNOTE VARCHAR,
+    ZANNOTATIONSTYLE INTEGER,
+    ZANNOTATIONDELETED INTEGER
+);
+
+INSERT INTO ZAEANNOTATION VALUES
+    (1, 'an-1', 'ASSET-1', 'It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife.',
+        NULL, 700000000.0, 700000600.0, 'Opening line', 3, 0),
+    (2, 'an-2', 'ASSET-1', NULL, 'I could easily forgive his pride, if he had not mortified mine.',
+        700100000.0, NULL, NULL, 0, 0),
+    -- Deleted on the device
+    (3, 'an-3', 'ASSET-1', 'Vanity and pride are different things.', NULL, 700200000.0, NULL, NULL, 1, 1),
+    -- Bookmark, without text
+    (4, 'an-4', 'ASSET-1', NULL, NULL, 700300000.0, NULL, NULL, 0, 0);
@@ -0,0 +1,12 @@
+-- Books of Apple Books.
+CREATE TABLE ZBKLIBRARYASSET (
+    Z_PK INTEGER PRIMARY KEY,
+    ZASSETID VARCHAR,
+    ZAUTHOR VARCHAR,
+    ZTITLE VARCHAR
+);
+
+INSERT INTO ZBKLIBRARYASSET VALUES
+    (1, 'ASSET-1', 'Jane Austen', 'Pride and Prejudice'),
+    -- Without annotations
+    (2, 'ASSET-2', 'Herman Melville', 'Moby Dick');
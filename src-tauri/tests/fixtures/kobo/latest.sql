@@ -0,0 +1,49 @@
+-- Recent firmware: highlight colors, dog-ears and hidden bookmarks,
+-- removed on the device until synced.
+CREATE TABLE DbVersion (version INTEGER);
+INSERT INTO DbVersion VALUES (190);
+
+CREATE TABLE content (
+    ContentID TEXT NOT NULL PRIMARY KEY,
+    ContentType TEXT NOT NULL,
+    BookID TEXT,
+    BookTitle TEXT,
+    Title TEXT,
+    Attribution TEXT,
+    VolumeIndex INTEGER
+);
+
+CREATE TABLE Bookmark (
+    BookmarkID TEXT NOT NULL PRIMARY KEY,
+    VolumeID TEXT NOT NULL,
+    ContentID TEXT NOT NULL,
+    StartContainerPath TEXT,
+    EndContainerPath TEXT,
+    Text TEXT,
+    Annotation TEXT,
+    ExtraAnnotationData BLOB,
+    DateCreated TEXT,
+    DateModified TEXT,
+    ChapterProgress REAL,
+    Hidden TEXT DEFAULT 'false',
+    Type TEXT,
+    Color INTEGER
+);
+
+INSERT INTO content (ContentID, ContentType, BookID, BookTitle, Title, Attribution, VolumeIndex) VALUES
+    ('file:///mnt/onboard/dune.epub', 6, NULL, NULL, 'Dune', 'Frank Herbert', 0),
+    ('file:///mnt/onboard/dune.epub#(1)OEBPS/ch1.xhtml', 9, 'file:///mnt/onboard/dune.epub', 'Dune', 'Book One', NULL, 1),
+    ('file:///mnt/onboard/dune.epub#(2)OEBPS/ch2.xhtml', 9, 'file:///mnt/onboard/dune.epub', 'Dune', 'Book Two', NULL, 2);
+
+INSERT INTO Bookmark
+    (BookmarkID, VolumeID, ContentID, Text, Annotation, DateCreated, DateModified, ChapterProgress, Hidden, Type, Color)
+VALUES
+    ('bm-1', 'file:///mnt/onboard/dune.epub', 'OEBPS/ch1.xhtml#point(/1/4/2:0)',
+        'Fear is the mind-killer.', NULL, '2024-06-01T08:00:00Z', NULL, 0.25, 'false', 'highlight', 0),
+    ('bm-2', 'file:///mnt/onboard/dune.epub', 'OEBPS/ch2.xhtml#point(/1/4/8:0)',
+        'The mystery of life isn''t a problem to solve, but a reality to experience.', 'Favourite',
+        '2024-06-02T08:00:00Z', '2024-06-03T08:00:00Z', 0.5, 'false', 'note', 2),
+    ('bm-3', 'file:///mnt/onboard/dune.epub', 'OEBPS/ch2.xhtml#point(/1/4/9:0)',
+        'Removed on the device', NULL, '2024-06-04T08:00:00Z', NULL, 0.6, 'true', 'highlight', 1),
+    ('bm-4', 'file:///mnt/onboard/dune.epub', 'OEBPS/ch2.xhtml#point(/1/4/12:0)',
+        NULL, NULL, '2024-06-05T08:00:00Z', NULL, 0.75, 'false', 'dogear', NULL);
@@ -0,0 +1,31 @@
+-- Firmware before DbVersion 174: no version table, bookmarks without type,
+-- progress or modification date.
+CREATE TABLE content (
+    ContentID TEXT NOT NULL PRIMARY KEY,
+    ContentType TEXT NOT NULL,
+    BookID TEXT,
+    BookTitle TEXT,
+    Title TEXT,
+    Attribution TEXT,
+    VolumeIndex INTEGER
+);
+
+CREATE TABLE Bookmark (
+    BookmarkID TEXT NOT NULL PRIMARY KEY,
+    VolumeID TEXT NOT NULL,
+    ContentID TEXT NOT NULL,
+    Text TEXT,
+    Annotation TEXT,
+    DateCreated TEXT
+);
+
+INSERT INTO content (ContentID, ContentType, BookID, BookTitle, Title, Attribution, VolumeIndex) VALUES
+    ('file:///mnt/onboard/dune.epub', 6, NULL, NULL, 'Dune', 'Frank Herbert', 0),
+    ('file:///mnt/onboard/dune.epub#(1)OEBPS/ch1.xhtml', 9, 'file:///mnt/onboard/dune.epub', 'Dune', 'Book One', NULL, 1),
+    ('file:///mnt/onboard/dune.epub#(2)OEBPS/ch2.xhtml', 9, 'file:///mnt/onboard/dune.epub', 'Dune', 'Book Two', NULL, 2);
+
+INSERT INTO Bookmark (BookmarkID, VolumeID, ContentID, Text, Annotation, DateCreated) VALUES
+    ('bm-1', 'file:///mnt/onboard/dune.epub', 'file:///mnt/onboard/dune.epub#(1)OEBPS/ch1.xhtml',
+        'Fear is the mind-killer.', NULL, '2012-05-01T10:00:00.000'),
+    ('bm-2', 'file:///mnt/onboard/dune.epub', 'file:///mnt/onboard/dune.epub#(2)OEBPS/ch2.xhtml',
+        'The mystery of life isn''t a problem to solve, but a reality to experience.', 'Favourite', '2012-05-02T10:00:00.000');
@@ -0,0 +1,37 @@
+-- DbVersion 174: bookmarks point to the content row of their chapter.
+CREATE TABLE DbVersion (version INTEGER);
+INSERT INTO DbVersion VALUES (174);
+
+CREATE TABLE content (
+    ContentID TEXT NOT NULL PRIMARY KEY,
+    ContentType TEXT NOT NULL,
+    BookID TEXT,
+    BookTitle TEXT,
+    Title TEXT,
+    Attribution TEXT,
+    VolumeIndex INTEGER
+);
+
+CREATE TABLE Bookmark (
+    BookmarkID TEXT NOT NULL PRIMARY KEY,
+    VolumeID TEXT NOT NULL,
+    ContentID TEXT NOT NULL,
+    Text TEXT,
+    Annotation TEXT,
+    DateCreated TEXT,
+    DateModified TEXT,
+    ChapterProgress REAL,
+    Type TEXT
+);
+
+INSERT INTO content (ContentID, ContentType, BookID, BookTitle, Title, Attribution, VolumeIndex) VALUES
+    ('file:///mnt/onboard/dune.epub', 6, NULL, NULL, 'Dune', 'Frank Herbert', 0),
+    ('file:///mnt/onboard/dune.epub#(1)OEBPS/ch1.xhtml', 9, 'file:///mnt/onboard/dune.epub', 'Dune', 'Book One', NULL, 1),
+    ('file:///mnt/onboard/dune.epub#(2)OEBPS/ch2.xhtml', 9, 'file:///mnt/onboard/dune.epub', 'Dune', 'Book Two', NULL, 2);
+
+INSERT INTO Bookmark VALUES
+    ('bm-1', 'file:///mnt/onboard/dune.epub', 'file:///mnt/onboard/dune.epub#(1)OEBPS/ch1.xhtml',
+        'Fear is the mind-killer.', NULL, '2020-11-22T10:11:42.000', NULL, 0.25, 'highlight'),
+    ('bm-2', 'file:///mnt/onboard/dune.epub', 'file:///mnt/onboard/dune.epub#(2)OEBPS/ch2.xhtml',
+        'The mystery of life isn''t a problem to solve, but a reality to experience.', 'Favourite',
+        '2020-11-23T10:11:42.000', '2020-11-24T10:11:42.000', 0.5, 'note');
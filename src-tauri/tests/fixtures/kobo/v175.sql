@@ -0,0 +1,37 @@
+-- DbVersion 175: bookmarks point to a part of the chapter, not to a content row.
+CREATE TABLE DbVersion (version INTEGER);
+INSERT INTO DbVersion VALUES (175);
+
+CREATE TABLE content (
+    ContentID TEXT NOT NULL PRIMARY KEY,
+    ContentType TEXT NOT NULL,
+    BookID TEXT,
+    BookTitle TEXT,
+    Title TEXT,
+    Attribution TEXT,
+    VolumeIndex INTEGER
+);
+
+CREATE TABLE Bookmark (
+    BookmarkID TEXT NOT NULL PRIMARY KEY,
+    VolumeID TEXT NOT NULL,
+    ContentID TEXT NOT NULL,
+    Text TEXT,
+    Annotation TEXT,
+    DateCreated TEXT,
+    DateModified TEXT,
+    ChapterProgress REAL,
+    Type TEXT
+);
+
+INSERT INTO content (ContentID, ContentType, BookID, BookTitle, Title, Attribution, VolumeIndex) VALUES
+    ('file:///mnt/onboard/dune.epub', 6, NULL, NULL, 'Dune', 'Frank Herbert', 0),
+    ('file:///mnt/onboard/dune.epub#(1)OEBPS/ch1.xhtml', 9, 'file:///mnt/onboard/dune.epub', 'Dune', 'Book One', NULL, 1),
+    ('file:///mnt/onboard/dune.epub#(2)OEBPS/ch2.xhtml', 9, 'file:///mnt/onboard/dune.epub', 'Dune', 'Book Two', NULL, 2);
+
+INSERT INTO Bookmark VALUES
+    ('bm-1', 'file:///mnt/onboard/dune.epub', 'OEBPS/ch1.xhtml#point(/1/4/2:0)',
+        'Fear is the mind-killer.', NULL, '2023-01-15T14:23:00Z', NULL, 0.25, 'highlight'),
+    ('bm-2', 'file:///mnt/onboard/dune.epub', 'OEBPS/ch2.xhtml#point(/1/4/8:0)',
+        'The mystery of life isn''t a problem to solve, but a reality to experience.', 'Favourite',
+        '2023-01-15T14:23:00Z', '2023-01-16T09:00:00Z', 0.5, 'note');
@@ -0,0 +1,42 @@
+-- we can read Lua syntax here!
+return {
+    ["annotations"] = {
+        [1] = {
+            ["chapter"] = "Book One: Dune",
+            ["color"] = "yellow",
+            ["datetime"] = "2024-01-15 10:23:45",
+            ["drawer"] = "lighten",
+            ["page"] = "/body/DocFragment[12]/body/p[3]/text().0",
+            ["pageno"] = 42,
+            ["pos0"] = "/body/DocFragment[12]/body/p[3]/text().0",
+            ["pos1"] = "/body/DocFragment[12]/body/p[3]/text().24",
+            ["text"] = "I must not fear.\nFear is the mind-killer.",
+        },
+        [2] = {
+            ["chapter"] = "Book One: Dune",
+            ["datetime"] = "2024-01-16 08:00:00",
+            ["datetime_updated"] = "2024-01-17 09:30:00",
+            ["note"] = "The \"litany\" against fear",
+            ["page"] = "/body/DocFragment[14]/body/p[1]/text().0",
+            ["pageno"] = 57,
+            ["pos0"] = "/body/DocFragment[14]/body/p[1]/text().0",
+            ["pos1"] = "/body/DocFragment[14]/body/p[1]/text().31",
+            ["text"] = "The mystery of life isn't a problem to solve.",
+        },
+        [3] = {
+            ["chapter"] = "Book Two: Muad'Dib",
+            ["datetime"] = "2024-01-18 21:10:00",
+            ["page"] = "/body/DocFragment[30]/body/p[9]/text().0",
+            ["pageno"] = 210,
+        },
+    },
+    ["doc_path"] = "/mnt/onboard/Books/Dune.epub",
+    ["doc_props"] = {
+        ["authors"] = "Frank Herbert",
+        ["language"] = "en",
+        ["title"] = "Dune",
+    },
+    ["summary"] = {
+        ["status"] = "reading",
+    },
+}
@@ -0,0 +1,27 @@
+-- we can read Lua syntax here!
+return {
+    ["annotations"] = {
+        [1] = {
+            ["datetime"] = "2024-02-01 09:00:00",
+            ["drawer"] = "lighten",
+            ["page"] = 5,
+            ["pos0"] = { ["page"] = 5, ["x"] = 72, ["y"] = 110.5 },
+            ["pos1"] = { ["page"] = 5, ["x"] = 480, ["y"] = 124 },
+            ["text"] = "Some things are in our control and others not.",
+        },
+        [2] = {
+            ["datetime"] = "2024-02-01 09:05:00",
+            ["drawer"] = "lighten",
+            ["note"] = "The dichotomy of control",
+            ["page"] = 5,
+            ["pos0"] = { ["page"] = 5, ["x"] = 72, ["y"] = 402 },
+            ["pos1"] = { ["page"] = 5, ["x"] = 301, ["y"] = 416 },
+            ["text"] = "Men are disturbed not by things, but by the views which they take of things.",
+        },
+    },
+    ["doc_path"] = "/mnt/onboard/Books/Enchiridion.pdf",
+    ["doc_props"] = {
+        ["authors"] = "Epictetus",
+        ["title"] = "Enchiridion",
+    },
+}
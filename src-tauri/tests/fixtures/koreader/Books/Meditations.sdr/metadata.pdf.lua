@@ -0,0 +1,42 @@
+-- we can read Lua syntax here!
+return {
+    ["bookmarks"] = {
+        [1] = {
+            ["datetime"] = "2019-05-02 18:04:11",
+            ["highlighted"] = true,
+            ["notes"] = "You have power over your mind - not outside events.",
+            ["page"] = 12,
+            ["pos0"] = { ["page"] = 12, ["x"] = 10, ["y"] = 20 },
+            ["text"] = "Realize this, and you will find strength.",
+        },
+        [2] = {
+            ["datetime"] = "2019-05-03 07:45:00",
+            ["highlighted"] = true,
+            ["notes"] = "The happiness of your life depends upon the quality of your thoughts.",
+            ["page"] = 30,
+            ["text"] = "Page 30 The happiness of your life @ 2019-05-03 07:45:00",
+        },
+    },
+    ["doc_props"] = {
+        ["authors"] = "Marcus Aurelius\nGregory Hays",
+        ["title"] = "",
+    },
+    ["highlight"] = {
+        [12] = {
+            [1] = {
+                ["chapter"] = "Book Two",
+                ["datetime"] = "2019-05-02 18:04:11",
+                ["drawer"] = "lighten",
+                ["text"] = "You have power over your mind - not outside events.",
+            },
+        },
+        [30] = {
+            [1] = {
+                ["chapter"] = "Book Eight",
+                ["datetime"] = "2019-05-03 07:45:00",
+                ["drawer"] = "underscore",
+                ["text"] = "The happiness of your life depends upon the quality of your thoughts.",
+            },
+        },
+    },
+}
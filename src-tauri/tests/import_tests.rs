@@ -0,0 +1,1191 @@
+use liture_notes_lib::export;
+use liture_notes_lib::import;
+use liture_notes_lib::lua::{self, LuaValue};
+use liture_notes_lib::models::{ImportCounts, ImportIssueLevel, ImportReport, QuoteFilter};
+use liture_notes_lib::queries;
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+const READWISE_CSV: &str = "\
+Highlight,Book Title,Book Author,Amazon Book ID,Note,Color,Tags,Location Type,Location,Highlighted at,Document tags
+\"We suffer more often in imagination than in reality.\",Letters from a Stoic,Seneca,B00XYZ,.stoic Read again,yellow,\"favorite, stoic\",location,120,2023-01-15 14:23:00+00:00,
+\"Luck is what happens when preparation meets opportunity.\",Letters from a Stoic,Seneca,B00XYZ,,yellow,,location,80,2023-01-14 09:00:00+00:00,
+,Letters from a Stoic,Seneca,B00XYZ,,,,,,,
+";
+
+const KINDLE_CLIPPINGS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/kindle/My Clippings.txt");
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("liture-import-test-{}-{}", Uuid::new_v4(), name))
+}
+
+#[test]
+fn test_split_readwise_note() {
+    assert_eq!(
+        import::split_readwise_note(".stoic .favorite Read again"),
+        (vec!["stoic".to_string(), "favorite".to_string()], Some("Read again".to_string()))
+    );
+    assert_eq!(import::split_readwise_note(".stoic"), (vec!["stoic".to_string()], None));
+    assert_eq!(
+        import::split_readwise_note("... and then"),
+        (Vec::new(), Some("... and then".to_string()))
+    );
+}
+
+#[test]
+fn test_read_readwise_csv() {
+    let mut report = ImportReport::default();
+    let highlights = import::read_readwise_csv(READWISE_CSV.as_bytes(), &mut report);
+    assert_eq!(highlights.len(), 2);
+    assert!(report.issues.is_empty());
+    assert_eq!(highlights[0].title, "Letters from a Stoic");
+    assert_eq!(highlights[0].author.as_deref(), Some("Seneca"));
+    assert_eq!(highlights[1].note, None);
+
+    // Upload template columns
+    let template = "Highlight,Title,Author,URL,Note,Location,Date\nSome text,A Book,,,,,\n";
+    let highlights = import::read_readwise_csv(template.as_bytes(), &mut report);
+    assert_eq!(highlights[0].title, "A Book");
+    assert_eq!(highlights[0].author, None);
+
+    // Rows missing the highlight are skipped
+    let invalid = "Highlight,Title\nSome text,A Book\nOther text\n";
+    let highlights = import::read_readwise_csv(invalid.as_bytes(), &mut report);
+    assert_eq!(highlights.len(), 1);
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].level, ImportIssueLevel::Error);
+    assert_eq!(report.issues[0].location, "line 3");
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_readwise_is_idempotent(pool: SqlitePool) {
+    let path = temp_path("readwise.csv");
+    fs::write(&path, READWISE_CSV).unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    let res = import::import_path(import::ImportSource::Readwise, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(res.to_string(), "Imported successfully 1 new books and 2 new quotes");
+    let res = import::import_path(import::ImportSource::Readwise, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(res.to_string(), "Imported successfully 0 new books and 0 new quotes");
+
+    let quotes = queries::query_quotes(&QuoteFilter::default(), None, None, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), 2);
+
+    let starred = quotes.iter().find(|q| q.starred == Some(1)).unwrap();
+    assert_eq!(
+        starred.content.as_deref(),
+        Some("We suffer more often in imagination than in reality.")
+    );
+    let tags: Vec<&str> = starred.tags.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(tags, ["stoic"]);
+    let quote = queries::get_quote_by_id(&starred.id, &mut *conn).await.unwrap();
+    assert_eq!((quote.location_start, quote.location_end), (Some(120), Some(120)));
+    assert_eq!((quote.page_start, quote.chapter_progress), (None, None));
+
+    let notes = queries::get_notes_by_quote_ids(std::slice::from_ref(&starred.id), &mut *conn).await.unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].content.as_deref(), Some("Read again"));
+
+    fs::remove_file(path).unwrap();
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_rollback_import_batch(pool: SqlitePool) {
+    let path = temp_path("readwise.csv");
+    fs::write(&path, READWISE_CSV).unwrap();
+    let seneca = queries::insert_author("Seneca".to_string(), &pool).await.unwrap();
+
+    let report = import::import_into_library(import::ImportSource::Readwise, path.to_str().unwrap().into(), None, &pool)
+        .await
+        .unwrap();
+    assert_eq!(report.authors, ImportCounts { created: 0, updated: 0, skipped: 1 });
+    assert_eq!(report.quotes.created, 2);
+
+    let batches = queries::get_import_batches(&pool).await.unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].source, "readwise");
+    let rows: Vec<(String, Option<String>)> =
+        sqlx::query_as("SELECT 'quote', import_batch_id FROM quote UNION ALL SELECT name, import_batch_id FROM author")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    for (row, batch_id) in rows {
+        let expected = (row != "Seneca").then(|| batches[0].id.clone());
+        assert_eq!(batch_id, expected, "{} batch", row);
+    }
+
+    let mut conn = pool.acquire().await.unwrap();
+    queries::rollback_import_batch(&batches[0].id, &mut conn).await.unwrap();
+
+    let quotes = queries::query_quotes(&QuoteFilter::default(), None, None, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert!(quotes.is_empty());
+    for table in ["book", "note", "tag", "quote_tag", "import_batch"] {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 0, "{} rows left", table);
+    }
+    // The author was there before the import
+    let author = queries::get_author_by_name("Seneca".to_string(), &mut *conn).await.unwrap();
+    assert_eq!(author.id, seneca.id);
+
+    assert!(queries::rollback_import_batch(&batches[0].id, &mut conn).await.is_err());
+
+    fs::remove_file(path).unwrap();
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_readwise_export_roundtrip(pool: SqlitePool) {
+    let path = temp_path("readwise.csv");
+    fs::write(&path, READWISE_CSV).unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+    import::import_path(import::ImportSource::Readwise, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+
+    let out = temp_path("export.csv");
+    let written = export::export_readwise_csv(&out, &mut conn).await.unwrap();
+    assert_eq!(written, 2);
+
+    let csv = fs::read_to_string(&out).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("Highlight,Title,Author,URL,Note,Location,Date"));
+    // Ordered by location
+    assert_eq!(
+        lines.next(),
+        Some("Luck is what happens when preparation meets opportunity.,Letters from a Stoic,Seneca,,,80,2023-01-14 09:00:00")
+    );
+    assert_eq!(
+        lines.next(),
+        Some("We suffer more often in imagination than in reality.,Letters from a Stoic,Seneca,,.favorite .stoic Read again,120,2023-01-15 14:23:00")
+    );
+
+    // Importing our own export adds nothing
+    let res = import::import_path(import::ImportSource::Readwise, out.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(res.to_string(), "Imported successfully 0 new books and 0 new quotes");
+
+    fs::remove_file(path).unwrap();
+    fs::remove_file(out).unwrap();
+}
+
+const KOREADER_FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/koreader");
+
+#[test]
+fn test_lua_parse() {
+    let value = lua::parse(
+        r#"-- comment
+        --[[ block
+        comment ]]
+        return {
+            ["text"] = "a \"quoted\"\n\65\u{e9}",
+            name = 'single',
+            long = [==[long ]] string]==],
+            [10] = -1.5e2, 0x10, true, nil, false;
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(value.get("text").and_then(LuaValue::as_str), Some("a \"quoted\"\nAé"));
+    assert_eq!(value.get("name").and_then(LuaValue::as_str), Some("single"));
+    assert_eq!(value.get("long").and_then(LuaValue::as_str), Some("long ]] string"));
+    assert_eq!(
+        value.values()[..4],
+        [
+            &LuaValue::Number(16.0),
+            &LuaValue::Bool(true),
+            &LuaValue::Bool(false),
+            &LuaValue::Number(-150.0)
+        ]
+    );
+}
+
+#[test]
+fn test_lua_parse_rejects_code() {
+    assert!(lua::parse("return os.execute('rm -rf /')").is_err());
+    assert!(lua::parse("return { a = print('x') }").is_err());
+    assert!(lua::parse("return { a = 1 } os.exit()").is_err());
+}
+
+#[test]
+fn test_lua_parse_nesting_limit() {
+    let nested = |depth: usize| format!("return {}{}", "{".repeat(depth), "}".repeat(depth));
+    assert!(lua::parse(&nested(200)).is_ok());
+    let error = lua::parse(&nested(201)).unwrap_err();
+    assert_eq!(error.message, "tables nested too deeply");
+    // Would overflow the stack without the limit
+    assert!(lua::parse(&nested(200_000)).is_err());
+}
+
+#[test]
+fn test_parse_koreader_metadata() {
+    let sidecar = Path::new(KOREADER_FIXTURES).join("Books/Dune.sdr/metadata.epub.lua");
+    let document =
+        import::parse_koreader_metadata(&fs::read_to_string(&sidecar).unwrap(), &sidecar).unwrap();
+    assert_eq!(document.doc_path, "/mnt/onboard/Books/Dune.epub");
+    assert_eq!(document.title, "Dune");
+    assert_eq!(document.author.as_deref(), Some("Frank Herbert"));
+    // The bookmark without text is left out
+    assert_eq!(document.highlights.len(), 2);
+    assert_eq!(document.highlights[0].text, "I must not fear.\nFear is the mind-killer.");
+    assert_eq!(document.highlights[0].page, Some(42.0));
+    assert_eq!(document.highlights[1].note.as_deref(), Some("The \"litany\" against fear"));
+
+    // Older sidecars keep highlights by page and notes in the bookmarks
+    let sidecar = Path::new(KOREADER_FIXTURES).join("Books/Meditations.sdr/metadata.pdf.lua");
+    let document =
+        import::parse_koreader_metadata(&fs::read_to_string(&sidecar).unwrap(), &sidecar).unwrap();
+    assert!(document.doc_path.ends_with("Books/Meditations.pdf"));
+    assert_eq!(document.title, "Meditations");
+    assert_eq!(document.author.as_deref(), Some("Marcus Aurelius, Gregory Hays"));
+    assert_eq!(document.highlights.len(), 2);
+    assert_eq!(document.highlights[0].page, Some(12.0));
+    assert_eq!(
+        document.highlights[0].note.as_deref(),
+        Some("Realize this, and you will find strength.")
+    );
+    // Generated bookmark text is not a note
+    assert_eq!(document.highlights[1].note, None);
+
+    // Highlights of a paged document are located by their point on the page
+    let sidecar = Path::new(KOREADER_FIXTURES).join("Books/Enchiridion.sdr/metadata.pdf.lua");
+    let document =
+        import::parse_koreader_metadata(&fs::read_to_string(&sidecar).unwrap(), &sidecar).unwrap();
+    assert_eq!(
+        document.highlights.iter().map(|h| h.pos.as_str()).collect::<Vec<_>>(),
+        ["5@72,110.5", "5@72,402"]
+    );
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_koreader(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+
+    let res = import::import_path(import::ImportSource::KOReader, KOREADER_FIXTURES.into(), None, &mut conn).await.unwrap();
+    assert_eq!(res.to_string(), "Imported successfully 3 new books and 6 new quotes");
+    let res = import::import_path(import::ImportSource::KOReader, KOREADER_FIXTURES.into(), None, &mut conn).await.unwrap();
+    assert_eq!(res.to_string(), "Imported successfully 0 new books and 0 new quotes");
+
+    let book = queries::get_book_by_original_id("koreader:/mnt/onboard/Books/Dune.epub".to_string(), &mut *conn)
+        .await
+        .unwrap();
+    let chapters = queries::get_chapters_by_book(&book.id, &mut *conn).await.unwrap();
+    assert_eq!(chapters.len(), 1);
+    assert_eq!(chapters[0].title, "Book One: Dune");
+
+    let quotes = queries::get_all_quotes_by_book_id(&book.id, Some("chapter_progress"), Some("ASC"), &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), 2);
+    assert!(quotes.iter().all(|q| q.chapter_id.as_deref() == Some(chapters[0].id.as_str())));
+    assert_eq!(
+        quotes.iter().map(|q| (q.page_start, q.page_end, q.chapter_progress)).collect::<Vec<_>>(),
+        [(Some(42), Some(42), None), (Some(57), Some(57), None)]
+    );
+
+    let notes = queries::get_notes_by_book(&book.id, &mut *conn).await.unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].quote_id.as_deref(), Some(quotes[1].id.as_str()));
+
+    // Both highlights of the same page are kept
+    let book = queries::get_book_by_original_id("koreader:/mnt/onboard/Books/Enchiridion.pdf".to_string(), &mut *conn)
+        .await
+        .unwrap();
+    let quotes = queries::get_all_quotes_by_book_id(&book.id, Some("date_created"), Some("ASC"), &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        quotes.iter().map(|q| (q.content.as_deref().unwrap_or_default(), q.page_start)).collect::<Vec<_>>(),
+        [
+            ("Some things are in our control and others not.", Some(5)),
+            ("Men are disturbed not by things, but by the views which they take of things.", Some(5)),
+        ]
+    );
+    let notes = queries::get_notes_by_book(&book.id, &mut *conn).await.unwrap();
+    assert_eq!(notes[0].quote_id.as_deref(), Some(quotes[1].id.as_str()));
+}
+
+/// Create a Calibre library with the tables the importer reads.
+async fn calibre_library() -> std::path::PathBuf {
+    use sqlx::sqlite::SqliteConnectOptions;
+    use sqlx::{ConnectOptions, Connection};
+
+    let dir = temp_path("calibre");
+    fs::create_dir_all(&dir).unwrap();
+    let mut conn = SqliteConnectOptions::new()
+        .filename(dir.join("metadata.db"))
+        .create_if_missing(true)
+        .connect()
+        .await
+        .unwrap();
+
+    let annotation = |text: &str, notes: &str, toc: &str, style: &str| {
+        serde_json::json!({
+            "type": "highlight",
+            "highlighted_text": text,
+            "notes": notes,
+            "toc_family_titles": ["Part One", toc],
+            "spine_index": 3,
+            "timestamp": "2023-03-04T10:20:30.000Z",
+            "style": serde_json::from_str::<serde_json::Value>(style).unwrap(),
+        })
+        .to_string()
+    };
+
+    sqlx::query(
+        "CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT, uuid TEXT);
+        CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER, author INTEGER);
+        CREATE TABLE annotations (
+            id INTEGER PRIMARY KEY, book INTEGER, format TEXT, user_type TEXT, user TEXT,
+            timestamp REAL, annot_id TEXT, annot_type TEXT, annot_data TEXT, searchable_text TEXT
+        );
+        INSERT INTO books VALUES (1, 'The Left Hand of Darkness', 'uuid-1'), (2, 'Unread', 'uuid-2');
+        INSERT INTO authors VALUES (1, 'Ursula K. Le Guin');
+        INSERT INTO books_authors_link VALUES (1, 1, 1), (2, 2, 1);",
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+
+    for (annot_id, annot_type, data) in [
+        ("a1", "highlight", annotation("Light is the left hand of darkness.", "Title drop", "Chapter 16", r#"{"kind":"color","type":"builtin","which":"yellow"}"#)),
+        ("a2", "highlight", annotation("The only thing that makes life possible is uncertainty.", "", "Chapter 5", r##"{"kind":"color","type":"custom","background-color":"#FF00AA"}"##)),
+        ("a3", "highlight", annotation("Underlined", "", "Chapter 5", r#"{"kind":"decoration","which":"wavy"}"#)),
+        ("a4", "bookmark", r#"{"type":"bookmark","title":"Bookmark","pos":"epubcfi(/6/4)"}"#.to_string()),
+    ] {
+        sqlx::query(
+            "INSERT INTO annotations (book, format, user_type, user, timestamp, annot_id, annot_type, annot_data)
+            VALUES (1, 'EPUB', 'local', 'viewer', 1677925230.0, ?, ?, ?)",
+        )
+        .bind(annot_id)
+        .bind(annot_type)
+        .bind(data)
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    }
+
+    conn.close().await.unwrap();
+    dir
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_calibre(pool: SqlitePool) {
+    let library = calibre_library().await;
+    let mut conn = pool.acquire().await.unwrap();
+
+    let res = import::import_path(import::ImportSource::Calibre, library.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(res.to_string(), "Imported successfully 1 new books and 3 new quotes");
+    let res = import::import_path(import::ImportSource::Calibre, library.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(res.to_string(), "Imported successfully 0 new books and 0 new quotes");
+
+    let book = queries::get_book_by_original_id("calibre:uuid-1".to_string(), &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(book.title, "The Left Hand of Darkness");
+
+    let mut chapters: Vec<String> = queries::get_chapters_by_book(&book.id, &mut *conn)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| c.title)
+        .collect();
+    chapters.sort();
+    assert_eq!(chapters, ["Chapter 16", "Chapter 5"]);
+
+    let quotes = queries::query_quotes(&QuoteFilter::default(), Some("date_created"), Some("ASC"), None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), 3);
+    let colors: Vec<Vec<(String, Option<String>)>> = quotes
+        .iter()
+        .map(|q| q.tags.iter().map(|t| (t.name.clone(), t.color.clone())).collect())
+        .collect();
+    assert!(colors.contains(&vec![("yellow".to_string(), Some("#ffeb6b".to_string()))]));
+    assert!(colors.contains(&vec![("#ff00aa".to_string(), Some("#ff00aa".to_string()))]));
+    assert!(colors.contains(&Vec::new()));
+
+    let notes = queries::get_notes_by_book(&book.id, &mut *conn).await.unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].content.as_deref(), Some("Title drop"));
+
+    fs::remove_dir_all(library).unwrap();
+}
+
+const KOBO_FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/kobo");
+
+/// A Kobo database built from the SQL `fixture`.
+/// Create the SQLite database `path` from the SQL script `fixture`.
+async fn fixture_database(fixture: &Path, path: &Path) {
+    use sqlx::sqlite::SqliteConnectOptions;
+    use sqlx::{ConnectOptions, Connection};
+
+    let mut conn = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .connect()
+        .await
+        .unwrap();
+    let sql = fs::read_to_string(fixture).unwrap();
+    sqlx::query(&sql).execute(&mut conn).await.unwrap();
+    conn.close().await.unwrap();
+}
+
+async fn kobo_database(fixture: &str) -> std::path::PathBuf {
+    let path = temp_path("KoboReader.sqlite");
+    fixture_database(&Path::new(KOBO_FIXTURES).join(fixture), &path).await;
+
+    path
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_kobo_versions(pool: SqlitePool) {
+    for (fixture, linked_chapters) in [
+        ("legacy.sql", true),
+        ("v174.sql", true),
+        ("v175.sql", false),
+        ("latest.sql", false),
+    ] {
+        let path = kobo_database(fixture).await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let report = import::import_path(import::ImportSource::Kobo, path.to_str().unwrap().into(), None, &mut tx)
+            .await
+            .unwrap();
+        assert_eq!(report.books.created, 1, "{}", fixture);
+        assert_eq!(report.authors.created, 1, "{}", fixture);
+        assert_eq!(report.chapters.created, 2, "{}", fixture);
+        assert_eq!(report.quotes.created, 2, "{}", fixture);
+        assert_eq!(report.notes.created, 1, "{}", fixture);
+        assert!(report.issues.is_empty(), "{}: {:?}", fixture, report.issues);
+
+        let quotes = queries::query_quotes(&QuoteFilter::default(), None, None, None, None, &mut *tx)
+            .await
+            .unwrap();
+        let contents: Vec<_> = quotes.iter().filter_map(|q| q.content.as_deref()).collect();
+        assert!(contents.contains(&"Fear is the mind-killer."), "{}", fixture);
+        assert!(!contents.contains(&"Removed on the device"), "{}", fixture);
+        if linked_chapters {
+            for bookmark in ["bm-1", "bm-2"] {
+                let quote = queries::get_quote_by_original_id(bookmark.to_string(), &mut *tx).await.unwrap();
+                assert!(quote.chapter_id.is_some(), "{}", fixture);
+            }
+        }
+
+        tx.rollback().await.unwrap();
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_kobo_colors_and_bookmarks(pool: SqlitePool) {
+    let path = kobo_database("latest.sql").await;
+    let mut conn = pool.acquire().await.unwrap();
+
+    let report = import::import_path(import::ImportSource::Kobo, path.to_str().unwrap().into(), None, &mut conn)
+        .await
+        .unwrap();
+    assert_eq!(report.quotes.created, 2);
+    assert_eq!(report.bookmarks.created, 1);
+
+    let quote = queries::get_quote_by_original_id("bm-1".to_string(), &mut *conn).await.unwrap();
+    assert_eq!(quote.color.as_deref(), Some("yellow"));
+    let quote = queries::get_quote_by_original_id("bm-2".to_string(), &mut *conn).await.unwrap();
+    assert_eq!(quote.color.as_deref(), Some("blue"));
+    assert!(queries::get_quote_by_original_id("bm-4".to_string(), &mut *conn).await.is_err());
+
+    let bookmark = queries::get_bookmark_by_original_id("bm-4", &mut *conn).await.unwrap();
+    assert_eq!(bookmark.kind, "dogear");
+    assert_eq!(bookmark.book_id, quote.book_id);
+    let bookmarks = queries::get_bookmarks_by_book(bookmark.book_id.as_deref().unwrap(), &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(bookmarks.len(), 1);
+
+    let filter = QuoteFilter {
+        colors: vec!["Blue".to_string()],
+        ..Default::default()
+    };
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn).await.unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0].color.as_deref(), Some("blue"));
+    let colors = queries::get_quote_colors(&mut *conn).await.unwrap();
+    assert_eq!(colors, vec!["blue".to_string(), "yellow".to_string()]);
+
+    // Importing again does not duplicate the dog-ear
+    let report = import::import_path(import::ImportSource::Kobo, path.to_str().unwrap().into(), None, &mut conn)
+        .await
+        .unwrap();
+    assert_eq!(report.bookmarks.created, 0);
+    assert_eq!(report.bookmarks.skipped, 1);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_kobo_invalid_database(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+
+    let path = temp_path("KoboReader.sqlite");
+    fs::write(&path, "not a database").unwrap();
+    let res = import::import_path(import::ImportSource::Kobo, path.to_str().unwrap().into(), None, &mut conn).await;
+    assert!(matches!(res, Err(import::ImportError::DbError(..))));
+    fs::remove_file(path).unwrap();
+
+    let path = kobo_database("legacy.sql").await;
+    {
+        use sqlx::Connection;
+        let mut kobo = sqlx::SqliteConnection::connect(&format!("sqlite:{}", path.display())).await.unwrap();
+        sqlx::query("DROP TABLE Bookmark").execute(&mut kobo).await.unwrap();
+        kobo.close().await.unwrap();
+    }
+    let res = import::import_path(import::ImportSource::Kobo, path.to_str().unwrap().into(), None, &mut conn).await;
+    match res {
+        Err(import::ImportError::InvalidFormat(message)) => {
+            assert_eq!(message, "Not a Kobo database: missing table Bookmark")
+        }
+        res => panic!("unexpected result {:?}", res.map(|r| r.to_string())),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+const IBOOKS_FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/ibooks");
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_ibooks_databases(pool: SqlitePool) {
+    // Copies of the databases, e.g. from a backup
+    let databases = import::IBooksDatabases {
+        annotation: temp_path("AEAnnotation_v10312011_1727_local.sqlite"),
+        asset: temp_path("BKLibrary-1-091020131601.sqlite"),
+    };
+    fixture_database(&Path::new(IBOOKS_FIXTURES).join("AEAnnotation.sql"), &databases.annotation).await;
+    fixture_database(&Path::new(IBOOKS_FIXTURES).join("BKLibrary.sql"), &databases.asset).await;
+
+    // The databases go through the preview, and the UI, as they are
+    let input = import::ImportInput::IBooksDatabases(databases.clone());
+    let preview = import::preview_import(import::ImportSource::IBooks, input.clone(), &pool).await.unwrap();
+    assert_eq!(preview.input, input);
+    let json = serde_json::to_string(&preview.input).unwrap();
+    assert_eq!(serde_json::from_str::<import::ImportInput>(&json).unwrap(), input);
+    assert_eq!(preview.report.quotes.created, 2);
+
+    let report = import::import_into_library(import::ImportSource::IBooks, preview.input, None, &pool)
+        .await
+        .unwrap();
+    assert_eq!(report.books, ImportCounts { created: 1, updated: 0, skipped: 0 });
+    assert_eq!(report.quotes, ImportCounts { created: 2, updated: 0, skipped: 0 });
+    assert_eq!(report.notes.created, 1);
+    assert!(report.issues.is_empty());
+
+    let mut conn = pool.acquire().await.unwrap();
+    let book = queries::get_book_by_original_id("ASSET-1".to_string(), &mut *conn).await.unwrap();
+    assert_eq!(book.title, "Pride and Prejudice");
+    let opening = queries::get_quote_by_original_id("an-1".to_string(), &mut *conn).await.unwrap();
+    assert_eq!(opening.color.as_deref(), Some("yellow"));
+    assert_eq!(opening.created_at.to_string(), "2023-03-08 20:26:40");
+    assert_eq!(opening.updated_at.to_string(), "2023-03-08 20:36:40");
+    let pride = queries::get_quote_by_original_id("an-2".to_string(), &mut *conn).await.unwrap();
+    assert_eq!(
+        pride.content.as_deref(),
+        Some("I could easily forgive his pride, if he had not mortified mine.")
+    );
+    assert_eq!(pride.color, None);
+    let notes = queries::get_notes_by_book(&book.id, &mut *conn).await.unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].content.as_deref(), Some("Opening line"));
+    assert_eq!(notes[0].quote_id.as_deref(), Some(opening.id.as_str()));
+    let batches = queries::get_import_batches(&mut *conn).await.unwrap();
+    assert_eq!(batches[0].path, databases.annotation.to_str().unwrap());
+    assert!(batches[0].file_hash.is_some());
+
+    // Found in a folder laid out like the iBooks container
+    let dir = temp_path("iBooks");
+    fs::create_dir_all(dir.join("AEAnnotation")).unwrap();
+    fs::create_dir_all(dir.join("BKLibrary")).unwrap();
+    let found = import::IBooksDatabases {
+        annotation: dir.join("AEAnnotation/AEAnnotation_v10312011_1727_local.sqlite"),
+        asset: dir.join("BKLibrary/BKLibrary-1-091020131601.sqlite"),
+    };
+    fs::copy(&databases.annotation, &found.annotation).unwrap();
+    fs::copy(&databases.asset, &found.asset).unwrap();
+    assert_eq!(import::IBooksDatabases::find(&dir).unwrap(), found);
+
+    let again = import::import_path(import::ImportSource::IBooks, dir.to_str().unwrap().into(), None, &mut conn)
+        .await
+        .unwrap();
+    assert_eq!(again.books, ImportCounts { created: 0, updated: 0, skipped: 1 });
+    assert_eq!(again.quotes, ImportCounts { created: 0, updated: 0, skipped: 2 });
+
+    let missing = import::IBooksDatabases {
+        annotation: databases.annotation.clone(),
+        asset: temp_path("BKLibrary.sqlite"),
+    };
+    let input = import::ImportInput::IBooksDatabases(missing);
+    let res = import::import_path(import::ImportSource::IBooks, input, None, &mut conn).await;
+    assert!(matches!(res, Err(import::ImportError::IoError(..))));
+    let res = import::import_path(import::ImportSource::IBooks, temp_path("iBooks").to_str().unwrap().into(), None, &mut conn).await;
+    assert!(matches!(res, Err(import::ImportError::IoError(..))));
+
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_file(databases.annotation).unwrap();
+    fs::remove_file(databases.asset).unwrap();
+}
+
+#[test]
+fn test_import_source_names() {
+    let sources: Vec<import::ImportSource> =
+        serde_json::from_str(r#"["kobo", "kindle", "ibooks", "readwise", "koreader", "calibre"]"#).unwrap();
+    assert_eq!(sources.len(), 6);
+    assert_eq!(sources[2], import::ImportSource::IBooks);
+    assert_eq!(serde_json::to_string(&import::ImportSource::KOReader).unwrap(), "\"koreader\"");
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_kindle_clippings_from_path(pool: SqlitePool) {
+    let report = import::import_into_library(import::ImportSource::Kindle, KINDLE_CLIPPINGS.into(), None, &pool)
+        .await
+        .unwrap();
+    assert_eq!(report.books, ImportCounts { created: 2, updated: 0, skipped: 0 });
+    assert_eq!(report.quotes, ImportCounts { created: 2, updated: 0, skipped: 0 });
+    assert_eq!(report.notes.created, 1);
+    assert!(report.issues.is_empty());
+
+    let batches = queries::get_import_batches(&pool).await.unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].source, "kindle");
+    assert_eq!(batches[0].report, report);
+    assert_eq!(batches[0].file_hash.as_ref().map(|h| h.len()), Some(64));
+
+    let mut conn = pool.acquire().await.unwrap();
+    let quotes = queries::query_quotes(&QuoteFilter::default(), Some("date_created"), Some("ASC"), None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        quotes.iter().map(|q| q.content.as_deref().unwrap_or_default()).collect::<Vec<_>>(),
+        [
+            "You forget what you want to remember, and you remember what you want to forget.",
+            "The happiness of your life depends upon the quality of your thoughts.",
+        ]
+    );
+    let book = quotes[0].book_id.clone().unwrap();
+    let notes = queries::get_notes_by_book(&book, &mut *conn).await.unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].content.as_deref(), Some("Memory"));
+    assert_eq!(notes[0].quote_id.as_deref(), Some(quotes[0].id.as_str()));
+    let quote = queries::get_quote_by_id(&quotes[0].id, &mut *conn).await.unwrap();
+    assert_eq!((quote.page_start, quote.page_end), (Some(12), Some(12)));
+    assert_eq!((quote.location_start, quote.location_end), (Some(170), Some(171)));
+
+    let missing = import::import_into_library(import::ImportSource::Kobo, "/nonexistent/KoboReader.sqlite".into(), None, &pool).await;
+    assert!(missing.is_err());
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_kindle_notebook(pool: SqlitePool) {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/kindle/notebook.html");
+    let report = import::import_into_library(import::ImportSource::Kindle, path.into(), None, &pool).await.unwrap();
+    assert_eq!(report.books, ImportCounts { created: 1, updated: 0, skipped: 0 });
+    assert_eq!(report.chapters, ImportCounts { created: 2, updated: 0, skipped: 0 });
+    assert_eq!(report.quotes, ImportCounts { created: 3, updated: 0, skipped: 0 });
+    assert_eq!(report.notes.created, 1);
+    assert!(report.issues.is_empty());
+
+    let mut conn = pool.acquire().await.unwrap();
+    let book = queries::get_book_by_original_id("The Art of War".to_string(), &mut *conn).await.unwrap();
+    let chapters = queries::get_chapters_by_book(&book.id, &mut *conn).await.unwrap();
+    let mut titles = chapters.iter().map(|c| c.title.as_str()).collect::<Vec<_>>();
+    titles.sort();
+    assert_eq!(titles, ["I. Laying Plans", "III. Attack by Stratagem"]);
+
+    let quotes = queries::get_all_quotes_by_book_id(&book.id, Some("chapter_progress"), Some("ASC"), &mut *conn).await.unwrap();
+    assert_eq!(
+        quotes
+            .iter()
+            .map(|q| (q.content.as_deref().unwrap_or_default(), q.color.as_deref(), q.location_start, q.location_end))
+            .collect::<Vec<_>>(),
+        [
+            ("The art of war is of vital importance to the State.", Some("yellow"), Some(45), Some(45)),
+            ("All warfare is based on deception.", Some("blue"), Some(78), Some(80)),
+            (
+                "If you know the enemy and know yourself, you need not fear the result of a hundred battles & more.",
+                Some("pink"),
+                Some(271),
+                Some(271)
+            ),
+        ]
+    );
+    assert_eq!(quotes[2].page_start, Some(18));
+    let chapter = chapters.iter().find(|c| c.title == "III. Attack by Stratagem").unwrap();
+    assert_eq!(quotes[2].chapter_id.as_deref(), Some(chapter.id.as_str()));
+
+    let notes = queries::get_notes_by_book(&book.id, &mut *conn).await.unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].content.as_deref(), Some("Opening line"));
+    assert_eq!(notes[0].quote_id.as_deref(), Some(quotes[0].id.as_str()));
+
+    let again = import::import_into_library(import::ImportSource::Kindle, path.into(), None, &pool).await.unwrap();
+    assert_eq!(again.books, ImportCounts { created: 0, updated: 0, skipped: 1 });
+    assert_eq!(again.chapters, ImportCounts { created: 0, updated: 0, skipped: 2 });
+    assert_eq!(again.quotes, ImportCounts { created: 0, updated: 0, skipped: 3 });
+    assert_eq!(again.notes.created, 0);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_kindle_notebook_after_clippings(pool: SqlitePool) {
+    let clippings = temp_path("My Clippings.txt");
+    fs::write(
+        &clippings,
+        "\
+The Art of War (Sun Tzu)
+- Your Highlight on Location 45 | Added on Saturday, 26 March 2016 14:59:39
+
+The art of war is of vital importance to the State.
+==========
+",
+    )
+    .unwrap();
+    import::import_into_library(import::ImportSource::Kindle, clippings.to_str().unwrap().into(), None, &pool)
+        .await
+        .unwrap();
+    fs::remove_file(&clippings).unwrap();
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/kindle/notebook.html");
+    let report = import::import_into_library(import::ImportSource::Kindle, path.into(), None, &pool).await.unwrap();
+    assert_eq!(report.books, ImportCounts { created: 0, updated: 0, skipped: 1 });
+    assert_eq!(report.quotes, ImportCounts { created: 2, updated: 1, skipped: 0 });
+    assert_eq!(report.notes.created, 1);
+
+    let mut conn = pool.acquire().await.unwrap();
+    let book = queries::get_book_by_original_id("The Art of War".to_string(), &mut *conn).await.unwrap();
+    let chapters = queries::get_chapters_by_book(&book.id, &mut *conn).await.unwrap();
+    let chapter = chapters.iter().find(|c| c.title == "I. Laying Plans").unwrap();
+    let quotes = queries::get_all_quotes_by_book_id(&book.id, Some("chapter_progress"), Some("ASC"), &mut *conn).await.unwrap();
+    assert_eq!(quotes.len(), 3);
+    let quote = quotes
+        .iter()
+        .find(|q| q.content.as_deref() == Some("The art of war is of vital importance to the State."))
+        .unwrap();
+    let quote = queries::get_quote_by_id(&quote.id, &mut *conn).await.unwrap();
+    assert_eq!(quote.chapter_id.as_deref(), Some(chapter.id.as_str()));
+    assert_eq!(quote.color.as_deref(), Some("yellow"));
+    assert_eq!((quote.page_start, quote.page_end), (Some(3), Some(3)));
+    assert_eq!((quote.location_start, quote.location_end), (Some(45), Some(45)));
+
+    let notes = queries::get_notes_by_book(&book.id, &mut *conn).await.unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].quote_id.as_deref(), Some(quote.id.as_str()));
+
+    let again = import::import_into_library(import::ImportSource::Kindle, path.into(), None, &pool).await.unwrap();
+    assert_eq!(again.quotes, ImportCounts { created: 0, updated: 0, skipped: 3 });
+    assert_eq!(again.notes.created, 0);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_clippings_skips_invalid_records(pool: SqlitePool) {
+    let path = temp_path("My Clippings.txt");
+    fs::write(
+        &path,
+        "\
+Meditations (Marcus Aurelius)
+- Your Highlight on page 12 | Added on Saturday, 26 March 2016 14:59:39
+
+The happiness of your life depends upon the quality of your thoughts.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 14 | Added on sometime last week
+
+Broken date.
+==========
+Meditations (Marcus Aurelius)
+==========
+",
+    )
+    .unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    let report = import::import_path(import::ImportSource::Kindle, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(report.quotes.created, 1);
+    assert_eq!(report.errors(), 2);
+    let locations: Vec<&str> = report.issues.iter().map(|i| i.location.as_str()).collect();
+    assert_eq!(locations, ["line 6", "line 11"]);
+    assert_eq!(
+        report.to_string(),
+        "Imported successfully 1 new books and 1 new quotes, 2 invalid records were skipped"
+    );
+
+    fs::remove_file(path).unwrap();
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_clippings_locations(pool: SqlitePool) {
+    let path = temp_path("My Clippings.txt");
+    fs::write(
+        &path,
+        "\
+Meditations (Marcus Aurelius)
+- Your Note Location 1024 | Added on Sunday, 27 March 2016 09:14:00
+
+Annotated before the highlight was clipped
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight Location 1022-24 | Added on Sunday, 27 March 2016 09:12:45
+
+Waste no more time arguing what a good man should be. Be one.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page xii | Location 90-95 | Added on Sunday, 27 March 2016 09:20:00
+
+Dwell on the beauty of life.
+==========
+Meditations (Marcus Aurelius)
+- Your Note on page 40 | Location 2000 | Added on Sunday, 27 March 2016 09:30:00
+
+Not on a highlight
+==========
+",
+    )
+    .unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    let report = import::import_path(import::ImportSource::Kindle, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(report.quotes.created, 2);
+    assert_eq!(report.notes.created, 2);
+
+    // Sorted by their location rather than by date
+    let quotes = queries::query_quotes(&QuoteFilter::default(), Some("chapter_progress"), Some("ASC"), None, None, &mut *conn)
+        .await
+        .unwrap();
+    let contents: Vec<&str> = quotes.iter().map(|q| q.content.as_deref().unwrap_or_default()).collect();
+    assert_eq!(contents, ["Dwell on the beauty of life.", "Waste no more time arguing what a good man should be. Be one."]);
+
+    let quote = queries::get_quote_by_id(&quotes[1].id, &mut *conn).await.unwrap();
+    assert_eq!((quote.location_start, quote.location_end), (Some(1022), Some(1024)));
+    let quote = queries::get_quote_by_id(&quotes[0].id, &mut *conn).await.unwrap();
+    assert_eq!((quote.page_start, quote.location_start), (None, Some(90)));
+
+    let notes = queries::get_notes_by_book(quotes[0].book_id.as_deref().unwrap(), &mut *conn).await.unwrap();
+    let linked = |content: &str| notes.iter().find(|n| n.content.as_deref() == Some(content)).unwrap().quote_id.clone();
+    assert_eq!(linked("Annotated before the highlight was clipped"), Some(quotes[1].id.clone()));
+    assert_eq!(linked("Not on a highlight"), None);
+
+    fs::remove_file(path).unwrap();
+}
+
+fn dune_clipping(kind: &str, location: &str, added_at: &str, content: &str) -> String {
+    format!(
+        "Dune (Frank Herbert)\n- Your {} Location {} | Added on Sunday, 27 March 2016 {}\n\n{}\n==========\n",
+        kind, location, added_at, content
+    )
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_clippings_highlight_versions(pool: SqlitePool) {
+    let path = temp_path("My Clippings.txt");
+    let clippings = [
+        dune_clipping("Highlight", "100-100", "10:00:00", "Fear is the mind-killer."),
+        dune_clipping("Highlight", "99-100", "10:05:00", "I must not fear. Fear is the mind-killer."),
+        dune_clipping("Highlight", "100-101", "10:10:00", "Fear is the mind-killer. Fear is the little-death."),
+        dune_clipping("Highlight", "101-101", "10:15:00", "I will face my fear."),
+    ];
+    fs::write(&path, clippings.concat()).unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    let report = import::import_path(import::ImportSource::Kindle, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(report.quotes, ImportCounts { created: 2, updated: 0, skipped: 2 });
+    assert_eq!(report.errors(), 0);
+    assert!(report.issues.iter().all(|i| i.level == ImportIssueLevel::Info));
+    let locations: Vec<&str> = report.issues.iter().map(|i| i.location.as_str()).collect();
+    assert_eq!(locations, ["line 1", "line 6"]);
+    assert_eq!(report.issues[0].message, "Superseded by a newer version of the highlight at line 11");
+
+    let quotes = queries::query_quotes(&QuoteFilter::default(), Some("date_created"), Some("ASC"), None, None, &mut *conn)
+        .await
+        .unwrap();
+    let contents: Vec<&str> = quotes.iter().map(|q| q.content.as_deref().unwrap_or_default()).collect();
+    assert_eq!(contents, ["Fear is the mind-killer. Fear is the little-death.", "I will face my fear."]);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_clippings_same_text_elsewhere(pool: SqlitePool) {
+    let path = temp_path("My Clippings.txt");
+    // The litany is recited again much later, highlighted within a longer passage
+    let clippings = [
+        dune_clipping("Highlight", "100-100", "10:00:00", "Fear is the mind-killer."),
+        dune_clipping("Highlight", "2500-2502", "10:05:00", "Paul recited: Fear is the mind-killer."),
+    ];
+    fs::write(&path, clippings.concat()).unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    let report = import::import_path(import::ImportSource::Kindle, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(report.quotes, ImportCounts { created: 2, updated: 0, skipped: 0 });
+    assert!(report.issues.is_empty());
+
+    // Nor is the library version replaced on re-import
+    let report = import::import_path(import::ImportSource::Kindle, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(report.quotes, ImportCounts { created: 0, updated: 0, skipped: 2 });
+    let quotes = queries::query_quotes(&QuoteFilter::default(), Some("date_created"), Some("ASC"), None, None, &mut *conn)
+        .await
+        .unwrap();
+    let contents: Vec<&str> = quotes.iter().map(|q| q.content.as_deref().unwrap_or_default()).collect();
+    assert_eq!(contents, ["Fear is the mind-killer.", "Paul recited: Fear is the mind-killer."]);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_reimport_grown_clippings(pool: SqlitePool) {
+    let path = temp_path("My Clippings.txt");
+    let first = [
+        dune_clipping("Highlight", "100-100", "10:00:00", "Fear is the mind-killer."),
+        dune_clipping("Note", "100", "10:01:00", "Litany"),
+    ]
+    .concat();
+    fs::write(&path, &first).unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    let report = import::import_path(import::ImportSource::Kindle, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(report.quotes.created, 1);
+    let quote = queries::query_quotes(&QuoteFilter::default(), None, None, None, None, &mut *conn).await.unwrap().remove(0);
+
+    // The highlight was extended on the device since
+    let grown = first.clone()
+        + &dune_clipping("Highlight", "99-100", "11:00:00", "I must not fear. Fear is the mind-killer.")
+        + &dune_clipping("Note", "99", "11:01:00", "Extended");
+    fs::write(&path, &grown).unwrap();
+    let report = import::import_path(import::ImportSource::Kindle, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(report.books, ImportCounts { created: 0, updated: 0, skipped: 1 });
+    assert_eq!(report.quotes, ImportCounts { created: 0, updated: 1, skipped: 1 });
+    assert_eq!(report.notes, ImportCounts { created: 1, updated: 0, skipped: 1 });
+
+    let quotes = queries::query_quotes(&QuoteFilter::default(), None, None, None, None, &mut *conn).await.unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0].id, quote.id);
+    assert_eq!(quotes[0].content.as_deref(), Some("I must not fear. Fear is the mind-killer."));
+    let updated = queries::get_quote_by_id(&quote.id, &mut *conn).await.unwrap();
+    assert_eq!((updated.location_start, updated.location_end), (Some(99), Some(100)));
+    let notes = queries::get_notes_by_book(quote.book_id.as_deref().unwrap(), &mut *conn).await.unwrap();
+    assert_eq!(notes.len(), 2);
+    assert!(notes.iter().all(|n| n.quote_id.as_deref() == Some(quote.id.as_str())));
+
+    // The older file does not bring the older version back
+    fs::write(&path, &first).unwrap();
+    let report = import::import_path(import::ImportSource::Kindle, path.to_str().unwrap().into(), None, &mut conn).await.unwrap();
+    assert_eq!(report.quotes, ImportCounts { created: 0, updated: 0, skipped: 1 });
+    assert_eq!(report.issues[0].message, "Superseded by a newer version of the highlight in the library");
+    let updated = queries::get_quote_by_id(&quote.id, &mut *conn).await.unwrap();
+    assert_eq!(updated.content.as_deref(), Some("I must not fear. Fear is the mind-killer."));
+
+    fs::remove_file(path).unwrap();
+}
+
+const KINDLE_LOCALES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/kindle/locales");
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_clippings_languages(pool: SqlitePool) {
+    let added_at = chrono::NaiveDate::from_ymd_opt(2016, 3, 26).unwrap().and_hms_opt(14, 59, 39).unwrap();
+
+    for language in ["en-us", "de", "fr", "it", "es", "pt", "nl", "ja", "zh"] {
+        let path = Path::new(KINDLE_LOCALES).join(format!("{}.txt", language));
+        let mut tx = pool.begin().await.unwrap();
+
+        let report = import::import_path(import::ImportSource::Kindle, path.to_str().unwrap().into(), None, &mut tx)
+            .await
+            .unwrap();
+        assert!(report.issues.is_empty(), "{}: {:?}", language, report.issues);
+        assert_eq!(report.quotes.created, 1, "{}", language);
+        assert_eq!(report.notes.created, 1, "{}", language);
+
+        let quotes = queries::query_quotes(&QuoteFilter::default(), None, None, None, None, &mut *tx)
+            .await
+            .unwrap();
+        let quote = queries::get_quote_by_id(&quotes[0].id, &mut *tx).await.unwrap();
+        assert_eq!(quote.created_at, added_at, "{}", language);
+        assert_eq!((quote.page_start, quote.page_end), (Some(12), Some(12)), "{}", language);
+        assert_eq!((quote.location_start, quote.location_end), (Some(170), Some(171)), "{}", language);
+        assert_eq!(quotes[0].author_name.as_deref().map(|a| !a.is_empty()), Some(true), "{}", language);
+
+        let notes = queries::get_notes_by_book(quote.book_id.as_deref().unwrap(), &mut *tx).await.unwrap();
+        assert_eq!(notes[0].quote_id.as_deref(), Some(quote.id.as_str()), "{}", language);
+
+        tx.rollback().await.unwrap();
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_preview_and_import_selection(pool: SqlitePool) {
+    let source = import::ImportSource::KOReader;
+    let preview = import::preview_import(source, KOREADER_FIXTURES.into(), &pool).await.unwrap();
+    assert_eq!(preview.report.quotes.created, 6);
+    let books: Vec<&import::PreviewBook> = preview.authors.iter().flat_map(|a| a.books.iter()).collect();
+    assert_eq!(books.len(), 3);
+    assert!(books.iter().all(|b| b.status == import::PreviewStatus::New));
+
+    // Nothing was written
+    let mut conn = pool.acquire().await.unwrap();
+    assert!(queries::query_quotes(&QuoteFilter::default(), None, None, None, None, &mut *conn)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Import one quote of the first book
+    let book = books[0];
+    let quote = &book.chapters[0].quotes[0];
+    let selection = import::ImportSelection {
+        books: [book.key.clone()].into(),
+        quotes: [quote.key.clone()].into(),
+    };
+    let report = import::import_into_library(source, KOREADER_FIXTURES.into(), Some(selection), &pool)
+        .await
+        .unwrap();
+    assert_eq!(report.books.created, 1);
+    assert_eq!(report.quotes.created, 1);
+
+    let imported = queries::get_quote_by_original_id(quote.key.clone(), &mut *conn).await.unwrap();
+    queries::update_quote_content(&imported.id, "Edited", &mut *conn).await.unwrap();
+
+    let preview = import::preview_import(source, KOREADER_FIXTURES.into(), &pool).await.unwrap();
+    let statuses: Vec<import::PreviewStatus> = preview
+        .authors
+        .iter()
+        .flat_map(|a| a.books.iter())
+        .flat_map(|b| b.chapters.iter())
+        .flat_map(|c| c.quotes.iter())
+        .map(|q| q.status)
+        .collect();
+    assert_eq!(statuses.iter().filter(|s| **s == import::PreviewStatus::Changed).count(), 1);
+    assert_eq!(statuses.iter().filter(|s| **s == import::PreviewStatus::New).count(), 5);
+
+    // Changed quotes are left alone unless selected
+    let report = import::import_into_library(source, KOREADER_FIXTURES.into(), None, &pool).await.unwrap();
+    assert_eq!(report.quotes.created, 5);
+    assert_eq!(report.quotes.updated, 0);
+    let selection = import::ImportSelection {
+        books: [book.key.clone()].into(),
+        quotes: [quote.key.clone()].into(),
+    };
+    let report = import::import_into_library(source, KOREADER_FIXTURES.into(), Some(selection), &pool)
+        .await
+        .unwrap();
+    assert_eq!(report.quotes.updated, 1);
+    let updated = queries::get_quote_by_id(&imported.id, &mut *conn).await.unwrap();
+    assert_eq!(updated.content, quote.content);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_preview_while_writing(pool: SqlitePool) {
+    // Another writer holds the write lock for the whole preview
+    let mut tx = pool.begin().await.unwrap();
+    queries::insert_author("Seneca".to_string(), &mut *tx).await.unwrap();
+
+    let preview = import::preview_import(import::ImportSource::Kindle, KINDLE_CLIPPINGS.into(), &pool)
+        .await
+        .unwrap();
+    assert!(preview.report.quotes.created > 0);
+    tx.commit().await.unwrap();
+
+    // Neither the library nor its search index were written
+    for (table, expected) in [("author", 1), ("quote", 0), ("quote_fts", 0), ("import_batch", 0)] {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, expected, "{} rows", table);
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_cancel_preview(pool: SqlitePool) {
+    let handle = import::ImportHandle::register();
+    assert!(import::cancel_import(&handle.id));
+    let run = import::ImportRun::default().with_progress(handle.clone(), |_| {});
+
+    let res = import::preview_run(import::ImportSource::Kindle, KINDLE_CLIPPINGS.into(), run, &pool).await;
+    assert!(matches!(res, Err(import::ImportError::Cancelled)));
+
+    handle.finish();
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_import_progress(pool: SqlitePool) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let handle = import::ImportHandle::register();
+    let sink = events.clone();
+    let run = import::ImportRun::new(None).with_progress(handle.clone(), move |progress| {
+        sink.lock().unwrap().push(progress);
+    });
+
+    import::import_run_into_library(import::ImportSource::Kindle, KINDLE_CLIPPINGS.into(), run, &pool)
+        .await
+        .unwrap();
+    handle.finish();
+
+    let events = events.lock().unwrap();
+    let last = events.last().unwrap();
+    assert_eq!(last.id, handle.id);
+    assert_eq!(last.phase, import::ImportPhase::Quotes);
+    assert_eq!(last.processed, last.total);
+    assert!(events.iter().all(|e| e.book.is_some()));
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_cancel_import(pool: SqlitePool) {
+    let handle = import::ImportHandle::register();
+    assert!(import::cancel_import(&handle.id));
+    let run = import::ImportRun::new(None).with_progress(handle.clone(), |_| {});
+
+    let res = import::import_run_into_library(import::ImportSource::Kindle, KINDLE_CLIPPINGS.into(), run, &pool).await;
+    assert!(matches!(res, Err(import::ImportError::Cancelled)));
+
+    // Rolled back
+    for table in ["book", "quote", "import_batch"] {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0, "{} rows left", table);
+    }
+
+    handle.finish();
+    assert!(!import::cancel_import(&handle.id));
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_cancel_running_import(pool: SqlitePool) {
+    let handle = import::ImportHandle::register();
+    let phases = Arc::new(Mutex::new(Vec::new()));
+    let run = {
+        let (cancel, phases) = (handle.clone(), phases.clone());
+        import::ImportRun::new(None).with_progress(handle.clone(), move |progress| {
+            phases.lock().unwrap().push(progress.phase);
+            // The first clipping is written after this, before the import stops at the second
+            if progress.phase == import::ImportPhase::Quotes {
+                assert!(import::cancel_import(&cancel.id));
+            }
+        })
+    };
+
+    let res = import::import_run_into_library(import::ImportSource::Kindle, KINDLE_CLIPPINGS.into(), run, &pool).await;
+    assert!(matches!(res, Err(import::ImportError::Cancelled)));
+    let phases = phases.lock().unwrap().clone();
+    assert_eq!(phases.last(), Some(&import::ImportPhase::Quotes));
+    assert_eq!(phases.iter().filter(|p| **p == import::ImportPhase::Quotes).count(), 1);
+
+    // The rows written before the cancellation are rolled back
+    for table in ["author", "book", "quote", "note", "import_batch"] {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0, "{} rows left", table);
+    }
+
+    handle.finish();
+}
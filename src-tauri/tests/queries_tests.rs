@@ -1,5 +1,7 @@
 use chrono::Utc;
-use liture_notes_lib::models::{Author, Book, Chapter, Note, Quote, Tag};
+use liture_notes_lib::models::{
+    Author, Book, Chapter, DeletedState, Note, Quote, QuoteFilter, Tag, TagMatch,
+};
 use liture_notes_lib::queries;
 use sqlx::SqlitePool;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -67,6 +69,7 @@ async fn init_db(pool: &SqlitePool) -> Result<TestData, sqlx::Error> {
                 updated_at: now,
                 deleted_at: None,
                 original_id: Some(generate_random_string(10)),
+                import_batch_id: None,
             },
             pool,
         )
@@ -81,6 +84,7 @@ async fn init_db(pool: &SqlitePool) -> Result<TestData, sqlx::Error> {
             id: Uuid::new_v4().to_string(),
             name: format!("Tag {}", i),
             color: Some(generate_random_rgb()),
+            import_batch_id: None,
         }, pool).await?;
         tags.push(tag);
     }
@@ -96,11 +100,17 @@ async fn init_db(pool: &SqlitePool) -> Result<TestData, sqlx::Error> {
             updated_at: now,
             deleted_at: None,
             original_id: Some(generate_random_string(10)),
+            color: None,
+            page_start: None,
+            page_end: None,
+            location_start: None,
+            location_end: None,
             author_id: Some(author.id.clone()),
             chapter_id: Some(chapters[0].id.clone()),
             chapter_progress: None,
             starred: Some(0),
             imported_at: Some(now),
+            import_batch_id: None,
         },
         pool,
     )
@@ -135,11 +145,17 @@ async fn init_db(pool: &SqlitePool) -> Result<TestData, sqlx::Error> {
                 updated_at: now,
                 deleted_at: None,
                 original_id: Some(generate_random_string(10)),
+                color: None,
+                page_start: None,
+                page_end: None,
+                location_start: None,
+                location_end: None,
                 author_id: Some(author.id.clone()),
                 chapter_id: Some(chapters[0].id.clone()),
                 chapter_progress: Some(i as f64),
                 starred: Some(1),
                 imported_at: Some(now),
+                import_batch_id: None,
             },
             pool,
         )
@@ -395,3 +411,554 @@ fn test_delete_quote(pool: SqlitePool) {
     let quote = queries::get_quote_by_id(&test_data.quote.id, &pool).await;
     assert!(quote.is_err());
 }
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_find_quotes(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+
+    let quotes = queries::find_quotes("reader", &pool).await.unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0].id, test_data.quote.id);
+    assert_eq!(quotes[0].book_title, Some("A Dance with Dragons".to_string()));
+    assert!(quotes[0].highlight.as_ref().unwrap().contains("<mark>reader</mark>"));
+    assert!(quotes[0].snippet.as_ref().unwrap().contains("<mark>reader</mark>"));
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_find_quotes_escapes_content(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    let mut quote = test_data.quote.clone();
+    quote.id = Uuid::new_v4().to_string();
+    quote.content = Some("<img src=x onerror=alert(1)> Tom & Jerry's \"escapade\"".to_string());
+    queries::insert_quote(&quote, &pool).await.unwrap();
+
+    let quotes = queries::find_quotes("escapade", &pool).await.unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(
+        quotes[0].highlight.as_deref(),
+        Some("&lt;img src=x onerror=alert(1)&gt; Tom &amp; Jerry&#39;s &quot;<mark>escapade</mark>&quot;")
+    );
+    assert!(!quotes[0].snippet.as_ref().unwrap().contains("<img"));
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_find_quotes_ranking(pool: SqlitePool) {
+    init_db(&pool).await.unwrap();
+
+    // Results are sorted by bm25, best match first
+    let quotes = queries::find_quotes("starred OR lives", &pool).await.unwrap();
+    assert_eq!(quotes.len(), STARRED_QUOTES_COUNT + 1);
+    assert!(quotes.windows(2).all(|w| w[0].rank <= w[1].rank));
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_find_quotes_syntax(pool: SqlitePool) {
+    init_db(&pool).await.unwrap();
+
+    let quotes = queries::find_quotes("\"thousand lives\"", &pool).await.unwrap();
+    assert_eq!(quotes.len(), 1);
+
+    let quotes = queries::find_quotes("\"lives thousand\"", &pool).await.unwrap();
+    assert_eq!(quotes.len(), 0);
+
+    let quotes = queries::find_quotes("star*", &pool).await.unwrap();
+    assert_eq!(quotes.len(), STARRED_QUOTES_COUNT);
+
+    let quotes = queries::find_quotes("starred NOT 1", &pool).await.unwrap();
+    assert_eq!(quotes.len(), STARRED_QUOTES_COUNT - 1);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_find_quotes_invalid_syntax(pool: SqlitePool) {
+    init_db(&pool).await.unwrap();
+
+    for search in ["\"reader", "reader)", "AND reader OR", "content: reader", "-reader^", "\"\""] {
+        assert!(queries::find_quotes(search, &pool).await.is_ok(), "{}", search);
+    }
+
+    let quotes = queries::find_quotes("AND reader OR", &pool).await.unwrap();
+    assert_eq!(quotes.len(), 1);
+
+    let quotes = queries::find_quotes("*** ---", &pool).await.unwrap();
+    assert!(quotes.is_empty());
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_find_quotes_by_book_title_and_author_name(pool: SqlitePool) {
+    init_db(&pool).await.unwrap();
+
+    let quotes = queries::find_quotes_by_book_title("starred", "A Dance with Dragons", &pool)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), STARRED_QUOTES_COUNT);
+
+    let quotes = queries::find_quotes_by_book_title("starred", "A Game of Thrones", &pool)
+        .await
+        .unwrap();
+    assert!(quotes.is_empty());
+
+    let quotes = queries::find_quotes_by_author_name("starred", "George R.R. Martin", &pool)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), STARRED_QUOTES_COUNT);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_trash_and_restore_book(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    // Quote deleted on its own before the book
+    queries::delete_quote(&test_data.quote.id, &mut *conn).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    queries::delete_book(&test_data.book.id, &mut *conn).await.unwrap();
+
+    let quotes = queries::get_all_quotes_by_book_id(&test_data.book.id, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert!(quotes.is_empty());
+    assert!(queries::get_notes_by_book(&test_data.book.id, &mut *conn).await.unwrap().is_empty());
+
+    // Only the quote deleted on its own is listed, the others are hidden with the book
+    let trashed_quotes = queries::get_trashed_quotes(&mut *conn).await.unwrap();
+    assert_eq!(trashed_quotes.len(), 1);
+    assert_eq!(trashed_quotes[0].id, test_data.quote.id);
+    let trashed_books = queries::get_trashed_books(&mut *conn).await.unwrap();
+    assert_eq!(trashed_books.len(), 1);
+
+    queries::restore_book(&test_data.book.id, &mut *conn).await.unwrap();
+    let quotes = queries::get_all_quotes_by_book_id(&test_data.book.id, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), 4);
+    assert_eq!(queries::get_notes_by_book(&test_data.book.id, &mut *conn).await.unwrap().len(), 1);
+    assert!(queries::get_trashed_books(&mut *conn).await.unwrap().is_empty());
+
+    queries::restore_quote(&test_data.quote.id, &mut *conn).await.unwrap();
+    assert!(queries::get_trashed_quotes(&mut *conn).await.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_trash_and_restore_author(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    queries::delete_author(test_data.author.id.clone(), &mut *conn).await.unwrap();
+    assert!(queries::get_books(&mut *conn).await.unwrap().is_empty());
+    assert_eq!(queries::get_trashed_authors(&mut *conn).await.unwrap().len(), 1);
+    assert!(queries::get_trashed_books(&mut *conn).await.unwrap().is_empty());
+
+    queries::restore_author(&test_data.author.id, &mut *conn).await.unwrap();
+    assert_eq!(queries::get_books(&mut *conn).await.unwrap().len(), 1);
+    let quotes = queries::get_all_quotes_by_book_id(&test_data.book.id, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), 5);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_purge_book(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    let tags = queries::get_tags(&pool).await.unwrap();
+    queries::insert_quote_tag(&test_data.quote.id, &tags[0].id, &pool)
+        .await
+        .unwrap();
+
+    let mut conn = pool.acquire().await.unwrap();
+
+    // Only trashed books can be purged
+    assert!(queries::purge_book(&test_data.book.id, &mut conn).await.is_err());
+
+    queries::delete_book(&test_data.book.id, &mut *conn).await.unwrap();
+    queries::purge_book(&test_data.book.id, &mut conn).await.unwrap();
+
+    for table in ["book", "chapter", "quote", "note", "quote_tag", "quote_fts"] {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 0, "{}", table);
+    }
+    assert_eq!(queries::get_authors(&mut *conn).await.unwrap().len(), 1);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_purge_trash(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    queries::delete_quote(&test_data.quote.id, &mut *conn).await.unwrap();
+    queries::delete_author(test_data.author.id.clone(), &mut *conn).await.unwrap();
+
+    // Nothing is old enough yet
+    let purged = queries::purge_trash(Some(30), &mut conn).await.unwrap();
+    assert_eq!(purged, 0);
+
+    let purged = queries::purge_trash(None, &mut conn).await.unwrap();
+    assert_eq!(purged, 1);
+    assert!(queries::get_trashed_quotes(&mut *conn).await.unwrap().is_empty());
+    assert!(queries::get_trashed_authors(&mut *conn).await.unwrap().is_empty());
+    let quotes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM quote")
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(quotes, 0);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_settings(pool: SqlitePool) {
+    assert_eq!(queries::get_setting("key", &pool).await.unwrap(), None);
+
+    queries::set_setting("key", Some("1"), &pool).await.unwrap();
+    queries::set_setting("key", Some("2"), &pool).await.unwrap();
+    assert_eq!(queries::get_setting("key", &pool).await.unwrap(), Some("2".to_string()));
+
+    queries::set_setting("key", None, &pool).await.unwrap();
+    assert_eq!(queries::get_setting("key", &pool).await.unwrap(), None);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_record_search(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+
+    assert!(queries::record_search("   ", &mut conn).await.unwrap().is_none());
+
+    let entry = queries::record_search("  thousand   lives ", &mut conn).await.unwrap().unwrap();
+    assert_eq!(entry.query, "thousand lives");
+    assert_eq!(entry.frequency, 1);
+
+    // Repeated right away, e.g. by the book and author searches, it's the same search
+    let entry = queries::record_search("thousand lives", &mut conn).await.unwrap().unwrap();
+    assert_eq!(entry.frequency, 1);
+
+    sqlx::query("UPDATE search_history SET timestamp = '2020-01-01 00:00:00.000'")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    let entry = queries::record_search("thousand lives", &mut conn).await.unwrap().unwrap();
+    assert_eq!(entry.frequency, 2);
+
+    let history = queries::get_recent_searches(10, &mut *conn).await.unwrap();
+    assert_eq!(history.len(), 1);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_search_history(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+
+    for query in ["reader", "dragons", "winter"] {
+        queries::record_search(query, &mut conn).await.unwrap();
+    }
+    sqlx::query("UPDATE search_history SET timestamp = '2020-01-01 00:00:00.000' WHERE query = 'reader'")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    queries::record_search("reader", &mut conn).await.unwrap();
+
+    let recent = queries::get_recent_searches(2, &mut *conn).await.unwrap();
+    let queries: Vec<_> = recent.iter().map(|s| s.query.as_str()).collect();
+    assert_eq!(queries, ["reader", "winter"]);
+
+    let frequent = queries::get_frequent_searches(10, &mut *conn).await.unwrap();
+    assert_eq!(frequent.len(), 3);
+    assert_eq!(frequent[0].query, "reader");
+    assert_eq!(frequent[0].frequency, 2);
+
+    queries::delete_search(frequent[0].id, &mut *conn).await.unwrap();
+    assert_eq!(queries::get_recent_searches(10, &mut *conn).await.unwrap().len(), 2);
+
+    queries::clear_search_history(&mut *conn).await.unwrap();
+    assert!(queries::get_recent_searches(10, &mut *conn).await.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_search_history_limit(pool: SqlitePool) {
+    let mut conn = pool.acquire().await.unwrap();
+
+    for i in 0..queries::SEARCH_HISTORY_LIMIT + 5 {
+        queries::record_search(&format!("query {}", i), &mut conn).await.unwrap();
+    }
+
+    let history = queries::get_recent_searches(1000, &mut *conn).await.unwrap();
+    assert_eq!(history.len() as i64, queries::SEARCH_HISTORY_LIMIT);
+    assert!(history.iter().all(|s| s.query != "query 0"));
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_smart_collection(pool: SqlitePool) {
+    let mut filter = QuoteFilter {
+        starred: Some(true),
+        ..Default::default()
+    };
+    let collection = queries::insert_smart_collection("Starred", &filter, Some("date_created"), Some("asc"), &pool)
+        .await
+        .unwrap();
+    assert_eq!(collection.filter.starred, Some(true));
+    assert_eq!(collection.sort_by.as_deref(), Some("date_created"));
+
+    filter.text = Some("thousand".to_string());
+    let mut conn = pool.acquire().await.unwrap();
+    let collection = queries::update_smart_collection(&collection.id, "Thousand", &filter, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(collection.name, "Thousand");
+    assert_eq!(collection.filter.text.as_deref(), Some("thousand"));
+    assert_eq!(collection.sort_by, None);
+
+    let collections = queries::get_smart_collections(&mut *conn).await.unwrap();
+    assert_eq!(collections.len(), 1);
+
+    queries::delete_smart_collection(&collection.id, &mut *conn).await.unwrap();
+    assert!(queries::get_smart_collections(&mut *conn).await.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_query_quotes(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    let tags = queries::get_tags(&pool).await.unwrap();
+    queries::insert_quote_tag(&test_data.quote.id, &tags[0].id, &pool)
+        .await
+        .unwrap();
+
+    let mut conn = pool.acquire().await.unwrap();
+    let mut filter = QuoteFilter {
+        starred: Some(true),
+        ..Default::default()
+    };
+    let quotes = queries::query_quotes(&filter, Some("chapter_progress"), Some("asc"), None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), STARRED_QUOTES_COUNT);
+    assert_eq!(quotes[0].content, Some("0 - This is a starred quote.".to_string()));
+
+    filter.text = Some("starred NOT 1".to_string());
+    filter.book_id = Some(test_data.book.id.clone());
+    filter.author_id = Some(test_data.author.id.clone());
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), STARRED_QUOTES_COUNT - 1);
+
+    let filter = QuoteFilter {
+        tag_ids: vec![tags[0].id.clone(), tags[1].id.clone()],
+        starred: Some(false),
+        text: Some("reader".to_string()),
+        ..Default::default()
+    };
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0].id, test_data.quote.id);
+    assert_eq!(quotes[0].tags.len(), 1);
+
+    // Books added in the future
+    let filter = QuoteFilter {
+        book_created_from: Some(Utc::now().naive_utc() + chrono::Duration::days(1)),
+        ..Default::default()
+    };
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert!(quotes.is_empty());
+
+    let filter = QuoteFilter {
+        text: Some("\"\"".to_string()),
+        ..Default::default()
+    };
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn)
+        .await
+        .unwrap();
+    assert!(quotes.is_empty());
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_query_quotes_facets(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    let tags = queries::get_tags(&pool).await.unwrap();
+    let starred = queries::get_starred_quotes(None, None, &pool).await.unwrap();
+    for tag in &tags[..2] {
+        queries::insert_quote_tag(&test_data.quote.id, &tag.id, &pool).await.unwrap();
+    }
+    queries::insert_quote_tag(&starred[0].id, &tags[0].id, &pool).await.unwrap();
+
+    let mut conn = pool.acquire().await.unwrap();
+    let mut filter = QuoteFilter {
+        tag_ids: vec![tags[0].id.clone(), tags[1].id.clone()],
+        ..Default::default()
+    };
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn).await.unwrap();
+    assert_eq!(quotes.len(), 2);
+
+    filter.tag_match = TagMatch::All;
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn).await.unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0].id, test_data.quote.id);
+    assert_eq!(quotes[0].tags.len(), 2);
+
+    let filter = QuoteFilter {
+        chapter_id: Some(test_data.chapter.id.clone()),
+        created_to: Some(Utc::now().naive_utc() + chrono::Duration::days(1)),
+        imported_from: Some(test_data.quote.imported_at.unwrap()),
+        ..Default::default()
+    };
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn).await.unwrap();
+    assert_eq!(quotes.len(), STARRED_QUOTES_COUNT + 1);
+
+    // Paging
+    let filter = QuoteFilter::default();
+    let all = queries::query_quotes(&filter, Some("chapter_progress"), Some("asc"), None, None, &mut *conn)
+        .await
+        .unwrap();
+    let page = queries::query_quotes(&filter, Some("chapter_progress"), Some("asc"), Some(2), Some(1), &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(page.len(), 2);
+    assert_eq!(page[0].id, all[1].id);
+    assert_eq!(page[1].id, all[2].id);
+
+    // Deleted state
+    queries::delete_quote(&test_data.quote.id, &mut *conn).await.unwrap();
+    let mut filter = QuoteFilter {
+        deleted: DeletedState::Deleted,
+        ..Default::default()
+    };
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn).await.unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0].id, test_data.quote.id);
+
+    filter.deleted = DeletedState::All;
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn).await.unwrap();
+    assert_eq!(quotes.len(), all.len());
+
+    filter.deleted = DeletedState::Active;
+    let quotes = queries::query_quotes(&filter, None, None, None, None, &mut *conn).await.unwrap();
+    assert_eq!(quotes.len(), all.len() - 1);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_get_books_and_authors_page(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    for title in ["b", "B", "a", "c"] {
+        queries::insert_book_with_defaults(title.to_string(), Some(test_data.author.id.clone()), None, &pool)
+            .await
+            .unwrap();
+    }
+
+    let mut conn = pool.acquire().await.unwrap();
+    let books = queries::get_books(&mut *conn).await.unwrap();
+    let mut cursor = None;
+    let mut paged = Vec::new();
+    loop {
+        let page = queries::get_books_page(cursor.as_deref(), 2, &mut conn).await.unwrap();
+        assert_eq!(page.total, 5);
+        assert!(page.items.len() <= 2);
+        paged.extend(page.items);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(paged.len(), books.len());
+    let titles: Vec<_> = paged.iter().map(|b| b.title.to_lowercase()).collect();
+    let mut sorted = titles.clone();
+    sorted.sort();
+    assert_eq!(titles, sorted);
+
+    let page = queries::get_authors_page(None, 10, &mut conn).await.unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.items[0].id, test_data.author.id);
+    assert!(page.next_cursor.is_none());
+
+    assert!(queries::get_books_page(Some("not a cursor"), 2, &mut conn).await.is_err());
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_get_all_quotes_by_book_id_page(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    for order in ["asc", "desc"] {
+        let all = queries::get_all_quotes_by_book_id(&test_data.book.id, Some("chapter_progress"), Some(order), &mut *conn)
+            .await
+            .unwrap();
+
+        let mut cursor = None;
+        let mut paged = Vec::new();
+        loop {
+            let page = queries::get_all_quotes_by_book_id_page(
+                &test_data.book.id,
+                Some("chapter_progress"),
+                Some(order),
+                cursor.as_deref(),
+                2,
+                &mut conn,
+            )
+            .await
+            .unwrap();
+            assert_eq!(page.total as usize, all.len());
+            paged.extend(page.items);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        let progress = |quotes: &[liture_notes_lib::models::QuoteWithTags]| {
+            quotes.iter().map(|q| q.chapter_progress).collect::<Vec<_>>()
+        };
+        assert_eq!(paged.len(), all.len());
+        assert_eq!(progress(&paged), progress(&all));
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_get_starred_quotes_and_quotes_by_tag_page(pool: SqlitePool) {
+    let test_data = init_db(&pool).await.unwrap();
+    let tags = queries::get_tags(&pool).await.unwrap();
+    queries::insert_quote_tag(&test_data.quote.id, &tags[0].id, &pool)
+        .await
+        .unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    let first = queries::get_starred_quotes_page(Some("chapter_progress"), Some("desc"), None, 2, &mut conn)
+        .await
+        .unwrap();
+    assert_eq!(first.total as usize, STARRED_QUOTES_COUNT);
+    assert_eq!(first.items.len(), 2);
+    assert_eq!(first.items[0].content, Some("2 - This is a starred quote.".to_string()));
+
+    let second = queries::get_starred_quotes_page(
+        Some("chapter_progress"),
+        Some("desc"),
+        first.next_cursor.as_deref(),
+        2,
+        &mut conn,
+    )
+    .await
+    .unwrap();
+    assert_eq!(second.items.len(), 1);
+    assert_eq!(second.items[0].content, Some("0 - This is a starred quote.".to_string()));
+    assert!(second.next_cursor.is_none());
+
+    let page = queries::get_quotes_by_tag_page(&tags[0].id, None, None, None, 10, &mut conn)
+        .await
+        .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.items[0].id, test_data.quote.id);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn test_insert_returning_releases_the_write_lock(pool: SqlitePool) {
+    // A transaction that reads before writing fails at once with "database is locked" when
+    // another connection still holds the write lock, e.g. the previous `INSERT ... RETURNING`
+    // when its statement was not run to the end. It only shows now and then, hence the loop.
+    for i in 0..500 {
+        queries::insert_author(format!("Author {}", i), &pool).await.unwrap();
+
+        let mut tx = pool.begin().await.unwrap();
+        queries::get_author_by_name(format!("Author {}", i), &mut *tx).await.unwrap();
+        queries::insert_author(format!("Other {}", i), &mut *tx).await.unwrap();
+        tx.commit().await.unwrap();
+    }
+}
@@ -28,7 +28,9 @@ import {
   QuoteRedux,
   SearchResults,
   Chapter,
-  Tag
+  Tag,
+  DbError,
+  ImportReport
 } from "@customTypes/index.ts";
 import { useQuoteStore, useAppStore } from "@stores/index";
 import TagScreen from "@screens/TagScreen";
@@ -599,9 +601,23 @@ function App() {
 
   // App startup loading
   useEffect(() => {
-    fetchBooksAndAuthors();
-    quoteStore.fetchStarredQuotes();
-    tagStore.fetchTags();
+    invoke<DbError | null>("get_db_init_error").then((dbError) => {
+      if (dbError) {
+        addToast(dbError.message, "error");
+        return;
+      }
+      fetchBooksAndAuthors();
+      quoteStore.fetchStarredQuotes();
+      tagStore.fetchTags();
+    });
+
+    const dbErrorListener = listen("db-error", (event: { payload: DbError }) => {
+      addToast(event.payload.message, "error");
+    });
+
+    return () => {
+      dbErrorListener.then((unlisten) => unlisten());
+    };
   }, []);
 
   useEffect(() => {
@@ -610,7 +626,7 @@ function App() {
       addToast(`Importing data from ${event.payload.device}...`);
     });
 
-    const importSuccessListener = listen("import-success", (event: { payload: { message: string } }) => {
+    const importSuccessListener = listen("import-success", (event: { payload: { message: string, report: ImportReport } }) => {
       addToast(event.payload.message, "success");
       fetchBooksAndAuthors();
       quoteStore.fetchStarredQuotes();
@@ -620,10 +636,36 @@ function App() {
       addToast(event.payload.message, "error");
     });
 
+    const importCancelledListener = listen("import-cancelled", (event: { payload: { message: string } }) => {
+      addToast(event.payload.message);
+    });
+
     return () => {
       importListener.then((unlisten) => unlisten());
       importSuccessListener.then((unlisten) => unlisten());
       importErrorListener.then((unlisten) => unlisten());
+      importCancelledListener.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  useEffect(() => {
+    // Setup export listeners - has to return for cleanup
+    const exportListener = listen("exporting", () => {
+      addToast("Exporting library...");
+    });
+
+    const exportSuccessListener = listen("export-success", (event: { payload: { message: string } }) => {
+      addToast(event.payload.message, "success");
+    });
+
+    const exportErrorListener = listen("export-error", (event: { payload: { message: string } }) => {
+      addToast(event.payload.message, "error");
+    });
+
+    return () => {
+      exportListener.then((unlisten) => unlisten());
+      exportSuccessListener.then((unlisten) => unlisten());
+      exportErrorListener.then((unlisten) => unlisten());
     };
   }, []);
 
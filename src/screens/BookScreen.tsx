@@ -71,6 +71,11 @@ function BookScreen({
       deleted_at: null,
       imported_at: null,
       original_id: null,
+      color: null,
+      page_start: null,
+      page_end: null,
+      location_start: null,
+      location_end: null,
     });
   };
 
@@ -16,5 +16,9 @@ export function convertStarredQuoteToQuote(starredQuote: types.QuoteRedux): type
     chapter_progress: null,
     imported_at: null,
     original_id: null,
+    page_start: null,
+    page_end: null,
+    location_start: null,
+    location_end: null,
   };
 }
@@ -7,6 +7,7 @@ export interface Book {
   updated_at: string;
   deleted_at: string | null;
   original_id: string | null;
+  import_batch_id?: string | null;
 }
 
 export interface Author {
@@ -16,6 +17,7 @@ export interface Author {
   updated_at: string;
   deleted_at: string | null;
   original_id: string | null;
+  import_batch_id?: string | null;
 }
 
 export interface Quote {
@@ -33,6 +35,12 @@ export interface Quote {
   chapter_id: string | null;
   chapter_progress: number | null;
   original_id: string | null;
+  color: string | null;
+  page_start: number | null;
+  page_end: number | null;
+  location_start: number | null;
+  location_end: number | null;
+  import_batch_id?: string | null;
 }
 
 export interface Chapter {
@@ -44,6 +52,7 @@ export interface Chapter {
   deleted_at: string | null;
   original_id: string | null;
   volume_index: number | null;
+  import_batch_id?: string | null;
 }
 
 export interface QuoteRedux {
@@ -54,6 +63,7 @@ export interface QuoteRedux {
   author_id: string | null;
   author_name: string | null;
   starred: number | null;
+  color: string | null;
   created_at: string;
   updated_at: string;
 }
@@ -75,12 +85,28 @@ export interface Note {
   created_at: string;
   updated_at: string;
   deleted_at: string | null;
+  import_batch_id?: string | null;
+}
+
+export type BookmarkKind = "bookmark" | "dogear";
+
+export interface Bookmark {
+  id: string;
+  book_id: string | null;
+  chapter_id: string | null;
+  chapter_progress: number | null;
+  kind: BookmarkKind;
+  created_at: string;
+  updated_at: string;
+  original_id: string | null;
+  import_batch_id?: string | null;
 }
 
 export interface Tag {
   id: string;
   name: string;
   color?: string;
+  import_batch_id?: string | null;
 }
 
 export interface QuoteWithTags extends Quote {
@@ -91,6 +117,61 @@ export interface QuoteWithTagsRedux extends QuoteRedux {
   tags: Tag[];
 }
 
+export type TagMatch = "any" | "all";
+
+export type DeletedState = "active" | "deleted" | "all";
+
+export interface QuoteFilter {
+  text?: string | null;
+  starred?: boolean | null;
+  tag_ids?: string[];
+  tag_match?: TagMatch;
+  book_id?: string | null;
+  author_id?: string | null;
+  chapter_id?: string | null;
+  colors?: string[];
+  book_created_from?: string | null;
+  book_created_to?: string | null;
+  created_from?: string | null;
+  created_to?: string | null;
+  imported_from?: string | null;
+  imported_to?: string | null;
+  deleted?: DeletedState;
+}
+
+export interface SmartCollection {
+  id: string;
+  name: string;
+  filter: QuoteFilter;
+  sort_by: string | null;
+  sort_order: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface Page<T> {
+  items: T[];
+  next_cursor: string | null;
+  total: number;
+}
+
+export type AnkiNoteType = "quote" | "cloze";
+
+export type RestoreMode = "replace" | "merge";
+
+export interface RestoreSummary {
+  created: number;
+  updated: number;
+  skipped: number;
+}
+
+export interface SearchHistory {
+  id: number;
+  query: string;
+  timestamp: string;
+  frequency: number;
+}
+
 export interface QuoteFts {
   id: string;
   content: string | null;
@@ -106,9 +187,17 @@ export interface QuoteFts {
   author_id: string | null;
   book_title: string | null;
   author_name: string | null;
+  rank: number;
+  snippet: string | null;
+  highlight: string | null;
   tags: Tag[];
 }
 
+export interface DbError {
+  kind: string;
+  message: string;
+}
+
 export interface SortOption {
   field: 'title' | 'createdAt' | 'updatedAt';
   direction: 'asc' | 'desc';
@@ -160,3 +249,91 @@ export interface NewBookData {
   authorId: string | null;
   authorName: string | null;
 }
+
+export type ImportSource = "kobo" | "kindle" | "ibooks" | "readwise" | "koreader" | "calibre";
+
+export interface ImportCounts {
+  created: number;
+  updated: number;
+  skipped: number;
+}
+
+export interface ImportIssue {
+  level: "warning" | "error" | "info";
+  location: string;
+  message: string;
+}
+
+export interface ImportReport {
+  books: ImportCounts;
+  authors: ImportCounts;
+  chapters: ImportCounts;
+  quotes: ImportCounts;
+  notes: ImportCounts;
+  bookmarks: ImportCounts;
+  issues: ImportIssue[];
+}
+
+export interface ImportBatch {
+  id: string;
+  source: ImportSource;
+  path: string;
+  file_hash: string | null;
+  report: ImportReport;
+  created_at: string;
+}
+
+export type ImportPhase = "reading" | "books" | "quotes";
+
+export interface ImportProgress {
+  id: string;
+  phase: ImportPhase;
+  processed: number;
+  total: number;
+  book: string | null;
+}
+
+export type PreviewStatus = "new" | "duplicate" | "changed" | "newer";
+
+export interface PreviewQuote {
+  key: string;
+  content: string | null;
+  note: string | null;
+  status: PreviewStatus;
+}
+
+export interface PreviewChapter {
+  title: string | null;
+  quotes: PreviewQuote[];
+}
+
+export interface PreviewBook {
+  key: string;
+  title: string;
+  status: PreviewStatus;
+  chapters: PreviewChapter[];
+}
+
+export interface PreviewAuthor {
+  name: string | null;
+  books: PreviewBook[];
+}
+
+export interface IBooksDatabases {
+  annotation: string;
+  asset: string;
+}
+
+export type ImportInput = string | IBooksDatabases;
+
+export interface ImportPreview {
+  source: ImportSource;
+  input: ImportInput;
+  authors: PreviewAuthor[];
+  report: ImportReport;
+}
+
+export interface ImportSelection {
+  books: string[];
+  quotes: string[];
+}